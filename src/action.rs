@@ -0,0 +1,187 @@
+//! # GitHub Actions runtime
+//!
+//! Helpers for structuring a Rust action with `pre` and `post` steps, e.g. a
+//! cache action which restores in the `main` step and saves in the `post`
+//! step, similar to [@actions/cache](https://github.com/actions/cache).
+//!
+//! The same binary runs in every step and detects the current [`Phase`] from
+//! the state saved by previous steps:
+//!
+//! ```rust,no_run
+//! use gha_toolkit::action::{self, CacheStep};
+//! use gha_toolkit::cache::CacheClient;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let client = CacheClient::from_env()?
+//!     .cache_from(["cargo-registry-"].into_iter())
+//!     .cache_to("cargo-registry-1234")
+//!     .build()?;
+//!
+//! let step = CacheStep::new(client, "cargo-registry", "registry.tar");
+//! step.run(action::start(false)?).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{info, instrument, warn};
+
+use crate::cache::CacheClient;
+use crate::core;
+use crate::Result;
+
+const STATE_IS_PRE: &str = "isPre";
+const STATE_IS_POST: &str = "isPost";
+const STATE_CACHE_MATCHED_KEY: &str = "cacheMatchedKey";
+
+/// Step of an action run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// `pre` step, run before the job steps.
+    Pre,
+
+    /// `main` step.
+    Main,
+
+    /// `post` step, run after the job steps.
+    Post,
+}
+
+impl Phase {
+    /// Detects the current phase from the state saved by [`start`].
+    ///
+    /// Set `has_pre` if the action defines a `pre` step. The `pre` step is
+    /// then detected by the absence of state, so it must not be skipped with
+    /// `pre-if`.
+    pub fn detect(has_pre: bool) -> Self {
+        if core::get_state(STATE_IS_POST) == "true" {
+            Self::Post
+        } else if has_pre && core::get_state(STATE_IS_PRE) != "true" {
+            Self::Pre
+        } else {
+            Self::Main
+        }
+    }
+}
+
+/// Detects the current [`Phase`] and saves state for detecting the next one.
+///
+/// See [`Phase::detect`].
+pub fn start(has_pre: bool) -> Result<Phase> {
+    let phase = Phase::detect(has_pre);
+    match phase {
+        Phase::Pre => core::save_state(STATE_IS_PRE, "true")?,
+        Phase::Main => core::save_state(STATE_IS_POST, "true")?,
+        Phase::Post => {}
+    }
+    Ok(phase)
+}
+
+/// Saves `value` as JSON state for the following steps.
+///
+/// See [`core::save_state`].
+pub fn save_state<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    core::save_state(name, &serde_json::to_string(value)?)
+}
+
+/// Gets JSON state saved with [`save_state`] by a previous step.
+pub fn get_state<T: DeserializeOwned>(name: &str) -> Result<Option<T>> {
+    let value = core::get_state(name);
+    if value.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&value)?))
+}
+
+/// Restores a cache entry into a file in the `main` step and saves the file in
+/// the `post` step, unless it was restored from the exact
+/// [`cache_to`][CacheClient::cache_to] key.
+///
+/// Sets the `cache-hit` output of the `main` step.
+///
+/// See [module][self] documentation.
+pub struct CacheStep {
+    client: CacheClient,
+    scope: String,
+    path: PathBuf,
+}
+
+impl CacheStep {
+    /// Creates a new [`CacheStep`] caching the file at `path` as the given
+    /// `scope`, see [`CacheClient::entry`].
+    pub fn new<S: Into<String>, P: Into<PathBuf>>(client: CacheClient, scope: S, path: P) -> Self {
+        Self {
+            client,
+            scope: scope.into(),
+            path: path.into(),
+        }
+    }
+
+    /// Restores in the [`Phase::Main`] step and saves in the [`Phase::Post`]
+    /// step.
+    pub async fn run(&self, phase: Phase) -> Result<()> {
+        match phase {
+            Phase::Pre => Ok(()),
+            Phase::Main => self.restore().await.map(|_| ()),
+            Phase::Post => self.save().await,
+        }
+    }
+
+    /// Restores the cache entry into the file. Returns `true` if the entry
+    /// was restored from the exact [`cache_to`][CacheClient::cache_to] key.
+    #[instrument(skip(self), fields(scope = %self.scope, path = %self.path.display()))]
+    pub async fn restore(&self) -> Result<bool> {
+        let entry = match self.client.entry(&self.scope).await? {
+            Some(entry) => entry,
+            None => {
+                info!("Cache not found");
+                core::set_output("cache-hit", "false")?;
+                return Ok(false);
+            }
+        };
+
+        if let Some(url) = &entry.archive_location {
+            let data = self.client.get(url).await?;
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&self.path, data)?;
+        }
+
+        let matched_key = entry.cache_key.unwrap_or_default();
+        info!("Cache restored from key: {matched_key}");
+        core::save_state(STATE_CACHE_MATCHED_KEY, &matched_key)?;
+
+        let exact_match = self.client.cache_to() == Some(matched_key.as_str());
+        core::set_output("cache-hit", if exact_match { "true" } else { "false" })?;
+        Ok(exact_match)
+    }
+
+    /// Saves the file as the cache entry.
+    #[instrument(skip(self), fields(scope = %self.scope, path = %self.path.display()))]
+    pub async fn save(&self) -> Result<()> {
+        let cache_to = match self.client.cache_to() {
+            Some(cache_to) => cache_to,
+            None => return Ok(()),
+        };
+
+        if core::get_state(STATE_CACHE_MATCHED_KEY) == cache_to {
+            info!("Cache hit occurred on the primary key {cache_to}, not saving cache.");
+            return Ok(());
+        }
+
+        if !self.path.exists() {
+            warn!(
+                "Path does not exist, not saving cache: {}",
+                self.path.display()
+            );
+            return Ok(());
+        }
+
+        self.client.put(&self.scope, File::open(&self.path)?).await
+    }
+}
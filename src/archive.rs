@@ -0,0 +1,509 @@
+//! # Archive extraction
+//!
+//! Pure Rust extraction of the archives tools and caches are distributed in,
+//! like `extractTar`, `extractZip` and `extract7z` of
+//! [@actions/tool-cache](https://github.com/actions/toolkit/tree/main/packages/tool-cache)
+//! but without spawning `tar`, `unzip` or `7z`:
+//!
+//! ```rust,no_run
+//! use gha_toolkit::archive::{self, ExtractOptions};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let options = ExtractOptions {
+//!     strip_components: 1,
+//!     ..Default::default()
+//! };
+//! let dir = archive::extract_tar("node-v18.12.1-linux-x64.tar.xz", None, &options)?;
+//! println!("Extracted to {}", dir.display());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Tar archives may be uncompressed or compressed with gzip, xz or zstd,
+//! which is detected from the archive. Extraction preserves permissions,
+//! modification times and symbolic links. Entries are never written outside
+//! of the destination directory: absolute paths and `..` components are
+//! rejected with [`Error::InvalidArchivePath`], as are symbolic links
+//! pointing outside of the destination and writes through such links.
+//!
+//! Extracting 7z archives requires the `sevenz` feature.
+
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use filetime::FileTime;
+use flate2::read::GzDecoder;
+use tracing::{debug, instrument};
+
+use crate::datetime::DateTime;
+use crate::temp::{self, TempFile};
+use crate::{Error, Result};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+#[cfg(unix)]
+const S_IFMT: u32 = 0o170_000;
+#[cfg(unix)]
+const S_IFLNK: u32 = 0o120_000;
+
+/// Compression of a tar archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarCompression {
+    /// Uncompressed tar archive.
+    None,
+
+    /// Gzip compressed tar archive, e.g. `.tar.gz` or `.tgz`.
+    Gzip,
+
+    /// XZ compressed tar archive, e.g. `.tar.xz`.
+    Xz,
+
+    /// Zstandard compressed tar archive, e.g. `.tar.zst`.
+    Zstd,
+}
+
+impl TarCompression {
+    /// Detects the compression from the first bytes of an archive.
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(GZIP_MAGIC) {
+            Self::Gzip
+        } else if header.starts_with(XZ_MAGIC) {
+            Self::Xz
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+}
+
+/// Options for extracting archives.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    /// Number of leading path components removed from entries, like
+    /// `tar --strip-components`. Entries with fewer components are skipped.
+    pub strip_components: usize,
+
+    /// Compression of tar archives. Detected from the archive by default.
+    pub compression: Option<TarCompression>,
+}
+
+/// Extracts the tar archive `file` and returns the destination directory.
+///
+/// `dest` defaults to a new directory in `RUNNER_TEMP`.
+#[instrument(skip(file), fields(file = %file.as_ref().display()))]
+pub fn extract_tar<P: AsRef<Path>>(
+    file: P,
+    dest: Option<&Path>,
+    options: &ExtractOptions,
+) -> Result<PathBuf> {
+    let dest = dest_dir(dest);
+    extract_tar_from(File::open(file)?, &dest, options)?;
+    Ok(dest)
+}
+
+/// Extracts a tar archive read from `reader` into `dest`, e.g. a cache
+/// archive downloaded into memory.
+pub fn extract_tar_from<R: Read>(reader: R, dest: &Path, options: &ExtractOptions) -> Result<()> {
+    let mut reader = BufReader::new(reader);
+    let compression = match options.compression {
+        Some(compression) => compression,
+        None => TarCompression::detect(reader.fill_buf()?),
+    };
+    debug!("Extracting tar archive with compression {compression:?}");
+
+    match compression {
+        TarCompression::None => unpack_tar(reader, dest, options),
+        TarCompression::Gzip => unpack_tar(GzDecoder::new(reader), dest, options),
+        TarCompression::Xz => {
+            // The XZ decoder isn't a reader, so the tar archive is stored
+            let mut temp = TempFile::create(".tar")?;
+            lzma_rs::xz_decompress(&mut reader, &mut temp.file)
+                .map_err(|err| invalid_data(err.to_string()))?;
+            temp.file.seek(SeekFrom::Start(0))?;
+            unpack_tar(BufReader::new(&temp.file), dest, options)
+        }
+        TarCompression::Zstd => {
+            let decoder = ruzstd::StreamingDecoder::new(reader)
+                .map_err(|err| invalid_data(err.to_string()))?;
+            unpack_tar(decoder, dest, options)
+        }
+    }
+}
+
+/// Extracts the ZIP archive `file` and returns the destination directory.
+///
+/// `dest` defaults to a new directory in `RUNNER_TEMP`.
+#[instrument(skip(file), fields(file = %file.as_ref().display()))]
+pub fn extract_zip<P: AsRef<Path>>(
+    file: P,
+    dest: Option<&Path>,
+    options: &ExtractOptions,
+) -> Result<PathBuf> {
+    let dest = dest_dir(dest);
+    let mut archive =
+        zip::ZipArchive::new(BufReader::new(File::open(file)?)).map_err(io::Error::from)?;
+    let mut extractor = Extractor::new(&dest, options)?;
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(io::Error::from)?;
+        let path = match extractor.path(Path::new(&entry.name().replace('\\', "/")))? {
+            Some(path) => path,
+            None => continue,
+        };
+
+        let modified = entry.last_modified();
+        let mtime = DateTime {
+            year: i64::from(modified.year()),
+            month: u32::from(modified.month()),
+            day: u32::from(modified.day()),
+            hour: u32::from(modified.hour()),
+            minute: u32::from(modified.minute()),
+            second: u32::from(modified.second()),
+        }
+        .to_unix();
+        let mtime = FileTime::from_unix_time(mtime, 0);
+        let mode = entry.unix_mode();
+
+        if entry.is_dir() {
+            extractor.directory(&path, mtime, mode)?;
+            continue;
+        }
+
+        #[cfg(unix)]
+        if mode.map_or(false, |mode| mode & S_IFMT == S_IFLNK) {
+            let mut target = String::new();
+            entry.read_to_string(&mut target)?;
+            extractor.symlink(&path, Path::new(&target))?;
+            continue;
+        }
+
+        extractor.file(&path, &mut entry, mtime, mode)?;
+    }
+
+    extractor.finish()?;
+    Ok(dest)
+}
+
+/// Extracts the 7z archive `file` and returns the destination directory.
+///
+/// `dest` defaults to a new directory in `RUNNER_TEMP`.
+#[cfg(feature = "sevenz")]
+#[instrument(skip(file), fields(file = %file.as_ref().display()))]
+pub fn extract_7z<P: AsRef<Path>>(
+    file: P,
+    dest: Option<&Path>,
+    options: &ExtractOptions,
+) -> Result<PathBuf> {
+    // Seconds between 1601-01-01, the epoch of Windows file times, and 1970-01-01
+    const WINDOWS_EPOCH_OFFSET: i64 = 11_644_473_600;
+    // Windows attribute flag for Unix permissions in the high 16 bits
+    const UNIX_EXTENSION: u32 = 0x8000;
+
+    let dest = dest_dir(dest);
+    let mut extractor = Extractor::new(&dest, options)?;
+
+    let mut error = None;
+    let result =
+        sevenz_rust::decompress_file_with_extract_fn(file.as_ref(), &dest, |entry, reader, _| {
+            let result = (|| {
+                let path = match extractor.path(Path::new(&entry.name().replace('\\', "/")))? {
+                    Some(path) => path,
+                    None => {
+                        return io::copy(reader, &mut io::sink())
+                            .map(drop)
+                            .map_err(Error::from)
+                    }
+                };
+
+                let mtime = if entry.has_last_modified_date {
+                    let secs = (entry.last_modified_date().to_raw() / 10_000_000) as i64;
+                    FileTime::from_unix_time(secs - WINDOWS_EPOCH_OFFSET, 0)
+                } else {
+                    FileTime::now()
+                };
+                let attributes = entry.windows_attributes();
+                let mode = if entry.has_windows_attributes && attributes & UNIX_EXTENSION != 0 {
+                    Some(attributes >> 16)
+                } else {
+                    None
+                };
+
+                if entry.is_directory() {
+                    return extractor.directory(&path, mtime, mode);
+                }
+
+                #[cfg(unix)]
+                if mode.map_or(false, |mode| mode & S_IFMT == S_IFLNK) {
+                    let mut target = String::new();
+                    reader.read_to_string(&mut target)?;
+                    return extractor.symlink(&path, Path::new(&target));
+                }
+
+                extractor.file(&path, reader, mtime, mode)
+            })();
+
+            match result {
+                Ok(()) => Ok(true),
+                Err(err) => {
+                    error = Some(err);
+                    Err(sevenz_rust::Error::other("extraction failed"))
+                }
+            }
+        });
+
+    if let Some(err) = error {
+        return Err(err);
+    }
+    result.map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+
+    extractor.finish()?;
+    Ok(dest)
+}
+
+/// Gets the relative path of an archive entry, or `None` if it's absolute or
+/// has `..` components, which could escape the destination directory.
+pub(crate) fn relative_path(name: &Path) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(path)
+}
+
+fn unpack_tar<R: Read>(reader: R, dest: &Path, options: &ExtractOptions) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(true);
+    archive.set_preserve_mtime(true);
+    let mut extractor = Extractor::new(dest, options)?;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = match extractor.path(&entry.path()?)? {
+            Some(path) => path,
+            None => continue,
+        };
+
+        let header = entry.header();
+        let mtime = FileTime::from_unix_time(header.mtime()? as i64, 0);
+        let mode = header.mode().ok();
+        let kind = header.entry_type();
+
+        if kind.is_dir() {
+            extractor.directory(&path, mtime, mode)?;
+        } else if kind.is_symlink() {
+            let target = entry
+                .link_name()?
+                .ok_or_else(|| Error::InvalidArchivePath(path.clone()))?;
+            extractor.symlink(&path, &target)?;
+        } else if kind.is_hard_link() {
+            let link = entry
+                .link_name()?
+                .ok_or_else(|| Error::InvalidArchivePath(path.clone()))?;
+            let source = extractor
+                .path(&link)?
+                .ok_or_else(|| Error::InvalidArchivePath(link.to_path_buf()))?;
+            extractor.hard_link(&path, &source)?;
+        } else if kind.is_file() || kind.is_contiguous() {
+            extractor.prepare(&path)?;
+            entry.unpack(&path)?;
+        } else {
+            debug!("Skipping {kind:?} entry {}", path.display());
+        }
+    }
+
+    extractor.finish()
+}
+
+/// Extracts entries into a directory without escaping it.
+struct Extractor<'a> {
+    dest: PathBuf,
+    options: &'a ExtractOptions,
+    /// Directories with their modification time and mode, which are set after
+    /// their contents are extracted.
+    directories: Vec<(PathBuf, FileTime, Option<u32>)>,
+}
+
+impl<'a> Extractor<'a> {
+    fn new(dest: &Path, options: &'a ExtractOptions) -> Result<Self> {
+        fs::create_dir_all(dest)?;
+        Ok(Self {
+            dest: dest.canonicalize()?,
+            options,
+            directories: Vec::new(),
+        })
+    }
+
+    /// Gets the extraction path of an entry, or `None` if all of its
+    /// components are stripped.
+    fn path(&self, name: &Path) -> Result<Option<PathBuf>> {
+        let path = relative_path(name).ok_or_else(|| Error::InvalidArchivePath(name.into()))?;
+        let path: PathBuf = path
+            .components()
+            .skip(self.options.strip_components)
+            .collect();
+        if path.as_os_str().is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(self.dest.join(path)))
+        }
+    }
+
+    /// Creates the directory `path` and its parents, failing if any of them
+    /// is a symbolic link resolving outside of the destination.
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        let relative = path
+            .strip_prefix(&self.dest)
+            .map_err(|_| Error::InvalidArchivePath(path.into()))?;
+
+        let mut dir = self.dest.clone();
+        for component in relative.components() {
+            dir.push(component);
+            match fs::symlink_metadata(&dir) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    if !dir.canonicalize()?.starts_with(&self.dest) {
+                        return Err(Error::InvalidArchivePath(dir));
+                    }
+                }
+                Ok(_) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => fs::create_dir(&dir)?,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Prepares writing the entry `path`, creating its parent directories and
+    /// removing an existing file or symbolic link instead of writing through it.
+    fn prepare(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            self.create_dir(parent)?;
+        }
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => Err(Error::IO(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is a directory", path.display()),
+            ))),
+            Ok(_) => Ok(fs::remove_file(path)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn directory(&mut self, path: &Path, mtime: FileTime, mode: Option<u32>) -> Result<()> {
+        self.create_dir(path)?;
+        self.directories.push((path.to_path_buf(), mtime, mode));
+        Ok(())
+    }
+
+    fn file<R: Read + ?Sized>(
+        &self,
+        path: &Path,
+        reader: &mut R,
+        mtime: FileTime,
+        mode: Option<u32>,
+    ) -> Result<()> {
+        self.prepare(path)?;
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        io::copy(reader, &mut file)?;
+        drop(file);
+
+        set_mode(path, mode)?;
+        filetime::set_file_mtime(path, mtime)?;
+        Ok(())
+    }
+
+    /// Creates a symbolic link, which must be relative and point inside the
+    /// destination.
+    fn symlink(&self, path: &Path, target: &Path) -> Result<()> {
+        let invalid = || Error::InvalidArchivePath(path.into());
+        let parent = path.parent().ok_or_else(invalid)?;
+        let mut depth = parent
+            .strip_prefix(&self.dest)
+            .map_err(|_| invalid())?
+            .components()
+            .count();
+        for component in target.components() {
+            match component {
+                Component::Normal(_) => depth += 1,
+                Component::CurDir => {}
+                Component::ParentDir if depth > 0 => depth -= 1,
+                _ => return Err(invalid()),
+            }
+        }
+
+        self.prepare(path)?;
+        debug!("Linking {} to {}", path.display(), target.display());
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(target, path)?;
+        #[cfg(windows)]
+        std::os::windows::fs::symlink_file(target, path)?;
+
+        // Links through other links may still resolve outside
+        if let Ok(resolved) = path.canonicalize() {
+            if !resolved.starts_with(&self.dest) {
+                fs::remove_file(path)?;
+                return Err(invalid());
+            }
+        }
+        Ok(())
+    }
+
+    fn hard_link(&self, path: &Path, source: &Path) -> Result<()> {
+        if !source.canonicalize()?.starts_with(&self.dest) {
+            return Err(Error::InvalidArchivePath(source.into()));
+        }
+        self.prepare(path)?;
+        fs::hard_link(source, path)?;
+        Ok(())
+    }
+
+    /// Sets the modification time and mode of directories, deepest first so
+    /// setting them isn't undone by extracting their contents.
+    fn finish(mut self) -> Result<()> {
+        self.directories
+            .sort_by_key(|(path, _, _)| Reverse(path.components().count()));
+        for (path, mtime, mode) in &self.directories {
+            set_mode(path, *mode)?;
+            filetime::set_file_mtime(path, *mtime)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: Option<u32>) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Some(mode) = mode {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: Option<u32>) -> Result<()> {
+    Ok(())
+}
+
+fn dest_dir(dest: Option<&Path>) -> PathBuf {
+    match dest {
+        Some(dest) => dest.to_path_buf(),
+        None => temp::unique_path(""),
+    }
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::IO(io::Error::new(io::ErrorKind::InvalidData, message))
+}
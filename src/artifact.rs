@@ -0,0 +1,1214 @@
+//! # GitHub Actions artifact client
+//!
+//! The [`ArtifactClient`] is an idiomatic Rust port of
+//! [@actions/artifact](https://github.com/actions/toolkit/tree/main/packages/artifact)
+//! for the v4 artifact service. Older GitHub Enterprise Server instances
+//! which only provide the legacy v3 service are detected by
+//! [`ArtifactClientBuilder::from_env`] and supported with the same API, see
+//! [`ServiceVersion`].
+//!
+//! Artifacts of the current workflow run, or of other runs and repositories
+//! with [`FindBy`], can be listed, downloaded and deleted.
+//!
+//! See [Storing workflow data as
+//! artifacts](https://docs.github.com/en/actions/using-workflows/storing-workflow-data-as-artifacts)
+//! for the official GitHub documentation.
+//!
+//! ```rust,no_run
+//! # use gha_toolkit::artifact::*;
+//! #
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let client = ArtifactClient::from_env()?.build()?;
+//!
+//! let response = client
+//!     .upload_artifact(
+//!         "binaries",
+//!         ["target/release/app"],
+//!         "target/release",
+//!         &UploadArtifactOptions::default(),
+//!     )
+//!     .await?;
+//!
+//! println!("Uploaded artifact {} ({} bytes)", response.id, response.size);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::env;
+use std::fmt;
+use std::fs::{self, File, Metadata};
+use std::io::Read;
+use std::mem;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::prelude::*;
+use futures::stream::FuturesUnordered;
+use http::{header, HeaderMap, HeaderName, HeaderValue};
+use reqwest::Url;
+use reqwest_middleware::ClientWithMiddleware;
+#[cfg(doc)]
+use reqwest_retry::policies::ExponentialBackoff;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, instrument};
+
+use crate::core::{self, secrets};
+use crate::datetime::DateTime;
+use crate::transport::{self, ClientOptions};
+use crate::{Error, Result};
+
+mod pipelines;
+mod twirp;
+mod unzip;
+mod zip;
+
+use self::twirp::*;
+use self::unzip::ZipExtractor;
+
+pub use self::zip::ZipWriter;
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+const DEFAULT_UPLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+const READ_BUFFER_SIZE: usize = 64 << 10; // 64 KiB
+
+/// Characters not allowed in artifact names.
+const INVALID_NAME_CHARACTERS: &[char] =
+    &['"', ':', '<', '>', '|', '*', '?', '\r', '\n', '\\', '/'];
+
+/// Characters not allowed in artifact file paths.
+const INVALID_PATH_CHARACTERS: &[char] = &['"', ':', '<', '>', '|', '*', '?', '\r', '\n'];
+
+/// Version of the GitHub Actions artifact service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServiceVersion {
+    /// Legacy pipelines API of the runtime service at `ACTIONS_RUNTIME_URL`,
+    /// used by older GitHub Enterprise Server instances. Files are uploaded
+    /// individually and deleting artifacts is not supported.
+    V3,
+
+    /// Results service at `ACTIONS_RESULTS_URL`.
+    V4,
+}
+
+impl Default for ServiceVersion {
+    fn default() -> Self {
+        Self::V4
+    }
+}
+
+/// GitHub Actions artifact client builder.
+///
+/// The access token is never written by the [`Debug`][fmt::Debug]
+/// implementation.
+///
+/// See [module][self] documentation.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ArtifactClientBuilder {
+    /// GitHub Actions results service base URL for [`ServiceVersion::V4`], or
+    /// runtime service base URL for [`ServiceVersion::V3`].
+    pub base_url: String,
+
+    /// GitHub Actions access token.
+    pub token: String,
+
+    /// Version of the artifact service.
+    pub version: ServiceVersion,
+
+    /// ID of the current workflow run, required by [`ServiceVersion::V3`].
+    pub workflow_run_id: Option<u64>,
+
+    /// GitHub REST API base URL for artifacts of other workflow runs, see
+    /// [`FindBy`].
+    pub api_url: String,
+
+    /// User agent for HTTP requests.
+    pub user_agent: String,
+
+    /// Maximum number of retries.
+    pub max_retries: u32,
+
+    /// Minimum retry interval. See [`ExponentialBackoff::min_retry_interval`].
+    pub min_retry_interval: Duration,
+
+    /// Maximum retry interval. See [`ExponentialBackoff::max_retry_interval`].
+    pub max_retry_interval: Duration,
+
+    /// Retry backoff factor base. See [`ExponentialBackoff::backoff_exponent`].
+    pub backoff_factor_base: u32,
+
+    /// Default compression level of the artifact archive from 0 (stored) to
+    /// 9.
+    pub compression_level: u32,
+
+    /// Maximum chunk size in bytes for uploads.
+    pub upload_chunk_size: u64,
+
+    /// Maximum time for each chunk upload request.
+    pub upload_chunk_timeout: Duration,
+
+    /// Number of parallel uploads.
+    pub upload_concurrency: u32,
+}
+
+impl Default for ArtifactClientBuilder {
+    fn default() -> Self {
+        Self {
+            base_url: Default::default(),
+            token: Default::default(),
+            version: Default::default(),
+            workflow_run_id: None,
+            api_url: DEFAULT_API_URL.into(),
+            user_agent: transport::DEFAULT_USER_AGENT.into(),
+            max_retries: transport::DEFAULT_MAX_RETRIES,
+            min_retry_interval: transport::DEFAULT_MIN_RETRY_INTERVAL,
+            max_retry_interval: transport::DEFAULT_MAX_RETRY_INTERVAL,
+            backoff_factor_base: transport::DEFAULT_BACKOFF_FACTOR_BASE,
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            upload_chunk_size: 8 << 20, // 8 MiB
+            upload_chunk_timeout: DEFAULT_UPLOAD_TIMEOUT,
+            upload_concurrency: 4,
+        }
+    }
+}
+
+impl fmt::Debug for ArtifactClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArtifactClientBuilder")
+            .field("base_url", &secrets::Redacted(&self.base_url))
+            .field("token", &secrets::MASK)
+            .field("version", &self.version)
+            .field("workflow_run_id", &self.workflow_run_id)
+            .field("api_url", &self.api_url)
+            .field("user_agent", &secrets::Redacted(&self.user_agent))
+            .field("max_retries", &self.max_retries)
+            .field("min_retry_interval", &self.min_retry_interval)
+            .field("max_retry_interval", &self.max_retry_interval)
+            .field("backoff_factor_base", &self.backoff_factor_base)
+            .field("compression_level", &self.compression_level)
+            .field("upload_chunk_size", &self.upload_chunk_size)
+            .field("upload_chunk_timeout", &self.upload_chunk_timeout)
+            .field("upload_concurrency", &self.upload_concurrency)
+            .finish()
+    }
+}
+
+impl ArtifactClientBuilder {
+    /// Creates a new [`ArtifactClientBuilder`] for the given GitHub Actions
+    /// results service base URL and access token.
+    ///
+    /// See [`version`][Self::version] for the runtime service of older
+    /// GitHub Enterprise Server instances.
+    pub fn new<B: Into<String>, T: Into<String>>(base_url: B, token: T) -> Self {
+        Self {
+            base_url: base_url.into(),
+            token: token.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new [`ArtifactClientBuilder`] from GitHub Actions
+    /// environmental variables.
+    ///
+    /// The [`ServiceVersion::V4`] results service is used if
+    /// `ACTIONS_RESULTS_URL` is set, otherwise the [`ServiceVersion::V3`]
+    /// runtime service of older GitHub Enterprise Server instances.
+    ///
+    /// The following environmental variables are read:
+    ///
+    /// - `ACTIONS_RESULTS_URL` - GitHub Actions results service base URL
+    /// - `ACTIONS_RUNTIME_URL` - GitHub Actions runtime service base URL, if
+    ///   `ACTIONS_RESULTS_URL` is not set
+    /// - `ACTIONS_RUNTIME_TOKEN` - GitHub Actions access token
+    /// - `GITHUB_RUN_ID` - ID of the current workflow run, if
+    ///   `ACTIONS_RESULTS_URL` is not set
+    /// - `GITHUB_API_URL` - GitHub REST API base URL (optional)
+    ///
+    pub fn from_env() -> Result<Self> {
+        let token = env::var("ACTIONS_RUNTIME_TOKEN").map_err(|source| Error::VarError {
+            source,
+            name: "ACTIONS_RUNTIME_TOKEN",
+        })?;
+
+        let mut builder = match env::var("ACTIONS_RESULTS_URL") {
+            Ok(url) => Self::new(url, token),
+            Err(_) => {
+                let url = env::var("ACTIONS_RUNTIME_URL").map_err(|source| Error::VarError {
+                    source,
+                    name: "ACTIONS_RUNTIME_URL",
+                })?;
+                let run_id = env::var("GITHUB_RUN_ID").map_err(|source| Error::VarError {
+                    source,
+                    name: "GITHUB_RUN_ID",
+                })?;
+                let run_id = run_id.parse().map_err(|_| Error::InvalidVar {
+                    name: "GITHUB_RUN_ID",
+                    value: run_id,
+                })?;
+
+                Self::new(url, token)
+                    .version(ServiceVersion::V3)
+                    .workflow_run_id(run_id)
+            }
+        };
+        if let Ok(api_url) = env::var("GITHUB_API_URL") {
+            builder.api_url = api_url;
+        }
+        Ok(builder)
+    }
+
+    /// Sets the GitHub Actions results or runtime service base URL.
+    pub fn base_url<T: Into<String>>(mut self, base_url: T) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Sets the GitHub Actions access token.
+    pub fn token<T: Into<String>>(mut self, token: T) -> Self {
+        self.token = token.into();
+        self
+    }
+
+    /// Sets the version of the artifact service.
+    pub fn version(mut self, version: ServiceVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the ID of the current workflow run.
+    pub fn workflow_run_id(mut self, workflow_run_id: u64) -> Self {
+        self.workflow_run_id = Some(workflow_run_id);
+        self
+    }
+
+    /// Sets the GitHub REST API base URL.
+    pub fn api_url<T: Into<String>>(mut self, api_url: T) -> Self {
+        self.api_url = api_url.into();
+        self
+    }
+
+    /// Sets the user agent for HTTP requests.
+    pub fn user_agent<T: Into<String>>(mut self, user_agent: T) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets the maximum number of retries.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the minimum retry interval.
+    pub fn min_retry_interval(mut self, min_retry_interval: Duration) -> Self {
+        self.min_retry_interval = min_retry_interval;
+        self
+    }
+
+    /// Sets the maximum retry interval.
+    pub fn max_retry_interval(mut self, max_retry_interval: Duration) -> Self {
+        self.max_retry_interval = max_retry_interval;
+        self
+    }
+
+    /// Sets the retry backoff factor base.
+    pub fn backoff_factor_base(mut self, backoff_factor_base: u32) -> Self {
+        self.backoff_factor_base = backoff_factor_base;
+        self
+    }
+
+    /// Sets the default compression level of the artifact archive.
+    pub fn compression_level(mut self, compression_level: u32) -> Self {
+        self.compression_level = compression_level;
+        self
+    }
+
+    /// Sets the maximum chunk size in bytes for uploads.
+    pub fn upload_chunk_size(mut self, upload_chunk_size: u64) -> Self {
+        self.upload_chunk_size = upload_chunk_size;
+        self
+    }
+
+    /// Sets the maximum time for each chunk upload request.
+    pub fn upload_chunk_timeout(mut self, upload_chunk_timeout: Duration) -> Self {
+        self.upload_chunk_timeout = upload_chunk_timeout;
+        self
+    }
+
+    /// Sets the number of parallel uploads.
+    pub fn upload_concurrency(mut self, upload_concurrency: u32) -> Self {
+        self.upload_concurrency = upload_concurrency;
+        self
+    }
+
+    /// Consumes this [`ArtifactClientBuilder`] and build an
+    /// [`ArtifactClient`].
+    ///
+    /// The access token is registered as a [secret][secrets::register].
+    pub fn build(self) -> Result<ArtifactClient> {
+        self.try_into()
+    }
+}
+
+/// GitHub Actions artifact client.
+///
+/// See [module][self] documentation.
+pub struct ArtifactClient {
+    client: ClientWithMiddleware,
+    version: ServiceVersion,
+    base_url: Url,
+    api_headers: HeaderMap,
+    api_url: String,
+
+    // Only used by `ServiceVersion::V4`
+    workflow_run_backend_id: String,
+    workflow_job_run_backend_id: String,
+
+    compression_level: u32,
+    upload_chunk_size: u64,
+    upload_chunk_timeout: Duration,
+    upload_concurrency: u32,
+}
+
+#[derive(Deserialize)]
+struct RuntimeTokenClaims {
+    #[serde(default)]
+    scp: String,
+}
+
+impl TryInto<ArtifactClient> for ArtifactClientBuilder {
+    type Error = Error;
+
+    fn try_into(self) -> Result<ArtifactClient, Self::Error> {
+        let base_url = self.base_url.trim_end_matches('/');
+        let (base_url, workflow_run_backend_id, workflow_job_run_backend_id) = match self.version {
+            ServiceVersion::V3 => {
+                let run_id = self.workflow_run_id.ok_or(Error::MissingWorkflowRunId)?;
+                let base_url = Url::parse(&format!(
+                    "{base_url}/_apis/pipelines/workflows/{run_id}/artifacts?api-version={}",
+                    pipelines::API_VERSION
+                ))?;
+                (base_url, String::new(), String::new())
+            }
+            ServiceVersion::V4 => {
+                let claims: RuntimeTokenClaims = core::decode_jwt_payload(&self.token)
+                    .map_err(|_| Error::InvalidRuntimeToken("expected a JWT"))?;
+
+                // The scope contains `Actions.Results:<run backend ID>:<job backend ID>`
+                let (run_id, job_id) = claims
+                    .scp
+                    .split(' ')
+                    .filter_map(|scope| scope.strip_prefix("Actions.Results:"))
+                    .find_map(|ids| ids.split_once(':'))
+                    .ok_or(Error::InvalidRuntimeToken("missing Actions.Results scope"))?;
+
+                let base_url = Url::parse(&format!("{base_url}/{SERVICE_PATH}"))?;
+                (base_url, run_id.to_string(), job_id.to_string())
+            }
+        };
+
+        secrets::register(&self.token);
+
+        let accept = match self.version {
+            ServiceVersion::V3 => pipelines::ACCEPT_JSON,
+            ServiceVersion::V4 => "application/json",
+        };
+        let mut api_headers = HeaderMap::new();
+        api_headers.insert(header::ACCEPT, HeaderValue::from_static(accept));
+
+        let auth_value = Bytes::from(format!("Bearer {}", self.token));
+        let mut auth_value = HeaderValue::from_maybe_shared(auth_value)?;
+        auth_value.set_sensitive(true);
+        api_headers.insert(header::AUTHORIZATION, auth_value);
+
+        let client = ClientOptions {
+            user_agent: &self.user_agent,
+            max_retries: self.max_retries,
+            min_retry_interval: self.min_retry_interval,
+            max_retry_interval: self.max_retry_interval,
+            backoff_factor_base: self.backoff_factor_base,
+            ca_bundle: None,
+        }
+        .build()?;
+
+        Ok(ArtifactClient {
+            client,
+            version: self.version,
+            base_url,
+            api_headers,
+            api_url: self.api_url.trim_end_matches('/').to_string(),
+            workflow_run_backend_id,
+            workflow_job_run_backend_id,
+            compression_level: self.compression_level,
+            upload_chunk_size: self.upload_chunk_size,
+            upload_chunk_timeout: self.upload_chunk_timeout,
+            upload_concurrency: self.upload_concurrency,
+        })
+    }
+}
+
+/// Options for [`ArtifactClient::upload_artifact`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UploadArtifactOptions {
+    /// Number of days before the artifact expires. Defaults to the retention
+    /// period of the repository.
+    pub retention_days: Option<u32>,
+
+    /// Compression level of the artifact archive from 0 (stored) to 9.
+    /// Defaults to [`ArtifactClientBuilder::compression_level`].
+    pub compression_level: Option<u32>,
+}
+
+/// Uploaded artifact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadArtifactResponse {
+    /// Artifact ID.
+    pub id: i64,
+
+    /// Size of the artifact archive in bytes.
+    pub size: u64,
+
+    /// SHA-256 digest of the artifact archive, e.g. `sha256:abcd…`. Only set
+    /// by the v4 artifact service.
+    pub digest: Option<String>,
+}
+
+/// Artifact of a workflow run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    /// Artifact ID.
+    pub id: i64,
+
+    /// Artifact name.
+    pub name: String,
+
+    /// Size of the artifact archive in bytes.
+    pub size: u64,
+
+    /// Time the artifact was created, e.g. `2022-12-01T08:30:00Z`.
+    pub created_at: Option<String>,
+
+    /// SHA-256 digest of the artifact archive, e.g. `sha256:abcd…`. Only set
+    /// for artifacts uploaded to the v4 artifact service.
+    pub digest: Option<String>,
+}
+
+/// Workflow run to find artifacts in, instead of the current run.
+///
+/// Artifacts of other runs are found with the GitHub REST API, which requires
+/// a `token` with `actions:read` permission on the repository, or
+/// `actions:write` to delete artifacts. The token is never written by the
+/// [`Debug`][fmt::Debug] implementation.
+#[derive(Clone, PartialEq, Eq)]
+pub struct FindBy {
+    /// GitHub access token.
+    pub token: String,
+
+    /// Owner of the repository, e.g. `octo-org`.
+    pub repository_owner: String,
+
+    /// Name of the repository, e.g. `octo-repo`.
+    pub repository_name: String,
+
+    /// ID of the workflow run.
+    pub workflow_run_id: u64,
+}
+
+impl fmt::Debug for FindBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FindBy")
+            .field("token", &secrets::MASK)
+            .field("repository_owner", &self.repository_owner)
+            .field("repository_name", &self.repository_name)
+            .field("workflow_run_id", &self.workflow_run_id)
+            .finish()
+    }
+}
+
+impl FindBy {
+    /// Creates a new [`FindBy`] for the workflow run `workflow_run_id` of the
+    /// repository `owner/name`.
+    ///
+    /// The token is registered as a [secret][secrets::register].
+    pub fn new<T, O, N>(
+        token: T,
+        repository_owner: O,
+        repository_name: N,
+        workflow_run_id: u64,
+    ) -> Self
+    where
+        T: Into<String>,
+        O: Into<String>,
+        N: Into<String>,
+    {
+        let token = token.into();
+        secrets::register(&token);
+        Self {
+            token,
+            repository_owner: repository_owner.into(),
+            repository_name: repository_name.into(),
+            workflow_run_id,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RestArtifactList {
+    #[serde(default)]
+    total_count: usize,
+    #[serde(default)]
+    artifacts: Vec<RestArtifact>,
+}
+
+#[derive(Deserialize)]
+struct RestArtifact {
+    id: i64,
+    name: String,
+    #[serde(default)]
+    size_in_bytes: u64,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+impl From<RestArtifact> for Artifact {
+    fn from(artifact: RestArtifact) -> Self {
+        Self {
+            id: artifact.id,
+            name: artifact.name,
+            size: artifact.size_in_bytes,
+            created_at: artifact.created_at,
+            digest: artifact.digest,
+        }
+    }
+}
+
+impl From<MonolithArtifact> for Artifact {
+    fn from(artifact: MonolithArtifact) -> Self {
+        Self {
+            id: artifact.database_id,
+            name: artifact.name,
+            size: artifact.size.max(0) as u64,
+            created_at: artifact.created_at,
+            digest: artifact.digest.filter(|digest| !digest.is_empty()),
+        }
+    }
+}
+
+impl ArtifactClient {
+    /// Creates a new [`ArtifactClientBuilder`].
+    ///
+    /// See [`ArtifactClientBuilder::new`].
+    pub fn builder<B: Into<String>, T: Into<String>>(
+        base_url: B,
+        token: T,
+    ) -> ArtifactClientBuilder {
+        ArtifactClientBuilder::new(base_url, token)
+    }
+
+    /// Creates a new [`ArtifactClientBuilder`] from environmental variables.
+    ///
+    /// See [`ArtifactClientBuilder::from_env`].
+    pub fn from_env() -> Result<ArtifactClientBuilder> {
+        ArtifactClientBuilder::from_env()
+    }
+
+    /// Uploads `files` as a zipped artifact named `name`.
+    ///
+    /// The archive paths of the files are relative to `root_directory`. The
+    /// archive is streamed to the artifact storage while it is written, so
+    /// neither the archive nor the files are held in memory. Directories are
+    /// skipped.
+    #[instrument(skip(self, files, root_directory, options))]
+    pub async fn upload_artifact<I, P, R>(
+        &self,
+        name: &str,
+        files: I,
+        root_directory: R,
+        options: &UploadArtifactOptions,
+    ) -> Result<UploadArtifactResponse>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+        R: AsRef<Path>,
+    {
+        check_artifact_name(name)?;
+        let entries = archive_entries(files, root_directory.as_ref())?;
+
+        if self.version == ServiceVersion::V3 {
+            return self
+                .upload_pipelines_artifact(name, &entries, options)
+                .await;
+        }
+
+        let expires_at = options.retention_days.map(|days| {
+            let expires_at = SystemTime::now() + Duration::from_secs(u64::from(days) * 86_400);
+            let expires_at = DateTime::from_system_time(expires_at).to_string();
+            debug!("Artifact expires at {expires_at}");
+            expires_at
+        });
+
+        let response: CreateArtifactResponse = self
+            .twirp(
+                "CreateArtifact",
+                &CreateArtifactRequest {
+                    workflow_run_backend_id: &self.workflow_run_backend_id,
+                    workflow_job_run_backend_id: &self.workflow_job_run_backend_id,
+                    name,
+                    expires_at,
+                    version: 4,
+                },
+            )
+            .await?;
+        if !response.ok {
+            return Err(Error::ArtifactServiceFailed("create artifact"));
+        }
+        secrets::register(&response.signed_upload_url);
+
+        let level = options.compression_level.unwrap_or(self.compression_level);
+        let url = Url::parse(&response.signed_upload_url)?;
+        let (size, digest) = self.upload_zip(&url, &entries, level).await?;
+
+        let response: FinalizeArtifactResponse = self
+            .twirp(
+                "FinalizeArtifact",
+                &FinalizeArtifactRequest {
+                    workflow_run_backend_id: &self.workflow_run_backend_id,
+                    workflow_job_run_backend_id: &self.workflow_job_run_backend_id,
+                    name,
+                    size: size.to_string(),
+                    hash: digest.clone(),
+                },
+            )
+            .await?;
+        if !response.ok {
+            return Err(Error::ArtifactServiceFailed("finalize artifact"));
+        }
+
+        Ok(UploadArtifactResponse {
+            id: response.artifact_id,
+            size,
+            digest: Some(digest),
+        })
+    }
+
+    /// Lists the artifacts of the current workflow run, or of the run given by
+    /// `find_by`.
+    #[instrument(skip(self))]
+    pub async fn list_artifacts(&self, find_by: Option<&FindBy>) -> Result<Vec<Artifact>> {
+        match find_by {
+            Some(find_by) => self.list_run_artifacts(find_by, None).await,
+            None => self.list_current_artifacts(None, None).await,
+        }
+    }
+
+    /// Gets the artifact named `name` of the current workflow run, or of the
+    /// run given by `find_by`.
+    ///
+    /// If several artifacts share the name, the latest one is returned.
+    #[instrument(skip(self))]
+    pub async fn get_artifact(&self, name: &str, find_by: Option<&FindBy>) -> Result<Artifact> {
+        let artifacts = match find_by {
+            Some(find_by) => self.list_run_artifacts(find_by, Some(name)).await?,
+            None => self.list_current_artifacts(Some(name), None).await?,
+        };
+
+        artifacts
+            .into_iter()
+            .filter(|artifact| artifact.name == name)
+            .max_by_key(|artifact| artifact.id)
+            .ok_or_else(|| Error::ArtifactNotFound(name.to_string()))
+    }
+
+    /// Downloads the artifact `artifact_id` of the current workflow run, or of
+    /// the run given by `find_by`, and extracts it into the `dest` directory.
+    ///
+    /// The archive is extracted while it is downloaded. Entries which would
+    /// be extracted outside of `dest` are rejected with
+    /// [`Error::InvalidArtifactPath`]. If the artifact has a digest, the
+    /// digest of the downloaded archive is verified after extraction and
+    /// [`Error::ArtifactDigestMismatch`] is returned if it differs.
+    #[instrument(skip(self, dest), fields(dest = %dest.as_ref().display()))]
+    pub async fn download_artifact<P: AsRef<Path>>(
+        &self,
+        artifact_id: i64,
+        dest: P,
+        find_by: Option<&FindBy>,
+    ) -> Result<()> {
+        if find_by.is_none() && self.version == ServiceVersion::V3 {
+            return self
+                .download_pipelines_artifact(artifact_id, dest.as_ref())
+                .await;
+        }
+
+        let (artifact, request) = match find_by {
+            Some(find_by) => {
+                let url = format!(
+                    "{}/repos/{}/{}/actions/artifacts/{artifact_id}",
+                    self.api_url, find_by.repository_owner, find_by.repository_name
+                );
+                let artifact: RestArtifact = self.rest(find_by, reqwest::Method::GET, &url).await?;
+
+                // Redirects to a signed URL, which must not get the token
+                let request = self
+                    .client
+                    .get(format!("{url}/zip"))
+                    .headers(rest_headers(find_by)?);
+                (Artifact::from(artifact), request)
+            }
+            None => {
+                let artifact = self
+                    .list_current_artifacts(None, Some(artifact_id))
+                    .await?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| Error::ArtifactNotFound(artifact_id.to_string()))?;
+
+                let response: GetSignedArtifactUrlResponse = self
+                    .twirp(
+                        "GetSignedArtifactURL",
+                        &GetSignedArtifactUrlRequest {
+                            workflow_run_backend_id: &self.workflow_run_backend_id,
+                            workflow_job_run_backend_id: &self.workflow_job_run_backend_id,
+                            name: &artifact.name,
+                        },
+                    )
+                    .await?;
+                if response.signed_url.is_empty() {
+                    return Err(Error::ArtifactServiceFailed("get signed artifact URL"));
+                }
+                secrets::register(&response.signed_url);
+
+                (artifact, self.client.get(response.signed_url))
+            }
+        };
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        let dest = dest.as_ref();
+        fs::create_dir_all(dest)?;
+        let mut extractor = ZipExtractor::new(dest);
+        let mut hasher = Sha256::new();
+
+        let mut response = response;
+        while let Some(chunk) = response.chunk().await? {
+            hasher.update(&chunk);
+            extractor.write(&chunk)?;
+        }
+        extractor.finish()?;
+
+        let actual = format!("sha256:{}", hex::encode(hasher.finalize()));
+        match artifact.digest {
+            Some(expected) if expected != actual => {
+                Err(Error::ArtifactDigestMismatch { expected, actual })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Deletes the artifact named `name` of the current workflow run, or of
+    /// the run given by `find_by`, and returns its ID.
+    ///
+    /// Artifacts of the current run cannot be deleted with
+    /// [`ServiceVersion::V3`].
+    #[instrument(skip(self))]
+    pub async fn delete_artifact(&self, name: &str, find_by: Option<&FindBy>) -> Result<i64> {
+        match find_by {
+            Some(find_by) => {
+                let artifact = self.get_artifact(name, Some(find_by)).await?;
+                let url = format!(
+                    "{}/repos/{}/{}/actions/artifacts/{}",
+                    self.api_url, find_by.repository_owner, find_by.repository_name, artifact.id
+                );
+
+                let response = self
+                    .client
+                    .delete(url)
+                    .headers(rest_headers(find_by)?)
+                    .send()
+                    .await?;
+                if !response.status().is_success() {
+                    return Err(status_error(response).await);
+                }
+                Ok(artifact.id)
+            }
+            None if self.version == ServiceVersion::V3 => {
+                Err(Error::ArtifactServiceUnsupported("deleting artifacts"))
+            }
+            None => {
+                let response: DeleteArtifactResponse = self
+                    .twirp(
+                        "DeleteArtifact",
+                        &DeleteArtifactRequest {
+                            workflow_run_backend_id: &self.workflow_run_backend_id,
+                            workflow_job_run_backend_id: &self.workflow_job_run_backend_id,
+                            name,
+                        },
+                    )
+                    .await?;
+                if !response.ok {
+                    return Err(Error::ArtifactServiceFailed("delete artifact"));
+                }
+                Ok(response.artifact_id)
+            }
+        }
+    }
+
+    async fn list_current_artifacts(
+        &self,
+        name: Option<&str>,
+        id: Option<i64>,
+    ) -> Result<Vec<Artifact>> {
+        if self.version == ServiceVersion::V3 {
+            return self.list_pipelines_artifacts(name, id).await;
+        }
+
+        let response: ListArtifactsResponse = self
+            .twirp(
+                "ListArtifacts",
+                &ListArtifactsRequest {
+                    workflow_run_backend_id: &self.workflow_run_backend_id,
+                    workflow_job_run_backend_id: &self.workflow_job_run_backend_id,
+                    name_filter: name,
+                    id_filter: id.map(|id| id.to_string()),
+                },
+            )
+            .await?;
+
+        Ok(response.artifacts.into_iter().map(Artifact::from).collect())
+    }
+
+    async fn list_run_artifacts(
+        &self,
+        find_by: &FindBy,
+        name: Option<&str>,
+    ) -> Result<Vec<Artifact>> {
+        let mut artifacts = Vec::new();
+        for page in 1.. {
+            let mut url = Url::parse(&format!(
+                "{}/repos/{}/{}/actions/runs/{}/artifacts",
+                self.api_url,
+                find_by.repository_owner,
+                find_by.repository_name,
+                find_by.workflow_run_id
+            ))?;
+            url.query_pairs_mut()
+                .append_pair("per_page", "100")
+                .append_pair("page", &page.to_string());
+            if let Some(name) = name {
+                url.query_pairs_mut().append_pair("name", name);
+            }
+
+            let list: RestArtifactList = self
+                .rest(find_by, reqwest::Method::GET, url.as_str())
+                .await?;
+            let last = list.artifacts.is_empty();
+            artifacts.extend(list.artifacts.into_iter().map(Artifact::from));
+            if last || artifacts.len() >= list.total_count {
+                break;
+            }
+        }
+        Ok(artifacts)
+    }
+
+    async fn rest<R: DeserializeOwned>(
+        &self,
+        find_by: &FindBy,
+        method: reqwest::Method,
+        url: &str,
+    ) -> Result<R> {
+        let response = self
+            .client
+            .request(method, url)
+            .headers(rest_headers(find_by)?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    #[instrument(skip(self, request))]
+    async fn twirp<T: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        request: &T,
+    ) -> Result<R> {
+        let url = self.base_url.join(method)?;
+
+        let response = self
+            .client
+            .post(url)
+            .headers(self.api_headers.clone())
+            .json(request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    #[instrument(skip(self, url, entries))]
+    async fn upload_zip(
+        &self,
+        url: &Url,
+        entries: &[(String, PathBuf)],
+        level: u32,
+    ) -> Result<(u64, String)> {
+        let mut zip = ZipWriter::new(level);
+        let mut upload = BlockUpload::new(self, url);
+        let mut buf = vec![0; READ_BUFFER_SIZE];
+
+        for (name, path) in entries {
+            let mut file = File::open(path)?;
+            let metadata = file.metadata()?;
+            let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+            zip.start_file(name, modified, file_mode(&metadata))?;
+
+            loop {
+                let len = file.read(&mut buf)?;
+                if len == 0 {
+                    break;
+                }
+                zip.write_file(&buf[..len])?;
+                upload.write(zip.take_output()).await?;
+            }
+        }
+
+        zip.finish()?;
+        upload.write(zip.take_output()).await?;
+        upload.finish().await
+    }
+
+    #[instrument(skip(self, url, data))]
+    async fn upload_block(&self, url: Url, block_id: String, data: Vec<u8>) -> Result<()> {
+        let mut url = url;
+        url.query_pairs_mut()
+            .append_pair("comp", "block")
+            .append_pair("blockid", &block_id);
+
+        let response = self
+            .client
+            .put(url)
+            .body(data)
+            .timeout(self.upload_chunk_timeout)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(status_error(response).await)
+        }
+    }
+
+    #[instrument(skip(self, url, block_ids))]
+    async fn commit_blocks(&self, url: &Url, block_ids: &[String]) -> Result<()> {
+        let mut url = url.clone();
+        url.query_pairs_mut().append_pair("comp", "blocklist");
+
+        let mut body = String::from(r#"<?xml version="1.0" encoding="utf-8"?><BlockList>"#);
+        for block_id in block_ids {
+            body.push_str("<Latest>");
+            body.push_str(block_id);
+            body.push_str("</Latest>");
+        }
+        body.push_str("</BlockList>");
+
+        let response = self
+            .client
+            .put(url)
+            .header(
+                HeaderName::from_static("x-ms-blob-content-type"),
+                HeaderValue::from_static("application/zip"),
+            )
+            .body(body)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(status_error(response).await)
+        }
+    }
+}
+
+/// Uploads data as blocks of a block blob, hashing it on the way.
+struct BlockUpload<'a> {
+    client: &'a ArtifactClient,
+    url: &'a Url,
+    hasher: Sha256,
+    size: u64,
+    pending: Vec<u8>,
+    block_ids: Vec<String>,
+    uploads: FuturesUnordered<BoxFuture<'a, Result<()>>>,
+}
+
+impl<'a> BlockUpload<'a> {
+    fn new(client: &'a ArtifactClient, url: &'a Url) -> Self {
+        Self {
+            client,
+            url,
+            hasher: Sha256::new(),
+            size: 0,
+            pending: Vec::new(),
+            block_ids: Vec::new(),
+            uploads: FuturesUnordered::new(),
+        }
+    }
+
+    async fn write(&mut self, data: Vec<u8>) -> Result<()> {
+        self.hasher.update(&data);
+        self.size += data.len() as u64;
+        self.pending.extend_from_slice(&data);
+
+        let block_size = self.client.upload_chunk_size.max(1) as usize;
+        while self.pending.len() >= block_size {
+            let rest = self.pending.split_off(block_size);
+            let block = mem::replace(&mut self.pending, rest);
+            self.upload_block(block).await?;
+        }
+        Ok(())
+    }
+
+    async fn upload_block(&mut self, block: Vec<u8>) -> Result<()> {
+        let block_id = base64::encode(format!("{:016x}", self.block_ids.len()));
+        self.block_ids.push(block_id.clone());
+
+        let upload = self.client.upload_block(self.url.clone(), block_id, block);
+        self.uploads.push(upload.boxed());
+
+        if self.uploads.len() >= self.client.upload_concurrency.max(1) as usize {
+            if let Some(result) = self.uploads.next().await {
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn finish(mut self) -> Result<(u64, String)> {
+        if !self.pending.is_empty() || self.block_ids.is_empty() {
+            let block = mem::take(&mut self.pending);
+            self.upload_block(block).await?;
+        }
+
+        while let Some(result) = self.uploads.next().await {
+            result?;
+        }
+
+        self.client.commit_blocks(self.url, &self.block_ids).await?;
+
+        let digest = hex::encode(self.hasher.finalize());
+        Ok((self.size, format!("sha256:{digest}")))
+    }
+}
+
+/// Creates an [`Error::ArtifactServiceStatus`] from an unsuccessful response
+/// with registered secrets scrubbed from the message.
+async fn status_error(response: reqwest::Response) -> Error {
+    let status = response.status();
+    let message = response.text().await.unwrap_or_else(|err| err.to_string());
+    let message = match serde_json::from_str::<TwirpError>(&message) {
+        Ok(TwirpError { code, msg }) if !code.is_empty() => format!("{code}: {msg}"),
+        _ => message,
+    };
+    let message = secrets::redact(&message).into_owned();
+    Error::ArtifactServiceStatus { status, message }
+}
+
+/// Gets the GitHub REST API headers for the token of `find_by`.
+fn rest_headers(find_by: &FindBy) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::ACCEPT,
+        HeaderValue::from_static("application/vnd.github+json"),
+    );
+
+    let auth_value = Bytes::from(format!("Bearer {}", find_by.token));
+    let mut auth_value = HeaderValue::from_maybe_shared(auth_value)?;
+    auth_value.set_sensitive(true);
+    headers.insert(header::AUTHORIZATION, auth_value);
+
+    Ok(headers)
+}
+
+/// Gets the archive paths of `files` relative to `root_directory`.
+fn archive_entries<I, P>(files: I, root_directory: &Path) -> Result<Vec<(String, PathBuf)>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut entries = Vec::new();
+    for file in files {
+        let file = file.as_ref();
+        if file.is_dir() {
+            continue;
+        }
+
+        let relative = file
+            .strip_prefix(root_directory)
+            .map_err(|_| Error::InvalidArtifactPath(file.to_path_buf()))?;
+
+        let mut name = String::new();
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => {
+                    let part = part
+                        .to_str()
+                        .ok_or_else(|| Error::InvalidArtifactPath(file.to_path_buf()))?;
+                    if part.contains(INVALID_PATH_CHARACTERS) {
+                        return Err(Error::InvalidArtifactPath(file.to_path_buf()));
+                    }
+                    if !name.is_empty() {
+                        name.push('/');
+                    }
+                    name.push_str(part);
+                }
+                Component::CurDir => {}
+                _ => return Err(Error::InvalidArtifactPath(file.to_path_buf())),
+            }
+        }
+
+        entries.push((name, file.to_path_buf()));
+    }
+    Ok(entries)
+}
+
+/// Checks that the artifact `name` is valid.
+pub fn check_artifact_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(INVALID_NAME_CHARACTERS) {
+        return Err(Error::InvalidArtifactName(name.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt as _;
+    metadata.permissions().mode()
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &Metadata) -> u32 {
+    if metadata.permissions().readonly() {
+        0o100444
+    } else {
+        0o100644
+    }
+}
@@ -0,0 +1,344 @@
+//! Legacy v3 artifact service, the pipelines API of the GitHub Actions
+//! runtime service.
+//!
+//! Each file is uploaded individually into the file container of the
+//! artifact, gzipped if that makes it smaller, in chunks with a
+//! `Content-Range` header like [cache uploads][crate::cache::CacheClient::put].
+//! The artifact is finalized by patching its total size.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::write::{GzDecoder, GzEncoder};
+use flate2::Compression;
+use futures::prelude::*;
+use http::{header, HeaderName, HeaderValue};
+use reqwest::Url;
+use reqwest_middleware::RequestBuilder;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use super::unzip::entry_path;
+use super::{
+    status_error, Artifact, ArtifactClient, UploadArtifactOptions, UploadArtifactResponse,
+};
+use crate::temp::TempFile;
+use crate::{Error, Result};
+
+pub(crate) const API_VERSION: &str = "6.0-preview";
+pub(crate) const ACCEPT_JSON: &str = "application/json;api-version=6.0-preview";
+const ACCEPT_OCTET_STREAM: &str = "application/octet-stream;api-version=6.0-preview";
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct CreateContainerRequest<'a> {
+    #[serde(rename = "Type")]
+    container_type: &'static str,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retention_days: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Container {
+    container_id: i64,
+    #[serde(default)]
+    size: i64,
+    name: String,
+    file_container_resource_url: String,
+}
+
+impl From<Container> for Artifact {
+    fn from(container: Container) -> Self {
+        Self {
+            id: container.container_id,
+            name: container.name,
+            size: container.size.max(0) as u64,
+            created_at: None,
+            digest: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ContainerList {
+    #[serde(default)]
+    value: Vec<Container>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PatchArtifactSizeRequest {
+    size: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContainerItem {
+    path: String,
+    item_type: String,
+    #[serde(default)]
+    content_location: String,
+}
+
+#[derive(Deserialize)]
+struct ContainerItemList {
+    #[serde(default)]
+    value: Vec<ContainerItem>,
+}
+
+impl ArtifactClient {
+    #[instrument(skip(self, entries, options))]
+    pub(super) async fn upload_pipelines_artifact(
+        &self,
+        name: &str,
+        entries: &[(String, PathBuf)],
+        options: &UploadArtifactOptions,
+    ) -> Result<UploadArtifactResponse> {
+        let request = self
+            .client
+            .post(self.base_url.clone())
+            .json(&CreateContainerRequest {
+                container_type: "actions_storage",
+                name,
+                retention_days: options.retention_days,
+            });
+        let container: Container = self.pipelines_json(request).await?;
+        let resource_url = Url::parse(&container.file_container_resource_url)?;
+
+        let level = options.compression_level.unwrap_or(self.compression_level);
+        let sizes: Vec<u64> = stream::iter(entries)
+            .map(|(entry, path)| {
+                let mut url = resource_url.clone();
+                url.query_pairs_mut()
+                    .append_pair("itemPath", &format!("{name}/{entry}"));
+                self.upload_pipelines_file(url, path, level)
+            })
+            .buffer_unordered(self.upload_concurrency.max(1) as usize)
+            .try_collect()
+            .await?;
+        let size = sizes.iter().sum();
+
+        let mut url = self.base_url.clone();
+        url.query_pairs_mut().append_pair("artifactName", name);
+        let response = self
+            .client
+            .patch(url)
+            .headers(self.api_headers.clone())
+            .json(&PatchArtifactSizeRequest { size })
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        Ok(UploadArtifactResponse {
+            id: container.container_id,
+            size,
+            digest: None,
+        })
+    }
+
+    /// Uploads a file and returns its size.
+    #[instrument(skip(self, url))]
+    async fn upload_pipelines_file(&self, url: Url, path: &Path, level: u32) -> Result<u64> {
+        let mut file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+
+        let mut gzip = None;
+        if level > 0 && file_size > 0 {
+            let mut temp = TempFile::create(".gz")?;
+            let mut encoder = GzEncoder::new(&mut temp.file, Compression::new(level.min(9)));
+            io::copy(&mut file, &mut encoder)?;
+            encoder.finish()?;
+
+            let gzip_size = temp.file.seek(SeekFrom::End(0))?;
+            if gzip_size < file_size {
+                temp.file.seek(SeekFrom::Start(0))?;
+                gzip = Some((temp, gzip_size));
+            } else {
+                debug!("Uploading {} without compression", path.display());
+                file.seek(SeekFrom::Start(0))?;
+            }
+        }
+
+        let gzipped = gzip.is_some();
+        let (mut source, upload_size): (&mut File, u64) = match gzip.as_mut() {
+            Some((temp, gzip_size)) => (&mut temp.file, *gzip_size),
+            None => (&mut file, file_size),
+        };
+
+        let mut start = 0;
+        loop {
+            let mut chunk = Vec::new();
+            let _ = (&mut source)
+                .take(self.upload_chunk_size.max(1))
+                .read_to_end(&mut chunk)?;
+            let chunk_size = chunk.len() as u64;
+
+            self.upload_pipelines_chunk(url.clone(), chunk, start, upload_size, file_size, gzipped)
+                .await?;
+
+            start += chunk_size;
+            if chunk_size == 0 || start >= upload_size {
+                break;
+            }
+        }
+
+        Ok(file_size)
+    }
+
+    #[instrument(skip(self, url, chunk))]
+    async fn upload_pipelines_chunk(
+        &self,
+        url: Url,
+        chunk: Vec<u8>,
+        start: u64,
+        upload_size: u64,
+        file_size: u64,
+        gzip: bool,
+    ) -> Result<()> {
+        // Empty files are uploaded with the range `bytes 0--1/0`
+        let end = (start + chunk.len() as u64) as i64 - 1;
+        let content_range = format!("bytes {start}-{end}/{upload_size}");
+
+        let mut request = self
+            .client
+            .put(url)
+            .headers(self.api_headers.clone())
+            .header(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/octet-stream"),
+            )
+            .header(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&content_range)?,
+            )
+            .header(
+                HeaderName::from_static("x-tfs-filelength"),
+                HeaderValue::from(file_size),
+            );
+        if gzip {
+            request = request.header(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        }
+
+        let response = request
+            .body(chunk)
+            .timeout(self.upload_chunk_timeout)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(status_error(response).await)
+        }
+    }
+
+    pub(super) async fn list_pipelines_artifacts(
+        &self,
+        name: Option<&str>,
+        id: Option<i64>,
+    ) -> Result<Vec<Artifact>> {
+        Ok(self
+            .pipelines_containers()
+            .await?
+            .into_iter()
+            .filter(|container| name.map_or(true, |name| container.name == name))
+            .filter(|container| id.map_or(true, |id| container.container_id == id))
+            .map(Artifact::from)
+            .collect())
+    }
+
+    #[instrument(skip(self, dest), fields(dest = %dest.display()))]
+    pub(super) async fn download_pipelines_artifact(
+        &self,
+        artifact_id: i64,
+        dest: &Path,
+    ) -> Result<()> {
+        let container = self
+            .pipelines_containers()
+            .await?
+            .into_iter()
+            .find(|container| container.container_id == artifact_id)
+            .ok_or_else(|| Error::ArtifactNotFound(artifact_id.to_string()))?;
+
+        let mut url = Url::parse(&container.file_container_resource_url)?;
+        url.query_pairs_mut()
+            .append_pair("itemPath", &container.name);
+        let items: ContainerItemList = self.pipelines_json(self.client.get(url)).await?;
+
+        let mut headers = self.api_headers.clone();
+        headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static(ACCEPT_OCTET_STREAM),
+        );
+        headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+
+        fs::create_dir_all(dest)?;
+        let prefix = format!("{}/", container.name);
+        for item in items.value {
+            if item.item_type != "file" {
+                continue;
+            }
+
+            // Item paths start with the artifact name
+            let relative = item
+                .path
+                .strip_prefix(&prefix)
+                .ok_or_else(|| Error::InvalidArtifactPath(PathBuf::from(&item.path)))?;
+            let path = entry_path(dest, relative)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut response = self
+                .client
+                .get(&item.content_location)
+                .headers(headers.clone())
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                return Err(status_error(response).await);
+            }
+
+            let gzip = response.headers().get(header::CONTENT_ENCODING)
+                == Some(&HeaderValue::from_static("gzip"));
+            let file = File::create(&path)?;
+            if gzip {
+                let mut decoder = GzDecoder::new(file);
+                while let Some(chunk) = response.chunk().await? {
+                    decoder.write_all(&chunk)?;
+                }
+                decoder.try_finish()?;
+            } else {
+                let mut file = file;
+                while let Some(chunk) = response.chunk().await? {
+                    file.write_all(&chunk)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn pipelines_containers(&self) -> Result<Vec<Container>> {
+        let list: ContainerList = self
+            .pipelines_json(self.client.get(self.base_url.clone()))
+            .await?;
+        Ok(list.value)
+    }
+
+    async fn pipelines_json<R: DeserializeOwned>(&self, request: RequestBuilder) -> Result<R> {
+        let response = request.headers(self.api_headers.clone()).send().await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+}
@@ -0,0 +1,122 @@
+//! Twirp JSON messages of the GitHub Actions results `ArtifactService`.
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+pub(crate) const SERVICE_PATH: &str = "twirp/github.actions.results.api.v1.ArtifactService/";
+
+#[derive(Serialize)]
+pub(crate) struct CreateArtifactRequest<'a> {
+    pub workflow_run_backend_id: &'a str,
+    pub workflow_job_run_backend_id: &'a str,
+    pub name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    pub version: u32,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct CreateArtifactResponse {
+    #[serde(default)]
+    pub ok: bool,
+    #[serde(default, alias = "signedUploadUrl")]
+    pub signed_upload_url: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct FinalizeArtifactRequest<'a> {
+    pub workflow_run_backend_id: &'a str,
+    pub workflow_job_run_backend_id: &'a str,
+    pub name: &'a str,
+    pub size: String,
+    pub hash: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct FinalizeArtifactResponse {
+    #[serde(default)]
+    pub ok: bool,
+    #[serde(default, alias = "artifactId", deserialize_with = "int64")]
+    pub artifact_id: i64,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct TwirpError {
+    #[serde(default)]
+    pub code: String,
+    #[serde(default)]
+    pub msg: String,
+}
+
+/// Deserializes an `int64`, which the protobuf JSON mapping encodes as a
+/// string.
+pub(crate) fn int64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Int64 {
+        Number(i64),
+        String(String),
+    }
+
+    match Int64::deserialize(deserializer)? {
+        Int64::Number(value) => Ok(value),
+        Int64::String(value) => value.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct ListArtifactsRequest<'a> {
+    pub workflow_run_backend_id: &'a str,
+    pub workflow_job_run_backend_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_filter: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_filter: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ListArtifactsResponse {
+    #[serde(default)]
+    pub artifacts: Vec<MonolithArtifact>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct MonolithArtifact {
+    #[serde(default, alias = "databaseId", deserialize_with = "int64")]
+    pub database_id: i64,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, deserialize_with = "int64")]
+    pub size: i64,
+    #[serde(default, alias = "createdAt")]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct GetSignedArtifactUrlRequest<'a> {
+    pub workflow_run_backend_id: &'a str,
+    pub workflow_job_run_backend_id: &'a str,
+    pub name: &'a str,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetSignedArtifactUrlResponse {
+    #[serde(default, alias = "signedUrl")]
+    pub signed_url: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct DeleteArtifactRequest<'a> {
+    pub workflow_run_backend_id: &'a str,
+    pub workflow_job_run_backend_id: &'a str,
+    pub name: &'a str,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DeleteArtifactResponse {
+    #[serde(default)]
+    pub ok: bool,
+    #[serde(default, alias = "artifactId", deserialize_with = "int64")]
+    pub artifact_id: i64,
+}
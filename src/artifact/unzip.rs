@@ -0,0 +1,367 @@
+//! Streaming ZIP extractor.
+//!
+//! Entries are extracted from their local file headers as the archive is
+//! received, so the archive never has to be stored. The end of stored entries
+//! without a size in the local file header is found by scanning for a data
+//! descriptor matching the data read so far.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use flate2::{Decompress, FlushDecompress, Status};
+
+use crate::archive::relative_path;
+use crate::{Error, Result};
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0606_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+const ZIP64_EXTRA_FIELD_TAG: u16 = 0x0001;
+
+/// Length of a ZIP64 data descriptor with signature.
+const MAX_DESCRIPTOR_LEN: usize = 24;
+
+const OUTPUT_BUFFER_SIZE: usize = 64 << 10; // 64 KiB
+
+/// Extracts a ZIP archive into a directory from chunks fed with
+/// [`ZipExtractor::write`].
+pub(crate) struct ZipExtractor {
+    dest: PathBuf,
+    buf: Vec<u8>,
+    output: Vec<u8>,
+    state: State,
+}
+
+enum State {
+    Header,
+    Data(Box<Current>),
+    Descriptor(Box<Current>),
+    Done,
+}
+
+struct Current {
+    file: Option<File>,
+    flags: u16,
+    zip64: bool,
+    crc32: u32,
+    method: Method,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    hasher: crc32fast::Hasher,
+}
+
+impl Current {
+    /// Returns `true` if the data descriptor has 64-bit sizes.
+    fn has_zip64_descriptor(&self) -> bool {
+        self.zip64
+            || self.compressed_size > u32::MAX as u64
+            || self.uncompressed_size > u32::MAX as u64
+    }
+}
+
+enum Method {
+    Stored { remaining: u64 },
+    StoredUntilDescriptor,
+    Deflated(Box<Decompress>),
+}
+
+impl ZipExtractor {
+    /// Creates a new [`ZipExtractor`] extracting into `dest`.
+    pub fn new<P: Into<PathBuf>>(dest: P) -> Self {
+        Self {
+            dest: dest.into(),
+            buf: Vec::new(),
+            output: vec![0; OUTPUT_BUFFER_SIZE],
+            state: State::Header,
+        }
+    }
+
+    /// Extracts the next chunk of the archive.
+    pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.buf.extend_from_slice(data);
+
+        let buf = mem::take(&mut self.buf);
+        let mut input = &buf[..];
+        let result = loop {
+            match self.step(input) {
+                Ok(Some(consumed)) => input = &input[consumed..],
+                Ok(None) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+        self.buf = input.to_vec();
+        result
+    }
+
+    /// Checks that the whole archive was extracted.
+    pub fn finish(self) -> Result<()> {
+        match self.state {
+            State::Done => Ok(()),
+            _ => Err(invalid_data("unexpected end of archive")),
+        }
+    }
+
+    /// Processes `input` and returns the number of bytes consumed, or `None`
+    /// if more input is needed.
+    fn step(&mut self, input: &[u8]) -> Result<Option<usize>> {
+        match mem::replace(&mut self.state, State::Done) {
+            State::Header => self.header(input),
+            State::Data(current) => self.data(current, input),
+            State::Descriptor(current) => self.descriptor(current, input),
+            State::Done => Ok(if input.is_empty() {
+                None
+            } else {
+                Some(input.len())
+            }),
+        }
+    }
+
+    fn header(&mut self, input: &[u8]) -> Result<Option<usize>> {
+        self.state = State::Header;
+        if input.len() < 4 {
+            return Ok(None);
+        }
+
+        match get_u32(input, 0) {
+            LOCAL_FILE_HEADER_SIGNATURE => {}
+            CENTRAL_DIRECTORY_HEADER_SIGNATURE
+            | ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE
+            | END_OF_CENTRAL_DIRECTORY_SIGNATURE => {
+                self.state = State::Done;
+                return Ok(Some(0));
+            }
+            _ => return Err(invalid_data("invalid local file header")),
+        }
+
+        if input.len() < 30 {
+            return Ok(None);
+        }
+        let flags = get_u16(input, 6);
+        let method = get_u16(input, 8);
+        let crc32 = get_u32(input, 14);
+        let mut compressed_size = u64::from(get_u32(input, 18));
+        let name_len = get_u16(input, 26) as usize;
+        let extra_len = get_u16(input, 28) as usize;
+
+        let len = 30 + name_len + extra_len;
+        if input.len() < len {
+            return Ok(None);
+        }
+        let name = String::from_utf8_lossy(&input[30..30 + name_len]);
+
+        let mut zip64 = false;
+        let mut extra = &input[30 + name_len..len];
+        while extra.len() >= 4 {
+            let tag = get_u16(extra, 0);
+            let size = (get_u16(extra, 2) as usize).min(extra.len() - 4);
+            if tag == ZIP64_EXTRA_FIELD_TAG && size >= 16 {
+                zip64 = true;
+                compressed_size = get_u64(extra, 12);
+            }
+            extra = &extra[4 + size..];
+        }
+
+        let method = match method {
+            METHOD_STORED if flags & FLAG_DATA_DESCRIPTOR != 0 && compressed_size == 0 => {
+                Method::StoredUntilDescriptor
+            }
+            METHOD_STORED => Method::Stored {
+                remaining: compressed_size,
+            },
+            METHOD_DEFLATED => Method::Deflated(Box::new(Decompress::new(false))),
+            _ => return Err(invalid_data("unsupported compression method")),
+        };
+
+        let path = entry_path(&self.dest, &name)?;
+        let file = if name.ends_with('/') || name.ends_with('\\') {
+            fs::create_dir_all(&path)?;
+            None
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            Some(File::create(&path)?)
+        };
+
+        self.state = State::Data(Box::new(Current {
+            file,
+            flags,
+            zip64,
+            crc32,
+            method,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            hasher: crc32fast::Hasher::new(),
+        }));
+        Ok(Some(len))
+    }
+
+    fn data(&mut self, mut current: Box<Current>, input: &[u8]) -> Result<Option<usize>> {
+        let (consumed, done) = match &mut current.method {
+            Method::Stored { remaining } => {
+                let len = (*remaining).min(input.len() as u64) as usize;
+                if len == 0 && *remaining > 0 {
+                    self.state = State::Data(current);
+                    return Ok(None);
+                }
+                *remaining -= len as u64;
+                let done = *remaining == 0;
+                current.compressed_size += len as u64;
+                write_entry(&mut current, &input[..len])?;
+                (len, done)
+            }
+            Method::StoredUntilDescriptor => {
+                let (len, done) = match find_descriptor(&current, input) {
+                    Some(len) => (len, true),
+                    None => (input.len().saturating_sub(MAX_DESCRIPTOR_LEN - 1), false),
+                };
+                if len == 0 && !done {
+                    self.state = State::Data(current);
+                    return Ok(None);
+                }
+                current.compressed_size += len as u64;
+                write_entry(&mut current, &input[..len])?;
+                (len, done)
+            }
+            Method::Deflated(decompress) => {
+                let total_in = decompress.total_in();
+                let total_out = decompress.total_out();
+                let status = decompress
+                    .decompress(input, &mut self.output, FlushDecompress::None)
+                    .map_err(|err| Error::IO(io::Error::new(io::ErrorKind::InvalidData, err)))?;
+
+                let consumed = (decompress.total_in() - total_in) as usize;
+                let produced = (decompress.total_out() - total_out) as usize;
+                let done = status == Status::StreamEnd;
+                if consumed == 0 && produced == 0 && !done {
+                    self.state = State::Data(current);
+                    return Ok(None);
+                }
+                current.compressed_size += consumed as u64;
+                write_entry(&mut current, &self.output[..produced])?;
+                (consumed, done)
+            }
+        };
+
+        self.state = if !done {
+            State::Data(current)
+        } else if current.flags & FLAG_DATA_DESCRIPTOR != 0 {
+            State::Descriptor(current)
+        } else {
+            check_crc32(&current, current.crc32)?;
+            State::Header
+        };
+        Ok(Some(consumed))
+    }
+
+    fn descriptor(&mut self, current: Box<Current>, input: &[u8]) -> Result<Option<usize>> {
+        if input.len() < 4 {
+            self.state = State::Descriptor(current);
+            return Ok(None);
+        }
+
+        let offset = if get_u32(input, 0) == DATA_DESCRIPTOR_SIGNATURE {
+            4
+        } else {
+            0
+        };
+        let len = offset
+            + 4
+            + if current.has_zip64_descriptor() {
+                16
+            } else {
+                8
+            };
+        if input.len() < len {
+            self.state = State::Descriptor(current);
+            return Ok(None);
+        }
+
+        check_crc32(&current, get_u32(input, offset))?;
+        self.state = State::Header;
+        Ok(Some(len))
+    }
+}
+
+/// Finds the data descriptor ending a stored entry in `input`, which must
+/// match the CRC-32 and size of the data before it.
+fn find_descriptor(current: &Current, input: &[u8]) -> Option<usize> {
+    (0..input.len()).find(|&len| {
+        let size = current.compressed_size + len as u64;
+        let zip64 = size > u32::MAX as u64;
+        let descriptor_len = if zip64 { 24 } else { 16 };
+        if input.len() < len + descriptor_len || get_u32(input, len) != DATA_DESCRIPTOR_SIGNATURE {
+            return false;
+        }
+
+        let descriptor_size = if zip64 {
+            get_u64(input, len + 8)
+        } else {
+            u64::from(get_u32(input, len + 8))
+        };
+        if descriptor_size != size {
+            return false;
+        }
+
+        let mut hasher = current.hasher.clone();
+        hasher.update(&input[..len]);
+        hasher.finalize() == get_u32(input, len + 4)
+    })
+}
+
+/// Gets the extraction path of an archive entry in `dest`, rejecting names
+/// which would escape the destination directory.
+pub(crate) fn entry_path(dest: &Path, name: &str) -> Result<PathBuf> {
+    match relative_path(Path::new(&name.replace('\\', "/"))) {
+        Some(path) if !path.as_os_str().is_empty() => Ok(dest.join(path)),
+        _ => Err(Error::InvalidArtifactPath(PathBuf::from(name))),
+    }
+}
+
+fn write_entry(current: &mut Current, data: &[u8]) -> Result<()> {
+    current.hasher.update(data);
+    current.uncompressed_size += data.len() as u64;
+    match current.file.as_mut() {
+        Some(file) => Ok(file.write_all(data)?),
+        None if data.is_empty() => Ok(()),
+        None => Err(invalid_data("directory entry with data")),
+    }
+}
+
+fn check_crc32(current: &Current, expected: u32) -> Result<()> {
+    if current.hasher.clone().finalize() == expected {
+        Ok(())
+    } else {
+        Err(invalid_data("CRC-32 mismatch"))
+    }
+}
+
+fn invalid_data(message: &'static str) -> Error {
+    Error::IO(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+fn get_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn get_u32(buf: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&buf[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn get_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
@@ -0,0 +1,354 @@
+//! Streaming ZIP writer.
+//!
+//! Entries are written with data descriptors so that the archive can be
+//! produced in a single pass without seeking. ZIP64 records are only written
+//! when sizes, offsets or the number of entries overflow the 32-bit fields.
+
+use std::io;
+use std::mem;
+use std::time::SystemTime;
+
+use flate2::{Compress, Compression, FlushCompress, Status};
+
+use crate::datetime::DateTime;
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+const FLAG_UTF8: u16 = 1 << 11;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+const VERSION_MADE_BY_UNIX: u16 = 3 << 8;
+
+const ZIP64_EXTRA_FIELD_TAG: u16 = 0x0001;
+
+/// Writes a ZIP archive into an in-memory buffer which the caller drains with
+/// [`ZipWriter::take_output`] between writes.
+///
+/// This is the writer used for uploading artifacts, exposed for producing
+/// archives in the same format without an upload.
+pub struct ZipWriter {
+    output: Vec<u8>,
+    offset: u64,
+    compression: Compression,
+    entries: Vec<Entry>,
+    current: Option<Current>,
+}
+
+struct Entry {
+    name: String,
+    method: u16,
+    time: u16,
+    date: u16,
+    mode: u32,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    offset: u64,
+}
+
+impl Entry {
+    fn is_zip64(&self) -> bool {
+        self.has_zip64_sizes() || self.offset > u32::MAX as u64
+    }
+
+    fn has_zip64_sizes(&self) -> bool {
+        self.compressed_size > u32::MAX as u64 || self.uncompressed_size > u32::MAX as u64
+    }
+}
+
+struct Current {
+    entry: Entry,
+    hasher: crc32fast::Hasher,
+    compress: Option<Compress>,
+}
+
+impl ZipWriter {
+    /// Creates a new [`ZipWriter`] with the given compression `level` from 0
+    /// (stored) to 9.
+    pub fn new(level: u32) -> Self {
+        Self {
+            output: Vec::new(),
+            offset: 0,
+            compression: Compression::new(level.min(9)),
+            entries: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// Takes the archive bytes written since the last call.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        mem::take(&mut self.output)
+    }
+
+    /// Starts a new file entry, finishing the current one.
+    pub fn start_file(&mut self, name: &str, modified: SystemTime, mode: u32) -> io::Result<()> {
+        self.finish_file()?;
+
+        let (time, date) = dos_date_time(modified);
+        let (method, compress) = if self.compression.level() == 0 {
+            (METHOD_STORED, None)
+        } else {
+            (
+                METHOD_DEFLATED,
+                Some(Compress::new(self.compression, false)),
+            )
+        };
+
+        let entry = Entry {
+            name: name.to_string(),
+            method,
+            time,
+            date,
+            mode,
+            crc32: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            offset: self.offset,
+        };
+
+        let mut header = Vec::with_capacity(30 + entry.name.len());
+        put_u32(&mut header, LOCAL_FILE_HEADER_SIGNATURE);
+        put_u16(&mut header, VERSION_DEFAULT);
+        put_u16(&mut header, FLAG_DATA_DESCRIPTOR | FLAG_UTF8);
+        put_u16(&mut header, entry.method);
+        put_u16(&mut header, entry.time);
+        put_u16(&mut header, entry.date);
+        put_u32(&mut header, 0); // crc-32, in data descriptor
+        put_u32(&mut header, 0); // compressed size, in data descriptor
+        put_u32(&mut header, 0); // uncompressed size, in data descriptor
+        put_u16(&mut header, entry.name.len() as u16);
+        put_u16(&mut header, 0); // extra field length
+        header.extend_from_slice(entry.name.as_bytes());
+        self.put(&header);
+
+        self.current = Some(Current {
+            entry,
+            hasher: crc32fast::Hasher::new(),
+            compress,
+        });
+        Ok(())
+    }
+
+    /// Writes data of the current file entry.
+    pub fn write_file(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut current = self
+            .current
+            .take()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "no file started"))?;
+
+        current.hasher.update(data);
+        current.entry.uncompressed_size += data.len() as u64;
+        let result = self.compress(&mut current, data, FlushCompress::None);
+
+        self.current = Some(current);
+        result
+    }
+
+    /// Finishes the current file entry, if any.
+    pub fn finish_file(&mut self) -> io::Result<()> {
+        let mut current = match self.current.take() {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+
+        self.compress(&mut current, &[], FlushCompress::Finish)?;
+
+        let mut entry = current.entry;
+        entry.crc32 = current.hasher.finalize();
+
+        let mut descriptor = Vec::with_capacity(24);
+        put_u32(&mut descriptor, DATA_DESCRIPTOR_SIGNATURE);
+        put_u32(&mut descriptor, entry.crc32);
+        if entry.has_zip64_sizes() {
+            put_u64(&mut descriptor, entry.compressed_size);
+            put_u64(&mut descriptor, entry.uncompressed_size);
+        } else {
+            put_u32(&mut descriptor, entry.compressed_size as u32);
+            put_u32(&mut descriptor, entry.uncompressed_size as u32);
+        }
+        self.put(&descriptor);
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Finishes the archive by writing the central directory.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.finish_file()?;
+
+        let central_directory_offset = self.offset;
+        let mut central_directory = Vec::new();
+        for entry in &self.entries {
+            let zip64 = entry.is_zip64();
+
+            let mut extra = Vec::new();
+            if zip64 {
+                put_u16(&mut extra, ZIP64_EXTRA_FIELD_TAG);
+                put_u16(&mut extra, 24);
+                put_u64(&mut extra, entry.uncompressed_size);
+                put_u64(&mut extra, entry.compressed_size);
+                put_u64(&mut extra, entry.offset);
+            }
+
+            let version = if zip64 {
+                VERSION_ZIP64
+            } else {
+                VERSION_DEFAULT
+            };
+            put_u32(&mut central_directory, CENTRAL_DIRECTORY_HEADER_SIGNATURE);
+            put_u16(&mut central_directory, VERSION_MADE_BY_UNIX | VERSION_ZIP64);
+            put_u16(&mut central_directory, version);
+            put_u16(&mut central_directory, FLAG_DATA_DESCRIPTOR | FLAG_UTF8);
+            put_u16(&mut central_directory, entry.method);
+            put_u16(&mut central_directory, entry.time);
+            put_u16(&mut central_directory, entry.date);
+            put_u32(&mut central_directory, entry.crc32);
+            put_u32(
+                &mut central_directory,
+                saturate(entry.compressed_size, zip64),
+            );
+            put_u32(
+                &mut central_directory,
+                saturate(entry.uncompressed_size, zip64),
+            );
+            put_u16(&mut central_directory, entry.name.len() as u16);
+            put_u16(&mut central_directory, extra.len() as u16);
+            put_u16(&mut central_directory, 0); // file comment length
+            put_u16(&mut central_directory, 0); // disk number start
+            put_u16(&mut central_directory, 0); // internal file attributes
+            put_u32(&mut central_directory, entry.mode << 16);
+            put_u32(&mut central_directory, saturate(entry.offset, zip64));
+            central_directory.extend_from_slice(entry.name.as_bytes());
+            central_directory.extend_from_slice(&extra);
+        }
+        self.put(&central_directory);
+
+        let central_directory_size = central_directory.len() as u64;
+        let entries = self.entries.len() as u64;
+        let zip64 = entries >= u16::MAX as u64
+            || central_directory_size >= u32::MAX as u64
+            || central_directory_offset >= u32::MAX as u64;
+
+        let mut end = Vec::with_capacity(98);
+        if zip64 {
+            let zip64_end_offset = self.offset;
+            put_u32(&mut end, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+            put_u64(&mut end, 44); // size of the remaining record
+            put_u16(&mut end, VERSION_MADE_BY_UNIX | VERSION_ZIP64);
+            put_u16(&mut end, VERSION_ZIP64);
+            put_u32(&mut end, 0); // number of this disk
+            put_u32(&mut end, 0); // disk with the central directory
+            put_u64(&mut end, entries);
+            put_u64(&mut end, entries);
+            put_u64(&mut end, central_directory_size);
+            put_u64(&mut end, central_directory_offset);
+
+            put_u32(&mut end, ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE);
+            put_u32(&mut end, 0); // disk with the zip64 end of central directory
+            put_u64(&mut end, zip64_end_offset);
+            put_u32(&mut end, 1); // total number of disks
+        }
+
+        put_u32(&mut end, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut end, 0); // number of this disk
+        put_u16(&mut end, 0); // disk with the central directory
+        put_u16(&mut end, entries.min(u16::MAX as u64) as u16);
+        put_u16(&mut end, entries.min(u16::MAX as u64) as u16);
+        put_u32(&mut end, saturate(central_directory_size, zip64));
+        put_u32(&mut end, saturate(central_directory_offset, zip64));
+        put_u16(&mut end, 0); // comment length
+        self.put(&end);
+
+        Ok(())
+    }
+
+    fn compress(
+        &mut self,
+        current: &mut Current,
+        mut input: &[u8],
+        flush: FlushCompress,
+    ) -> io::Result<()> {
+        let compress = match current.compress.as_mut() {
+            Some(compress) => compress,
+            None => {
+                current.entry.compressed_size += input.len() as u64;
+                self.put(input);
+                return Ok(());
+            }
+        };
+
+        loop {
+            self.output.reserve(input.len() / 2 + 4096);
+
+            let total_in = compress.total_in();
+            let total_out = compress.total_out();
+            let status = compress
+                .compress_vec(input, &mut self.output, flush)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+            let consumed = (compress.total_in() - total_in) as usize;
+            let produced = compress.total_out() - total_out;
+            input = &input[consumed..];
+            self.offset += produced;
+            current.entry.compressed_size += produced;
+
+            let done = match flush {
+                FlushCompress::Finish => status == Status::StreamEnd,
+                _ => input.is_empty(),
+            };
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    fn put(&mut self, data: &[u8]) {
+        self.output.extend_from_slice(data);
+        self.offset += data.len() as u64;
+    }
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Gets the 32-bit field value, which is `0xFFFFFFFF` if the value is stored in
+/// a ZIP64 record.
+fn saturate(value: u64, zip64: bool) -> u32 {
+    if zip64 {
+        u32::MAX
+    } else {
+        value as u32
+    }
+}
+
+/// Converts to MS-DOS time and date, clamped to the representable range.
+fn dos_date_time(time: SystemTime) -> (u16, u16) {
+    let dt = DateTime::from_system_time(time);
+    if dt.year < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let year = dt.year.min(2107) as u16 - 1980;
+
+    let time = ((dt.hour as u16) << 11) | ((dt.minute as u16) << 5) | (dt.second as u16 / 2);
+    let date = (year << 9) | ((dt.month as u16) << 5) | dt.day as u16;
+    (time, date)
+}
@@ -0,0 +1,483 @@
+//! # GitHub Actions artifact attestations
+//!
+//! Functions for generating [SLSA build
+//! provenance](https://slsa.dev/spec/v1.0/provenance) for build artifacts,
+//! similar to
+//! [@actions/attest](https://github.com/actions/toolkit/tree/main/packages/attest).
+//! An [in-toto
+//! statement](https://github.com/in-toto/attestation/blob/main/spec/v1/statement.md)
+//! with the digests of the artifacts and a predicate describing the workflow
+//! run is signed as a [DSSE envelope](https://github.com/secure-systems-lab/dsse)
+//! in a [Sigstore bundle](https://docs.sigstore.dev/about/bundle/).
+//!
+//! Statements are signed with a key of the caller, or keyless with a
+//! short-lived certificate issued by [Fulcio](https://docs.sigstore.dev/certificate_authority/overview/)
+//! for the OIDC ID token of the workflow run, which needs the
+//! `id-token: write` permission. Bundles are not recorded in a transparency
+//! log, and are verified offline against a [`TrustRoot`] of public keys and
+//! certificate authorities. Since Sigstore bundles with a certificate need a
+//! transparency log entry, keyless bundles have the media type
+//! `application/vnd.gha-toolkit.unlogged-bundle+json` instead, and aren't
+//! accepted by Sigstore verifiers like `gh attestation verify`. Requires the
+//! `attest` feature.
+//!
+//! ```rust,no_run
+//! use gha_toolkit::attest::{self, SigningKey, Statement, Subject};
+//! use gha_toolkit::context::Context;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let context = Context::from_env()?;
+//! let subject = Subject::from_file("target/release/octo").await?;
+//! let statement = Statement::provenance(&context, vec![subject])?;
+//!
+//! let key = if SigningKey::keyless_available() {
+//!     SigningKey::keyless(attest::PUBLIC_GOOD_FULCIO_URL).await?
+//! } else {
+//!     SigningKey::from_pem(&std::fs::read("signing-key.pem")?)?
+//! };
+//! let bundle = attest::sign(&statement, &key)?;
+//! std::fs::write("octo.sigstore.json", serde_json::to_vec(&bundle)?)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Verifying the bundle of an artifact with the public key:
+//!
+//! ```rust,no_run
+//! use gha_toolkit::attest::{Bundle, Subject, TrustRoot};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let trust_root = TrustRoot::new().public_key(&std::fs::read("signing-key.pub")?)?;
+//! let bundle: Bundle = serde_json::from_slice(&std::fs::read("octo.sigstore.json")?)?;
+//!
+//! let statement = trust_root.verify(&bundle)?;
+//! let subject = Subject::from_file("octo").await?;
+//! assert!(statement.subject.contains(&subject));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::path::Path;
+
+use openssl::ec::{EcGroup, EcKey};
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, PKeyRef, Private, Public};
+use openssl::sign::{Signer, Verifier};
+use openssl::x509::X509;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{debug, instrument};
+
+use crate::context::Context;
+use crate::core::{get_id_token, secrets};
+use crate::io::sha256_file;
+use crate::transport::ClientOptions;
+use crate::{Error, Result};
+
+mod bundle;
+
+pub use self::bundle::*;
+
+/// Type of in-toto statements.
+pub const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+
+/// Predicate type of SLSA v1 build provenance.
+pub const SLSA_PROVENANCE_TYPE: &str = "https://slsa.dev/provenance/v1";
+
+/// Build type of GitHub Actions workflow runs.
+pub const GITHUB_WORKFLOW_BUILD_TYPE: &str = "https://actions.github.io/buildtypes/workflow/v1";
+
+/// URL of the public-good instance of Fulcio.
+pub const PUBLIC_GOOD_FULCIO_URL: &str = "https://fulcio.sigstore.dev";
+
+/// Payload type of in-toto statements in DSSE envelopes.
+const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// Audience of OIDC ID tokens for Fulcio.
+const FULCIO_AUDIENCE: &str = "sigstore";
+
+/// Artifact of an in-toto statement.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Subject {
+    /// Artifact name, e.g. the file name.
+    pub name: String,
+
+    /// Digests by algorithm, e.g. `sha256` to the hex digest.
+    pub digest: BTreeMap<String, String>,
+}
+
+impl Subject {
+    /// Creates a new [`Subject`] with the hex SHA-256 digest of the artifact.
+    pub fn sha256<N: Into<String>, D: Into<String>>(name: N, digest: D) -> Self {
+        Self {
+            name: name.into(),
+            digest: [("sha256".to_string(), digest.into())]
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Creates a new [`Subject`] for a file, named by its file name.
+    pub async fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self::sha256(name, sha256_file(path).await?))
+    }
+}
+
+/// in-toto statement about artifacts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Statement {
+    /// Statement type, [`STATEMENT_TYPE`].
+    #[serde(rename = "_type")]
+    pub type_: String,
+
+    /// Artifacts the statement is about.
+    pub subject: Vec<Subject>,
+
+    /// Predicate type, e.g. [`SLSA_PROVENANCE_TYPE`].
+    pub predicate_type: String,
+
+    /// Predicate, e.g. a [`Provenance`].
+    pub predicate: Value,
+}
+
+impl Statement {
+    /// Creates a new [`Statement`] about `subject`.
+    pub fn new<T: Into<String>>(
+        subject: Vec<Subject>,
+        predicate_type: T,
+        predicate: Value,
+    ) -> Self {
+        Self {
+            type_: STATEMENT_TYPE.into(),
+            subject,
+            predicate_type: predicate_type.into(),
+            predicate,
+        }
+    }
+
+    /// Creates a new [`Statement`] with the SLSA build [`Provenance`] of the
+    /// workflow run of `context`.
+    pub fn provenance(context: &Context, subject: Vec<Subject>) -> Result<Self> {
+        let predicate = serde_json::to_value(Provenance::from_context(context))?;
+        Ok(Self::new(subject, SLSA_PROVENANCE_TYPE, predicate))
+    }
+}
+
+/// SLSA v1 build provenance predicate.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// Inputs of the build.
+    pub build_definition: BuildDefinition,
+
+    /// Details of the build run.
+    pub run_details: RunDetails,
+}
+
+/// Inputs of a build in a [`Provenance`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildDefinition {
+    /// Build type, e.g. [`GITHUB_WORKFLOW_BUILD_TYPE`].
+    pub build_type: String,
+
+    /// Parameters under the control of the caller of the build.
+    pub external_parameters: Value,
+
+    /// Parameters under the control of the build platform.
+    #[serde(default)]
+    pub internal_parameters: Value,
+
+    /// Artifacts the build depends on, e.g. the source commit.
+    #[serde(default)]
+    pub resolved_dependencies: Vec<ResourceDescriptor>,
+}
+
+/// Details of a build run in a [`Provenance`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunDetails {
+    /// Build platform.
+    pub builder: Builder,
+
+    /// Metadata of the build run.
+    #[serde(default)]
+    pub metadata: BuildMetadata,
+}
+
+/// Build platform of a [`Provenance`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Builder {
+    /// Builder ID, e.g. the URL of the workflow file at its ref.
+    pub id: String,
+}
+
+/// Metadata of a build run in a [`Provenance`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildMetadata {
+    /// Unique ID of the build run, e.g. the URL of the workflow run attempt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invocation_id: Option<String>,
+}
+
+/// Artifact in a [`Provenance`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ResourceDescriptor {
+    /// Artifact URI, e.g. `git+https://github.com/octo-org/octo-repo@refs/heads/main`.
+    pub uri: String,
+
+    /// Digests by algorithm, e.g. `gitCommit` to the commit SHA.
+    pub digest: BTreeMap<String, String>,
+}
+
+impl Provenance {
+    /// Creates the [`Provenance`] of the workflow run of `context` with the
+    /// [`GITHUB_WORKFLOW_BUILD_TYPE`].
+    pub fn from_context(context: &Context) -> Self {
+        let server_url = context.server_url.trim_end_matches('/');
+
+        // e.g. octo-org/octo-repo/.github/workflows/ci.yml@refs/heads/main
+        let (workflow_path, workflow_ref) = context
+            .workflow_ref
+            .split_once('@')
+            .unwrap_or((&context.workflow_ref, &context.ref_));
+        let mut segments = workflow_path.splitn(3, '/');
+        let workflow_repository = match (segments.next(), segments.next()) {
+            (Some(owner), Some(repo)) => format!("{owner}/{repo}"),
+            _ => context.repository.clone(),
+        };
+        let workflow_path = segments.next().unwrap_or_default();
+
+        Self {
+            build_definition: BuildDefinition {
+                build_type: GITHUB_WORKFLOW_BUILD_TYPE.into(),
+                external_parameters: json!({
+                    "workflow": {
+                        "ref": workflow_ref,
+                        "repository": format!("{server_url}/{workflow_repository}"),
+                        "path": workflow_path,
+                    },
+                }),
+                internal_parameters: json!({
+                    "github": {
+                        "event_name": context.event_name,
+                        "repository_id": context.repository_id.to_string(),
+                        "repository_owner_id": context.repository_owner_id.to_string(),
+                        "runner_environment": context.runner_environment,
+                    },
+                }),
+                resolved_dependencies: vec![ResourceDescriptor {
+                    uri: format!("git+{server_url}/{}@{}", context.repository, context.ref_),
+                    digest: [("gitCommit".to_string(), context.sha.clone())]
+                        .into_iter()
+                        .collect(),
+                }],
+            },
+            run_details: RunDetails {
+                builder: Builder {
+                    id: format!("{server_url}/{}", context.workflow_ref),
+                },
+                metadata: BuildMetadata {
+                    invocation_id: Some(format!(
+                        "{server_url}/{}/actions/runs/{}/attempts/{}",
+                        context.repository, context.run_id, context.run_attempt
+                    )),
+                },
+            },
+        }
+    }
+}
+
+/// Private key signing statements, with the material to verify its
+/// signatures.
+///
+/// The private key is never written by the [`Debug`][fmt::Debug]
+/// implementation.
+pub struct SigningKey {
+    key: PKey<Private>,
+    verification_material: VerificationMaterial,
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("key", &secrets::MASK)
+            .field("verification_material", &self.verification_material)
+            .finish()
+    }
+}
+
+impl SigningKey {
+    /// Creates a new [`SigningKey`] from a PEM encoded ECDSA, Ed25519 or RSA
+    /// private key.
+    ///
+    /// Signatures are verified with the public key, identified in bundles by
+    /// its [`key_id`].
+    pub fn from_pem(private_key_pem: &[u8]) -> Result<Self> {
+        let key = PKey::private_key_from_pem(private_key_pem)?;
+        let hint = key_id(&key)?;
+        Ok(Self {
+            key,
+            verification_material: VerificationMaterial::public_key(hint),
+        })
+    }
+
+    /// Whether an OIDC ID token for [`keyless`][Self::keyless] signing is
+    /// available, i.e. the workflow run has the `id-token: write`
+    /// permission.
+    pub fn keyless_available() -> bool {
+        env::var_os("ACTIONS_ID_TOKEN_REQUEST_URL").is_some()
+            && env::var_os("ACTIONS_ID_TOKEN_REQUEST_TOKEN").is_some()
+    }
+
+    /// Creates a new ephemeral [`SigningKey`] with a certificate issued by
+    /// the Fulcio instance at `fulcio_url`, e.g.
+    /// [`PUBLIC_GOOD_FULCIO_URL`], for the OIDC ID token of the workflow
+    /// run.
+    ///
+    /// The certificate is valid for 10 minutes and identifies the workflow
+    /// run.
+    #[instrument]
+    pub async fn keyless(fulcio_url: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct SigningCertificate {
+            signed_certificate_embedded_sct: Option<SignedCertificate>,
+            signed_certificate_detached_sct: Option<SignedCertificate>,
+        }
+
+        #[derive(Deserialize)]
+        struct SignedCertificate {
+            chain: CertificateChain,
+        }
+
+        #[derive(Deserialize)]
+        struct CertificateChain {
+            certificates: Vec<String>,
+        }
+
+        let id_token = get_id_token(Some(FULCIO_AUDIENCE)).await?;
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+        // Proof of possession of the private key for the token subject
+        let proof = sign_bytes(&key, id_token.claims.sub.as_bytes())?;
+        let request = json!({
+            "credentials": {
+                "oidcIdentityToken": id_token.token,
+            },
+            "publicKeyRequest": {
+                "publicKey": {
+                    "algorithm": "ECDSA",
+                    "content": String::from_utf8_lossy(&key.public_key_to_pem()?),
+                },
+                "proofOfPossession": base64::encode(proof),
+            },
+        });
+
+        let url = format!("{}/api/v2/signingCert", fulcio_url.trim_end_matches('/'));
+        let client = ClientOptions::default().build()?;
+        let response = client.post(url).json(&request).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = secrets::redact(&response.text().await.unwrap_or_default()).into_owned();
+            return Err(Error::FulcioStatus { status, message });
+        }
+
+        let response: SigningCertificate = response.json().await?;
+        let certificate = response
+            .signed_certificate_embedded_sct
+            .or(response.signed_certificate_detached_sct)
+            .and_then(|signed| signed.chain.certificates.into_iter().next())
+            .ok_or_else(|| Error::FulcioStatus {
+                status,
+                message: "Response has no certificate".into(),
+            })?;
+        let certificate = X509::from_pem(certificate.as_bytes())?.to_der()?;
+        debug!("Issued signing certificate");
+
+        Ok(Self {
+            key,
+            verification_material: VerificationMaterial::certificate(certificate),
+        })
+    }
+}
+
+/// Signs an in-toto statement and returns it as a Sigstore bundle, or a
+/// bundle of the same format with its own media type if `key` is
+/// [keyless][SigningKey::keyless].
+pub fn sign(statement: &Statement, key: &SigningKey) -> Result<Bundle> {
+    let payload = serde_json::to_vec(statement)?;
+    let sig = sign_bytes(&key.key, &pae(PAYLOAD_TYPE, &payload))?;
+
+    let media_type = if key.verification_material.certificate.is_some() {
+        UNLOGGED_BUNDLE_MEDIA_TYPE
+    } else {
+        BUNDLE_MEDIA_TYPE
+    };
+    Ok(Bundle {
+        media_type: media_type.into(),
+        verification_material: key.verification_material.clone(),
+        dsse_envelope: Envelope {
+            payload: base64::encode(payload),
+            payload_type: PAYLOAD_TYPE.into(),
+            signatures: vec![Signature {
+                sig: base64::encode(sig),
+                keyid: String::new(),
+            }],
+        },
+    })
+}
+
+/// Gets the ID of a public key, the hex SHA-256 digest of its DER encoding.
+pub fn key_id<T>(key: &PKeyRef<T>) -> Result<String>
+where
+    T: openssl::pkey::HasPublic,
+{
+    use sha2::Digest as _;
+    let der = key.public_key_to_der()?;
+    Ok(hex::encode(&sha2::Sha256::digest(&der)[..]))
+}
+
+/// Gets the DSSE pre-authentication encoding of a payload, which is signed
+/// instead of the payload.
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut pae = format!(
+        "DSSEv1 {} {payload_type} {} ",
+        payload_type.len(),
+        payload.len()
+    )
+    .into_bytes();
+    pae.extend_from_slice(payload);
+    pae
+}
+
+/// Signs `data` with SHA-256, or with Ed25519 for Ed25519 keys.
+fn sign_bytes(key: &PKeyRef<Private>, data: &[u8]) -> Result<Vec<u8>> {
+    let mut signer = match key.id() {
+        Id::ED25519 => Signer::new_without_digest(key)?,
+        _ => Signer::new(openssl::hash::MessageDigest::sha256(), key)?,
+    };
+    Ok(signer.sign_oneshot_to_vec(data)?)
+}
+
+/// Verifies a signature of `data` created by [`sign_bytes`].
+fn verify_bytes(key: &PKeyRef<Public>, data: &[u8], signature: &[u8]) -> Result<bool> {
+    let mut verifier = match key.id() {
+        Id::ED25519 => Verifier::new_without_digest(key)?,
+        _ => Verifier::new(openssl::hash::MessageDigest::sha256(), key)?,
+    };
+    Ok(verifier.verify_oneshot(signature, data)?)
+}
@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use openssl::asn1::{Asn1Time, Asn1TimeRef};
+use openssl::pkey::{PKey, Public};
+use openssl::stack::Stack;
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::verify::X509VerifyParam;
+use openssl::x509::{X509Ref, X509StoreContext, X509};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{key_id, pae, verify_bytes, Statement, PAYLOAD_TYPE, STATEMENT_TYPE};
+use crate::{Error, Result};
+
+/// Media type of the bundles signed with a key by this module.
+pub(super) const BUNDLE_MEDIA_TYPE: &str = "application/vnd.dev.sigstore.bundle.v0.3+json";
+
+/// Media type of the keyless bundles created by this module. A Sigstore
+/// bundle v0.3 with a certificate needs a transparency log entry or timestamp,
+/// which these bundles don't have.
+pub(super) const UNLOGGED_BUNDLE_MEDIA_TYPE: &str =
+    "application/vnd.gha-toolkit.unlogged-bundle+json";
+
+/// Media type prefix of all Sigstore bundle versions.
+const BUNDLE_MEDIA_TYPE_PREFIX: &str = "application/vnd.dev.sigstore.bundle";
+
+/// Sigstore bundle with a DSSE envelope.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bundle {
+    /// Bundle media type, e.g.
+    /// `application/vnd.dev.sigstore.bundle.v0.3+json`, or
+    /// `application/vnd.gha-toolkit.unlogged-bundle+json` for keyless
+    /// bundles without a transparency log entry.
+    pub media_type: String,
+
+    /// Material to verify the signatures.
+    pub verification_material: VerificationMaterial,
+
+    /// Signed statement.
+    pub dsse_envelope: Envelope,
+}
+
+impl Bundle {
+    /// Decodes the statement of the envelope without verifying it.
+    pub fn statement(&self) -> Result<Statement> {
+        let payload = base64::decode(&self.dsse_envelope.payload)?;
+        Ok(serde_json::from_slice(&payload)?)
+    }
+
+    /// Gets the identity in the signing certificate, e.g. the URL of the
+    /// workflow file for certificates issued by Fulcio for GitHub Actions,
+    /// or `None` if signed with a public key.
+    pub fn certificate_identity(&self) -> Result<Option<String>> {
+        let leaf = match self
+            .verification_material
+            .certificates()?
+            .into_iter()
+            .next()
+        {
+            Some(leaf) => leaf,
+            None => return Ok(None),
+        };
+        Ok(certificate_identity(&leaf))
+    }
+}
+
+/// Material to verify the signatures of a [`Bundle`], either a public key
+/// identifier or a certificate.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMaterial {
+    /// Identifier of the public key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<PublicKeyIdentifier>,
+
+    /// Signing certificate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<X509Certificate>,
+
+    /// Signing certificate and its chain, in bundles before v0.3.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x509_certificate_chain: Option<X509CertificateChain>,
+
+    /// Transparency log entries, empty for bundles created by this module.
+    #[serde(default)]
+    pub tlog_entries: Vec<Value>,
+}
+
+impl VerificationMaterial {
+    pub(super) fn public_key(hint: String) -> Self {
+        Self {
+            public_key: Some(PublicKeyIdentifier { hint }),
+            ..Default::default()
+        }
+    }
+
+    pub(super) fn certificate(der: Vec<u8>) -> Self {
+        Self {
+            certificate: Some(X509Certificate {
+                raw_bytes: base64::encode(der),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Decodes the signing certificate followed by its chain.
+    fn certificates(&self) -> Result<Vec<X509>> {
+        let certificates = match (&self.certificate, &self.x509_certificate_chain) {
+            (Some(certificate), _) => vec![certificate],
+            (None, Some(chain)) => chain.certificates.iter().collect(),
+            (None, None) => Vec::new(),
+        };
+        certificates
+            .into_iter()
+            .map(|certificate| Ok(X509::from_der(&base64::decode(&certificate.raw_bytes)?)?))
+            .collect()
+    }
+}
+
+/// Identifier of a public key in [`VerificationMaterial`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PublicKeyIdentifier {
+    /// Key ID, see [`key_id`][super::key_id].
+    pub hint: String,
+}
+
+/// DER encoded X.509 certificate in [`VerificationMaterial`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X509Certificate {
+    /// Base64 encoded DER certificate.
+    pub raw_bytes: String,
+}
+
+/// X.509 certificate chain in [`VerificationMaterial`], starting with the
+/// signing certificate.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct X509CertificateChain {
+    /// Certificates of the chain.
+    pub certificates: Vec<X509Certificate>,
+}
+
+/// DSSE envelope of a signed payload.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    /// Base64 encoded payload.
+    pub payload: String,
+
+    /// Payload type, `application/vnd.in-toto+json` for statements.
+    pub payload_type: String,
+
+    /// Signatures of the payload.
+    pub signatures: Vec<Signature>,
+}
+
+/// Signature in a DSSE [`Envelope`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Signature {
+    /// Base64 encoded signature.
+    pub sig: String,
+
+    /// Key ID, empty if identified by the [`VerificationMaterial`].
+    #[serde(default)]
+    pub keyid: String,
+}
+
+/// Public keys and certificate authorities trusted to sign bundles.
+///
+/// Certificates are verified at the start of their validity, since bundles
+/// without transparency log entries have no trusted signing time.
+///
+/// A certificate authority like Fulcio issues certificates for any workflow
+/// of any repository, so certificates are only trusted with an expected
+/// [certificate identity][TrustRoot::certificate_identity] and the code
+/// signing extended key usage.
+#[derive(Clone, Default)]
+pub struct TrustRoot {
+    /// Public keys by key ID.
+    keys: HashMap<String, PKey<Public>>,
+    certificate_authorities: Vec<X509>,
+    certificate_identity: Option<String>,
+}
+
+impl fmt::Debug for TrustRoot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrustRoot")
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .field(
+                "certificate_authorities",
+                &self.certificate_authorities.len(),
+            )
+            .field("certificate_identity", &self.certificate_identity)
+            .finish()
+    }
+}
+
+impl TrustRoot {
+    /// Creates a new empty [`TrustRoot`].
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Trusts a PEM encoded public key.
+    pub fn public_key(mut self, public_key_pem: &[u8]) -> Result<Self> {
+        let key = PKey::public_key_from_pem(public_key_pem)?;
+        self.keys.insert(key_id(&key)?, key);
+        Ok(self)
+    }
+
+    /// Trusts PEM encoded certificate authorities, e.g. the root and
+    /// intermediate certificates of a Fulcio instance.
+    ///
+    /// Requires a [certificate identity][Self::certificate_identity].
+    pub fn certificate_authorities(mut self, certificates_pem: &[u8]) -> Result<Self> {
+        self.certificate_authorities
+            .extend(X509::stack_from_pem(certificates_pem)?);
+        Ok(self)
+    }
+
+    /// Only trusts certificates with the given identity, e.g.
+    /// `https://github.com/octo-org/octo-repo/.github/workflows/release.yml@refs/heads/main`
+    /// for the release workflow on the main branch.
+    ///
+    /// See [`Bundle::certificate_identity`].
+    pub fn certificate_identity<T: Into<String>>(mut self, identity: T) -> Self {
+        self.certificate_identity = Some(identity.into());
+        self
+    }
+
+    /// Verifies the signature of a bundle and returns its statement.
+    ///
+    /// Fails with [`Error::AttestationVerification`] if the bundle isn't
+    /// signed by a trusted key or certificate, or the certificate doesn't
+    /// have the expected [identity][Self::certificate_identity], and if
+    /// certificate authorities are trusted without an expected identity. The
+    /// subjects of the statement are left to the caller to check.
+    pub fn verify(&self, bundle: &Bundle) -> Result<Statement> {
+        if !self.certificate_authorities.is_empty() && self.certificate_identity.is_none() {
+            return Err(Error::AttestationVerification(
+                "missing certificate identity",
+            ));
+        }
+        if !bundle.media_type.starts_with(BUNDLE_MEDIA_TYPE_PREFIX)
+            && bundle.media_type != UNLOGGED_BUNDLE_MEDIA_TYPE
+        {
+            return Err(Error::AttestationVerification(
+                "unsupported bundle media type",
+            ));
+        }
+        let envelope = &bundle.dsse_envelope;
+        if envelope.payload_type != PAYLOAD_TYPE {
+            return Err(Error::AttestationVerification("unsupported payload type"));
+        }
+
+        let key = self.verification_key(&bundle.verification_material)?;
+        let payload = base64::decode(&envelope.payload)?;
+        let pae = pae(&envelope.payload_type, &payload);
+        let mut verified = false;
+        for signature in &envelope.signatures {
+            let sig = base64::decode(&signature.sig)?;
+            if verify_bytes(&key, &pae, &sig).unwrap_or(false) {
+                verified = true;
+                break;
+            }
+        }
+        if !verified {
+            return Err(Error::AttestationVerification("invalid signature"));
+        }
+
+        let statement: Statement = serde_json::from_slice(&payload)?;
+        if statement.type_ != STATEMENT_TYPE {
+            return Err(Error::AttestationVerification("unsupported statement type"));
+        }
+        Ok(statement)
+    }
+
+    /// Gets the trusted public key verifying the signatures of a bundle.
+    fn verification_key(&self, material: &VerificationMaterial) -> Result<PKey<Public>> {
+        let mut certificates = material.certificates()?.into_iter();
+        if let Some(leaf) = certificates.next() {
+            let mut chain = Stack::new()?;
+            for certificate in certificates {
+                chain.push(certificate)?;
+            }
+
+            let mut store = X509StoreBuilder::new()?;
+            for ca in &self.certificate_authorities {
+                store.add_cert(ca.clone())?;
+            }
+            let mut param = X509VerifyParam::new()?;
+            param.set_time(unix_time(leaf.not_before())? as _);
+            store.set_param(&param)?;
+            let store = store.build();
+
+            let mut context = X509StoreContext::new()?;
+            if !context.init(&store, &leaf, &chain, |context| context.verify_cert())? {
+                return Err(Error::AttestationVerification("untrusted certificate"));
+            }
+            if !has_code_signing_usage(&leaf)? {
+                return Err(Error::AttestationVerification(
+                    "certificate not for code signing",
+                ));
+            }
+            if certificate_identity(&leaf) != self.certificate_identity {
+                return Err(Error::AttestationVerification(
+                    "untrusted certificate identity",
+                ));
+            }
+            return Ok(leaf.public_key()?);
+        }
+
+        match &material.public_key {
+            Some(public_key) => self
+                .keys
+                .get(&public_key.hint)
+                .cloned()
+                .ok_or(Error::AttestationVerification("untrusted public key")),
+            None => Err(Error::AttestationVerification(
+                "missing verification material",
+            )),
+        }
+    }
+}
+
+/// Gets the first URI or email subject alternative name of a certificate.
+fn certificate_identity(certificate: &X509Ref) -> Option<String> {
+    certificate.subject_alt_names().and_then(|names| {
+        names
+            .iter()
+            .find_map(|name| name.uri().or_else(|| name.email()).map(ToString::to_string))
+    })
+}
+
+/// Checks that a certificate has the code signing extended key usage, like
+/// the certificates issued by Fulcio.
+fn has_code_signing_usage(certificate: &X509Ref) -> Result<bool> {
+    // The extension isn't exposed otherwise by the `openssl` crate
+    let text = String::from_utf8_lossy(&certificate.to_text()?).into_owned();
+    let mut lines = text.lines();
+    let has_usage = lines.any(|line| line.trim().starts_with("X509v3 Extended Key Usage:"))
+        && lines.next().map_or(false, |usages| {
+            usages
+                .split(',')
+                .any(|usage| usage.trim() == "Code Signing")
+        });
+    Ok(has_usage)
+}
+
+/// Converts an ASN.1 time to seconds since the Unix epoch.
+fn unix_time(time: &Asn1TimeRef) -> Result<i64> {
+    let diff = Asn1Time::from_unix(0)?.diff(time)?;
+    Ok(i64::from(diff.days) * 86_400 + i64::from(diff.secs))
+}
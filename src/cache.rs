@@ -7,6 +7,12 @@
 //! workflows](https://docs.github.com/en/actions/using-workflows/caching-dependencies-to-speed-up-workflows)
 //! for the official GitHub documentation.
 //!
+//! Failed requests and chunks with an invalid size or checksum are retried
+//! according to a [`RetryPolicy`], by default an exponential
+//! [`BackoffPolicy`] for [transient][is_transient] errors. Expired archive
+//! URLs are only refreshed by [`CacheClient::get_entry`] with a custom
+//! policy.
+//!
 //! ```rust
 //! use std::io::Cursor;
 //! use std::time::SystemTime;
@@ -45,10 +51,12 @@
 //! ```
 
 use std::env;
+use std::fmt;
 use std::io::{prelude::*, SeekFrom};
 use std::ops::DerefMut as _;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use async_lock::{Mutex, Semaphore};
 use bytes::Bytes;
@@ -56,22 +64,21 @@ use futures::prelude::*;
 use http::{header, header::HeaderName, HeaderMap, HeaderValue, StatusCode};
 use hyperx::header::{ContentRange, ContentRangeSpec, Header as _};
 use reqwest::{Body, Url};
-use reqwest_middleware::ClientWithMiddleware;
-use reqwest_retry::policies::ExponentialBackoff;
-#[cfg(doc)]
-use reqwest_retry::policies::ExponentialBackoffBuilder;
-use reqwest_retry::RetryTransientMiddleware;
-use reqwest_retry_after::RetryAfterMiddleware;
-use reqwest_tracing::TracingMiddleware;
+use reqwest_middleware::{ClientWithMiddleware, Middleware};
 use sha2::{Digest, Sha256};
 use tracing::{debug, instrument, warn};
 
+use crate::core::secrets::{self, Redacted};
+use crate::transport::{self, ClientOptions};
 use crate::{Error, Result};
 
 use serde::{Deserialize, Serialize};
 
+mod retry;
+
+pub use self::retry::*;
+
 const BASE_URL_PATH: &str = "/_apis/artifactcache/";
-const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_CRATE_NAME"), "/", env!("CARGO_PKG_VERSION"));
 const DEFAULT_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
 const DEFAULT_UPLOAD_TIMEOUT: Duration = Duration::from_secs(60);
 
@@ -124,8 +131,11 @@ struct CacheQuery<'a> {
 
 /// GitHub Actions cache client builder.
 ///
+/// The access token is never written by the [`Debug`][fmt::Debug]
+/// implementation, and other registered [secrets] are redacted.
+///
 /// See [module][self] documentation.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Clone)]
 pub struct CacheClientBuilder {
     /// GitHub Actions cache API base URL.
     pub base_url: String,
@@ -142,18 +152,30 @@ pub struct CacheClientBuilder {
     /// Cache key prefixes to read.
     pub cache_from: Vec<String>,
 
-    /// Maximum number of retries.
+    /// Maximum number of retries. See [`BackoffPolicy::max_retries`].
     pub max_retries: u32,
 
-    /// Minimum retry interval. See [`ExponentialBackoff::min_retry_interval`].
+    /// Minimum retry interval. See [`BackoffPolicy::min_retry_interval`].
     pub min_retry_interval: Duration,
 
-    /// Maximum retry interval. See [`ExponentialBackoff::max_retry_interval`].
+    /// Maximum retry interval. See [`BackoffPolicy::max_retry_interval`].
     pub max_retry_interval: Duration,
 
-    /// Retry backoff factor base. See [`ExponentialBackoff::backoff_exponent`].
+    /// Retry backoff factor base. See [`BackoffPolicy::backoff_factor_base`].
     pub backoff_factor_base: u32,
 
+    /// Whether to randomize retry intervals, disabled by default. See
+    /// [`BackoffPolicy::jitter`].
+    pub retry_jitter: bool,
+
+    /// Maximum time for retrying an operation. See
+    /// [`BackoffPolicy::time_budget`].
+    pub retry_time_budget: Option<Duration>,
+
+    /// Retry policy to use instead of a [`BackoffPolicy`] with the above
+    /// options.
+    pub retry_policy: Option<Arc<dyn RetryPolicy>>,
+
     /// Maximum chunk size in bytes for downloads.
     pub download_chunk_size: u64,
 
@@ -171,6 +193,18 @@ pub struct CacheClientBuilder {
 
     /// Number of parallel uploads.
     pub upload_concurrency: u32,
+
+    /// HTTP client to use instead of building one with the user agent and
+    /// CA bundle, e.g. for connection pool limits.
+    pub client: Option<reqwest::Client>,
+
+    /// Additional middleware, run on every attempt after the tracing
+    /// middleware, e.g. for metrics.
+    pub middleware: Vec<Arc<dyn Middleware>>,
+
+    /// PEM bundle of additional root certificates, e.g. for GitHub
+    /// Enterprise Server with a private certificate authority.
+    pub ca_bundle: Option<PathBuf>,
 }
 
 impl Default for CacheClientBuilder {
@@ -178,23 +212,100 @@ impl Default for CacheClientBuilder {
         Self {
             base_url: Default::default(),
             token: Default::default(),
-            user_agent: DEFAULT_USER_AGENT.into(),
+            user_agent: transport::DEFAULT_USER_AGENT.into(),
             cache_to: None,
             cache_from: vec![],
-            max_retries: 2,
-            min_retry_interval: Duration::from_millis(50),
-            max_retry_interval: Duration::from_secs(10),
-            backoff_factor_base: 3,
+            max_retries: transport::DEFAULT_MAX_RETRIES,
+            min_retry_interval: transport::DEFAULT_MIN_RETRY_INTERVAL,
+            max_retry_interval: transport::DEFAULT_MAX_RETRY_INTERVAL,
+            backoff_factor_base: transport::DEFAULT_BACKOFF_FACTOR_BASE,
+            retry_jitter: false,
+            retry_time_budget: None,
+            retry_policy: None,
             download_chunk_size: 4 << 20, // 4 MiB
             download_chunk_timeout: DEFAULT_DOWNLOAD_TIMEOUT,
             download_concurrency: 8,
             upload_concurrency: 4,
             upload_chunk_size: 1 << 20, // 1 MiB
             upload_chunk_timeout: DEFAULT_UPLOAD_TIMEOUT,
+            client: None,
+            middleware: vec![],
+            ca_bundle: None,
         }
     }
 }
 
+impl fmt::Debug for CacheClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheClientBuilder")
+            .field("base_url", &Redacted(&self.base_url))
+            .field("token", &Redacted(secrets::MASK))
+            .field("user_agent", &Redacted(&self.user_agent))
+            .field("cache_to", &Redacted(&self.cache_to))
+            .field("cache_from", &Redacted(&self.cache_from))
+            .field("max_retries", &self.max_retries)
+            .field("min_retry_interval", &self.min_retry_interval)
+            .field("max_retry_interval", &self.max_retry_interval)
+            .field("backoff_factor_base", &self.backoff_factor_base)
+            .field("retry_jitter", &self.retry_jitter)
+            .field("retry_time_budget", &self.retry_time_budget)
+            .field("retry_policy", &self.retry_policy.is_some())
+            .field("download_chunk_size", &self.download_chunk_size)
+            .field("download_chunk_timeout", &self.download_chunk_timeout)
+            .field("download_concurrency", &self.download_concurrency)
+            .field("upload_chunk_size", &self.upload_chunk_size)
+            .field("upload_chunk_timeout", &self.upload_chunk_timeout)
+            .field("upload_concurrency", &self.upload_concurrency)
+            .field("client", &self.client)
+            .field("middleware", &self.middleware.len())
+            .field("ca_bundle", &self.ca_bundle)
+            .finish()
+    }
+}
+
+/// Compares the options of the builders. The HTTP clients aren't compared,
+/// and middleware and retry policies are compared by address.
+impl PartialEq for CacheClientBuilder {
+    fn eq(&self, other: &Self) -> bool {
+        self.base_url == other.base_url
+            && self.token == other.token
+            && self.user_agent == other.user_agent
+            && self.cache_to == other.cache_to
+            && self.cache_from == other.cache_from
+            && self.max_retries == other.max_retries
+            && self.min_retry_interval == other.min_retry_interval
+            && self.max_retry_interval == other.max_retry_interval
+            && self.backoff_factor_base == other.backoff_factor_base
+            && self.retry_jitter == other.retry_jitter
+            && self.retry_time_budget == other.retry_time_budget
+            && match (&self.retry_policy, &other.retry_policy) {
+                (Some(a), Some(b)) => same_arc(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+            && self.download_chunk_size == other.download_chunk_size
+            && self.download_chunk_timeout == other.download_chunk_timeout
+            && self.download_concurrency == other.download_concurrency
+            && self.upload_chunk_size == other.upload_chunk_size
+            && self.upload_chunk_timeout == other.upload_chunk_timeout
+            && self.upload_concurrency == other.upload_concurrency
+            && self.middleware.len() == other.middleware.len()
+            && self
+                .middleware
+                .iter()
+                .zip(&other.middleware)
+                .all(|(a, b)| same_arc(a, b))
+            && self.ca_bundle == other.ca_bundle
+    }
+}
+
+impl Eq for CacheClientBuilder {}
+
+/// Checks whether two trait objects share the same allocation, ignoring their
+/// vtables which may differ between codegen units.
+fn same_arc<T: ?Sized>(a: &Arc<T>, b: &Arc<T>) -> bool {
+    Arc::as_ptr(a) as *const u8 == Arc::as_ptr(b) as *const u8
+}
+
 impl CacheClientBuilder {
     /// Creates a new [`CacheClientBuilder`] for the given GitHub Actions cache
     /// API base URL and access token.
@@ -214,6 +325,10 @@ impl CacheClientBuilder {
     /// - `ACTIONS_CACHE_URL` - GitHub Actions cache API base URL
     /// - `ACTIONS_RUNTIME_TOKEN` - GitHub Actions access token
     /// - `SEGMENT_DOWNLOAD_TIMEOUT_MINS` - download chunk timeout
+    /// - `NODE_EXTRA_CA_CERTS` - CA bundle, see [`ca_bundle`][Self::ca_bundle]
+    ///
+    /// Proxies are read from the `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and
+    /// `NO_PROXY` environmental variables when the client is built.
     ///
     pub fn from_env() -> Result<Self> {
         let url = env::var("ACTIONS_CACHE_URL").map_err(|source| Error::VarError {
@@ -235,6 +350,10 @@ impl CacheClientBuilder {
             builder.download_chunk_timeout = timeout;
         }
 
+        if let Some(ca_bundle) = env::var_os("NODE_EXTRA_CA_CERTS").filter(|v| !v.is_empty()) {
+            builder.ca_bundle = Some(ca_bundle.into());
+        }
+
         Ok(builder)
     }
 
@@ -295,6 +414,24 @@ impl CacheClientBuilder {
         self
     }
 
+    /// Sets whether to randomize retry intervals.
+    pub fn retry_jitter(mut self, retry_jitter: bool) -> Self {
+        self.retry_jitter = retry_jitter;
+        self
+    }
+
+    /// Sets the maximum time for retrying an operation.
+    pub fn retry_time_budget(mut self, retry_time_budget: Duration) -> Self {
+        self.retry_time_budget = Some(retry_time_budget);
+        self
+    }
+
+    /// Sets the retry policy to use instead of a [`BackoffPolicy`].
+    pub fn retry_policy<P: RetryPolicy + 'static>(mut self, retry_policy: P) -> Self {
+        self.retry_policy = Some(Arc::new(retry_policy));
+        self
+    }
+
     /// Maximum chunk size in bytes for downloads.
     pub fn download_chunk_size(mut self, download_chunk_size: u64) -> Self {
         self.download_chunk_size = download_chunk_size;
@@ -331,7 +468,30 @@ impl CacheClientBuilder {
         self
     }
 
+    /// Sets the HTTP client to use instead of building one.
+    ///
+    /// The [`user_agent`][Self::user_agent] and [`ca_bundle`][Self::ca_bundle]
+    /// are ignored, but the tracing middleware is still added.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Adds middleware, run on every attempt after the tracing middleware.
+    pub fn middleware<M: Middleware>(mut self, middleware: M) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Sets the path of a PEM bundle of additional root certificates.
+    pub fn ca_bundle<P: Into<PathBuf>>(mut self, ca_bundle: P) -> Self {
+        self.ca_bundle = Some(ca_bundle.into());
+        self
+    }
+
     /// Consumes this [`CacheClientBuilder`] and build a [`CacheClient`].
+    ///
+    /// The access token is registered as a [secret][secrets::register].
     pub fn build(self) -> Result<CacheClient> {
         self.try_into()
     }
@@ -342,6 +502,7 @@ impl CacheClientBuilder {
 /// See [module][self] documentation.
 pub struct CacheClient {
     client: ClientWithMiddleware,
+    retry_policy: Arc<dyn RetryPolicy>,
     base_url: Url,
     api_headers: HeaderMap,
 
@@ -387,24 +548,37 @@ impl TryInto<CacheClient> for CacheClientBuilder {
             HeaderValue::from_static("application/json;api-version=6.0-preview.1"),
         );
 
+        secrets::register(&self.token);
+
         let auth_value = Bytes::from(format!("Bearer {}", self.token));
         let mut auth_value = header::HeaderValue::from_maybe_shared(auth_value)?;
         auth_value.set_sensitive(true);
         api_headers.insert(http::header::AUTHORIZATION, auth_value);
 
-        let retry_policy = ExponentialBackoff::builder()
-            .retry_bounds(self.min_retry_interval, self.max_retry_interval)
-            .backoff_exponent(self.backoff_factor_base)
-            .build_with_max_retries(self.max_retries);
-
-        let client = reqwest::ClientBuilder::new()
-            .user_agent(self.user_agent)
-            .build()?;
-        let client = reqwest_middleware::ClientBuilder::new(client)
-            .with(TracingMiddleware::default())
-            .with(RetryAfterMiddleware::new())
-            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-            .build();
+        // Operations are retried by the retry policy instead of the middleware
+        let options = ClientOptions {
+            user_agent: &self.user_agent,
+            max_retries: 0,
+            ca_bundle: self.ca_bundle.as_deref(),
+            ..Default::default()
+        };
+        let client = match self.client {
+            Some(client) => client,
+            None => options.build_client()?,
+        };
+        let client = options.with_middleware(client, &self.middleware);
+
+        let retry_policy = match self.retry_policy {
+            Some(retry_policy) => retry_policy,
+            None => Arc::new(BackoffPolicy {
+                max_retries: self.max_retries,
+                min_retry_interval: self.min_retry_interval,
+                max_retry_interval: self.max_retry_interval,
+                backoff_factor_base: self.backoff_factor_base,
+                jitter: self.retry_jitter,
+                time_budget: self.retry_time_budget,
+            }),
+        };
 
         let base_url = Url::parse(&format!(
             "{}{}",
@@ -414,6 +588,7 @@ impl TryInto<CacheClient> for CacheClientBuilder {
 
         Ok(CacheClient {
             client,
+            retry_policy,
             base_url,
             api_headers,
             cache_to,
@@ -468,6 +643,10 @@ impl CacheClient {
     /// Gets the cache entry identified by the given `version`.
     #[instrument(skip(self))]
     pub async fn entry(&self, version: &str) -> Result<Option<ArtifactCacheEntry>> {
+        self.retry(|_| true, || self.do_entry(version)).await
+    }
+
+    async fn do_entry(&self, version: &str) -> Result<Option<ArtifactCacheEntry>> {
         let cache_from = if let Some(cache_from) = self.cache_from.as_ref() {
             cache_from
         } else {
@@ -493,18 +672,14 @@ impl CacheClient {
             return Ok(None);
         };
         if !status.is_success() {
-            let message = response.text().await.unwrap_or_else(|err| err.to_string());
-            return Err(Error::CacheServiceStatus { status, message });
+            return Err(status_error(response).await);
         }
 
         let cache_result: ArtifactCacheEntry = response.json().await?;
         debug!("Cache Result: {}", serde_json::to_string(&cache_result)?);
 
         if let Some(cache_download_url) = cache_result.archive_location.as_ref() {
-            println!(
-                "::add-mask::{}",
-                shell_escape::escape(cache_download_url.into())
-            );
+            secrets::register(cache_download_url);
         } else {
             return Err(Error::CacheNotFound);
         }
@@ -627,15 +802,49 @@ impl CacheClient {
         Ok(chunks.concat())
     }
 
+    /// Gets the cache archive of the entry identified by the given `version`.
+    ///
+    /// Unlike [`entry`][Self::entry] followed by [`get`][Self::get], errors
+    /// of the signed archive URL, e.g. `403 Forbidden` when it expired, are
+    /// retried with a refreshed URL if the [`RetryPolicy`] allows it.
+    ///
+    /// The default [`BackoffPolicy`] only retries [transient][is_transient]
+    /// errors, so refreshing the URL requires a custom policy set with
+    /// [`CacheClientBuilder::retry_policy`]. With the default policy, the
+    /// `403 Forbidden` error is returned.
+    #[instrument(skip(self))]
+    pub async fn get_entry(&self, version: &str) -> Result<Option<Vec<u8>>> {
+        self.retry(is_forbidden, || async {
+            let archive_location = match self.entry(version).await? {
+                Some(ArtifactCacheEntry {
+                    archive_location: Some(archive_location),
+                    ..
+                }) => archive_location,
+                _ => return Ok(None),
+            };
+            self.get(&archive_location).await.map(Some)
+        })
+        .await
+    }
+
     #[instrument(skip(self, uri))]
     async fn download_first_chunk(&self, uri: Url) -> Result<(Bytes, Option<ContentRange>)> {
-        self.do_download_chunk(uri, 0, self.download_chunk_size, true)
-            .await
+        // Expired signed URLs are refreshed by `get_entry`
+        self.retry(
+            |err| !is_forbidden(err),
+            || self.do_download_chunk(uri.clone(), 0, self.download_chunk_size, true),
+        )
+        .await
     }
 
     #[instrument(skip_all, fields(uri, start, size))]
     async fn download_chunk(&self, uri: Url, start: u64, size: u64) -> Result<Bytes> {
-        let (bytes, _) = self.do_download_chunk(uri, start, size, false).await?;
+        let (bytes, _) = self
+            .retry(
+                |err| !is_forbidden(err),
+                || self.do_download_chunk(uri.clone(), start, size, false),
+            )
+            .await?;
         Ok(bytes)
     }
 
@@ -664,8 +873,7 @@ impl CacheClient {
         let status = response.status();
         let partial_content = expect_partial && status == StatusCode::PARTIAL_CONTENT;
         if !status.is_success() {
-            let message = response.text().await.unwrap_or_else(|err| err.to_string());
-            return Err(Error::CacheServiceStatus { status, message });
+            return Err(status_error(response).await);
         }
 
         let content_length = response.content_length();
@@ -739,8 +947,15 @@ impl CacheClient {
         Ok(())
     }
 
+    /// Reserves a cache entry, only retrying connection errors since a
+    /// repeated reservation conflicts with the first one.
     #[instrument(skip(self))]
     async fn reserve(&self, key: &str, version: &str, cache_size: u64) -> Result<Option<i64>> {
+        self.retry(is_connect, || self.do_reserve(key, version, cache_size))
+            .await
+    }
+
+    async fn do_reserve(&self, key: &str, version: &str, cache_size: u64) -> Result<Option<i64>> {
         let url = self.base_url.join("caches")?;
 
         let reserve_cache_request = ReserveCacheRequest {
@@ -764,8 +979,7 @@ impl CacheClient {
                 return Ok(None);
             }
             _ if !status.is_success() => {
-                let message = response.text().await.unwrap_or_else(|err| err.to_string());
-                return Err(Error::CacheServiceStatus { status, message });
+                return Err(status_error(response).await);
             }
             _ => {}
         }
@@ -841,8 +1055,17 @@ impl CacheClient {
         Ok(())
     }
 
-    #[instrument(skip(self, uri, body))]
-    async fn upload_chunk<T: Into<Body>>(
+    #[instrument(skip(self, uri, chunk))]
+    async fn upload_chunk(&self, uri: Url, chunk: Vec<u8>, start: u64, size: u64) -> Result<()> {
+        let chunk = Bytes::from(chunk);
+        self.retry(
+            |_| true,
+            || self.do_upload_chunk(uri.clone(), chunk.clone(), start, size),
+        )
+        .await
+    }
+
+    async fn do_upload_chunk<T: Into<Body>>(
         &self,
         uri: Url,
         body: T,
@@ -868,17 +1091,22 @@ impl CacheClient {
             .send()
             .await?;
 
-        let status = response.status();
-        if status.is_success() {
+        if response.status().is_success() {
             Ok(())
         } else {
-            let message = response.text().await.unwrap_or_else(|err| err.to_string());
-            Err(Error::CacheServiceStatus { status, message })
+            Err(status_error(response).await)
         }
     }
 
+    /// Commits a cache entry, only retrying connection errors since a
+    /// repeated commit fails once the first one succeeded.
     #[instrument(skip(self))]
     async fn commit(&self, cache_id: i64, cache_size: u64) -> Result<()> {
+        self.retry(is_connect, || self.do_commit(cache_id, cache_size))
+            .await
+    }
+
+    async fn do_commit(&self, cache_id: i64, cache_size: u64) -> Result<()> {
         let url = self.base_url.join(&format!("caches/{cache_id}"))?;
         let commit_cache_request = CommitCacheRequest {
             size: cache_size as i64,
@@ -892,14 +1120,52 @@ impl CacheClient {
             .send()
             .await?;
 
-        let status = response.status();
-        if status.is_success() {
+        if response.status().is_success() {
             Ok(())
         } else {
-            let message = response.text().await.unwrap_or_else(|err| err.to_string());
-            Err(Error::CacheServiceStatus { status, message })
+            Err(status_error(response).await)
         }
     }
+
+    /// Runs `operation` until it succeeds, fails with an error that isn't
+    /// `retryable`, or the retry policy gives up.
+    async fn retry<T, F, Fut>(&self, retryable: fn(&Error) -> bool, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let start = Instant::now();
+        let mut retries = 0;
+        loop {
+            let err = match operation().await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            let interval = if retryable(&err) {
+                self.retry_policy
+                    .retry_interval(&err, retries, start.elapsed())
+            } else {
+                None
+            };
+            match interval {
+                Some(interval) => {
+                    warn!("Retrying in {interval:?} after error: {err}");
+                    tokio::time::sleep(interval).await;
+                    retries += 1;
+                }
+                None => return Err(err),
+            }
+        }
+    }
+}
+
+/// Creates an [`Error::CacheServiceStatus`] from an unsuccessful response
+/// with registered secrets scrubbed from the message.
+async fn status_error(response: reqwest::Response) -> Error {
+    let status = response.status();
+    let message = response.text().await.unwrap_or_else(|err| err.to_string());
+    let message = secrets::redact(&message).into_owned();
+    Error::CacheServiceStatus { status, message }
 }
 
 fn get_cache_version(version: &str) -> String {
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use http::StatusCode;
+use rand::Rng as _;
+
+use crate::Error;
+
+/// Policy for retrying failed cache operations.
+///
+/// Operations are retried as a whole, e.g. a chunk download including
+/// verifying its size and checksum, so the policy sees the crate [`Error`]
+/// rather than the HTTP response.
+///
+/// Chunk downloads failing with `403 Forbidden`, e.g. because the signed
+/// archive URL expired, aren't retried with the same URL. They are retried by
+/// [`CacheClient::get_entry`][super::CacheClient::get_entry] with a refreshed
+/// URL if the policy allows it. Reserving and committing cache entries are
+/// only retried after connection errors, since the cache service may have
+/// applied a request that failed otherwise.
+pub trait RetryPolicy: Send + Sync {
+    /// Gets the interval before retrying an operation that failed with
+    /// `error`, or `None` to fail.
+    ///
+    /// `retries` is the number of retries so far and `elapsed` the time since
+    /// the first attempt.
+    fn retry_interval(&self, error: &Error, retries: u32, elapsed: Duration) -> Option<Duration>;
+}
+
+/// Exponential backoff [`RetryPolicy`] for [transient][is_transient] errors.
+///
+/// This is the default policy built from the retry options of the
+/// [`CacheClientBuilder`][super::CacheClientBuilder].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackoffPolicy {
+    /// Maximum number of retries.
+    pub max_retries: u32,
+
+    /// Interval before the first retry.
+    pub min_retry_interval: Duration,
+
+    /// Maximum retry interval.
+    pub max_retry_interval: Duration,
+
+    /// Retry backoff factor base.
+    pub backoff_factor_base: u32,
+
+    /// Whether to multiply each interval by a random factor between 1 and 2,
+    /// so that retries are spread out but never sooner than without jitter.
+    pub jitter: bool,
+
+    /// Maximum time from the first attempt until the last retry.
+    pub time_budget: Option<Duration>,
+}
+
+impl RetryPolicy for BackoffPolicy {
+    fn retry_interval(&self, error: &Error, retries: u32, elapsed: Duration) -> Option<Duration> {
+        if retries >= self.max_retries || !is_transient(error) {
+            return None;
+        }
+
+        let factor = self
+            .backoff_factor_base
+            .checked_pow(retries)
+            .unwrap_or(u32::MAX);
+        let mut interval = self
+            .min_retry_interval
+            .checked_mul(factor)
+            .unwrap_or(self.max_retry_interval);
+        if self.jitter {
+            interval = interval.mul_f64(rand::thread_rng().gen_range(1.0..2.0));
+        }
+        let interval = interval.min(self.max_retry_interval);
+
+        match self.time_budget {
+            Some(time_budget) if elapsed + interval > time_budget => None,
+            _ => Some(interval),
+        }
+    }
+}
+
+/// Checks whether `error` is transient, i.e. a timeout, connection error,
+/// `408 Request Timeout`, `429 Too Many Requests` or server error of the
+/// cache service, or a chunk with an invalid size or checksum.
+pub fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::CacheChunkChecksum | Error::CacheChunkSize { .. } => true,
+        Error::CacheServiceStatus { status, .. } => {
+            *status == StatusCode::REQUEST_TIMEOUT
+                || *status == StatusCode::TOO_MANY_REQUESTS
+                || status.is_server_error()
+        }
+        Error::Reqwest(err) | Error::ReqwestMiddleware(reqwest_middleware::Error::Reqwest(err)) => {
+            err.is_timeout() || err.is_connect() || err.is_request() || err.is_body()
+        }
+        _ => false,
+    }
+}
+
+/// Checks whether `error` is `403 Forbidden`, e.g. for an expired signed URL.
+pub(super) fn is_forbidden(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::CacheServiceStatus {
+            status: StatusCode::FORBIDDEN,
+            ..
+        }
+    )
+}
+
+/// Checks whether `error` is a connection error, i.e. the request provably
+/// didn't reach the cache service, so retrying it can't repeat its effects.
+pub(super) fn is_connect(error: &Error) -> bool {
+    match error {
+        Error::Reqwest(err) | Error::ReqwestMiddleware(reqwest_middleware::Error::Reqwest(err)) => {
+            err.is_connect()
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,221 @@
+//! # GitHub Actions workflow run context
+//!
+//! The [`Context`] is read from the [default environment
+//! variables](https://docs.github.com/en/actions/learn-github-actions/variables#default-environment-variables)
+//! set by the runner, with the webhook payload of the triggering event parsed
+//! into an [`Event`].
+//!
+//! ```rust,no_run
+//! # use gha_toolkit::context::*;
+//! #
+//! # fn main() -> anyhow::Result<()> {
+//! let context = Context::from_env()?;
+//!
+//! if let Event::PullRequest(event) = &context.event {
+//!     println!("Running for #{}", event.number);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{Error, Result};
+
+mod event;
+
+pub use self::event::*;
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+const DEFAULT_GRAPHQL_URL: &str = "https://api.github.com/graphql";
+const DEFAULT_SERVER_URL: &str = "https://github.com";
+
+/// GitHub Actions workflow run context.
+///
+/// See [module][self] documentation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Context {
+    /// Name of the running action or step ID, `GITHUB_ACTION`.
+    pub action: String,
+
+    /// Path of the running action, `GITHUB_ACTION_PATH`.
+    pub action_path: Option<PathBuf>,
+
+    /// Account that triggered the workflow run, `GITHUB_ACTOR`.
+    pub actor: String,
+
+    /// REST API URL, `GITHUB_API_URL`.
+    pub api_url: String,
+
+    /// Target branch of a pull request, `GITHUB_BASE_REF`.
+    pub base_ref: Option<String>,
+
+    /// Name of the triggering event, `GITHUB_EVENT_NAME`.
+    pub event_name: String,
+
+    /// Path of the webhook payload file, `GITHUB_EVENT_PATH`.
+    pub event_path: Option<PathBuf>,
+
+    /// GraphQL API URL, `GITHUB_GRAPHQL_URL`.
+    pub graphql_url: String,
+
+    /// Source branch of a pull request, `GITHUB_HEAD_REF`.
+    pub head_ref: Option<String>,
+
+    /// ID of the current job, `GITHUB_JOB`.
+    pub job: String,
+
+    /// Fully-formed ref that triggered the workflow run, `GITHUB_REF`.
+    pub ref_: String,
+
+    /// Short ref name that triggered the workflow run, `GITHUB_REF_NAME`.
+    pub ref_name: String,
+
+    /// Type of ref that triggered the workflow run, `GITHUB_REF_TYPE`.
+    pub ref_type: String,
+
+    /// Owner and repository name, `GITHUB_REPOSITORY`.
+    pub repository: String,
+
+    /// Repository ID, `GITHUB_REPOSITORY_ID`.
+    pub repository_id: u64,
+
+    /// Repository owner, `GITHUB_REPOSITORY_OWNER`.
+    pub repository_owner: String,
+
+    /// Repository owner ID, `GITHUB_REPOSITORY_OWNER_ID`.
+    pub repository_owner_id: u64,
+
+    /// Unique workflow run ID, `GITHUB_RUN_ID`.
+    pub run_id: u64,
+
+    /// Workflow run number, `GITHUB_RUN_NUMBER`.
+    pub run_number: u64,
+
+    /// Workflow run attempt, `GITHUB_RUN_ATTEMPT`.
+    pub run_attempt: u64,
+
+    /// GitHub server URL, `GITHUB_SERVER_URL`.
+    pub server_url: String,
+
+    /// Commit SHA that triggered the workflow run, `GITHUB_SHA`.
+    pub sha: String,
+
+    /// Name of the workflow, `GITHUB_WORKFLOW`.
+    pub workflow: String,
+
+    /// Ref path to the workflow, e.g.
+    /// `octo-org/octo-repo/.github/workflows/ci.yml@refs/heads/main`,
+    /// `GITHUB_WORKFLOW_REF`.
+    pub workflow_ref: String,
+
+    /// Commit SHA of the workflow file, `GITHUB_WORKFLOW_SHA`.
+    pub workflow_sha: String,
+
+    /// Default working directory of steps, `GITHUB_WORKSPACE`.
+    pub workspace: PathBuf,
+
+    /// Runner architecture, e.g. `X64`, `RUNNER_ARCH`.
+    pub runner_arch: String,
+
+    /// Runner environment, `github-hosted` or `self-hosted`,
+    /// `RUNNER_ENVIRONMENT`.
+    pub runner_environment: String,
+
+    /// Runner name, `RUNNER_NAME`.
+    pub runner_name: String,
+
+    /// Runner operating system, e.g. `Linux`, `RUNNER_OS`.
+    pub runner_os: String,
+
+    /// Temporary directory emptied at the start and end of each job,
+    /// `RUNNER_TEMP`.
+    pub runner_temp: PathBuf,
+
+    /// Directory of preinstalled tools, `RUNNER_TOOL_CACHE`.
+    pub runner_tool_cache: PathBuf,
+
+    /// Webhook payload of the triggering event.
+    pub event: Event,
+}
+
+impl Context {
+    /// Reads the [`Context`] from the default environment variables.
+    ///
+    /// Unset variables are left empty, except for the API and server URLs
+    /// which default to `github.com`. The webhook payload is read from
+    /// `GITHUB_EVENT_PATH` if the file exists.
+    pub fn from_env() -> Result<Self> {
+        let event_name = var("GITHUB_EVENT_NAME");
+        let event_path = var_opt("GITHUB_EVENT_PATH").map(PathBuf::from);
+
+        let event = match &event_path {
+            Some(path) if path.exists() => {
+                let payload = serde_json::from_slice(&fs::read(path)?)?;
+                Event::parse(&event_name, payload)?
+            }
+            _ => Event::default(),
+        };
+
+        Ok(Self {
+            action: var("GITHUB_ACTION"),
+            action_path: var_opt("GITHUB_ACTION_PATH").map(Into::into),
+            actor: var("GITHUB_ACTOR"),
+            api_url: var_opt("GITHUB_API_URL").unwrap_or_else(|| DEFAULT_API_URL.into()),
+            base_ref: var_opt("GITHUB_BASE_REF"),
+            event_name,
+            event_path,
+            graphql_url: var_opt("GITHUB_GRAPHQL_URL")
+                .unwrap_or_else(|| DEFAULT_GRAPHQL_URL.into()),
+            head_ref: var_opt("GITHUB_HEAD_REF"),
+            job: var("GITHUB_JOB"),
+            ref_: var("GITHUB_REF"),
+            ref_name: var("GITHUB_REF_NAME"),
+            ref_type: var("GITHUB_REF_TYPE"),
+            repository: var("GITHUB_REPOSITORY"),
+            repository_id: var_u64("GITHUB_REPOSITORY_ID")?,
+            repository_owner: var("GITHUB_REPOSITORY_OWNER"),
+            repository_owner_id: var_u64("GITHUB_REPOSITORY_OWNER_ID")?,
+            run_id: var_u64("GITHUB_RUN_ID")?,
+            run_number: var_u64("GITHUB_RUN_NUMBER")?,
+            run_attempt: var_u64("GITHUB_RUN_ATTEMPT")?,
+            server_url: var_opt("GITHUB_SERVER_URL").unwrap_or_else(|| DEFAULT_SERVER_URL.into()),
+            sha: var("GITHUB_SHA"),
+            workflow: var("GITHUB_WORKFLOW"),
+            workflow_ref: var("GITHUB_WORKFLOW_REF"),
+            workflow_sha: var("GITHUB_WORKFLOW_SHA"),
+            workspace: var("GITHUB_WORKSPACE").into(),
+            runner_arch: var("RUNNER_ARCH"),
+            runner_environment: var("RUNNER_ENVIRONMENT"),
+            runner_name: var("RUNNER_NAME"),
+            runner_os: var("RUNNER_OS"),
+            runner_temp: var("RUNNER_TEMP").into(),
+            runner_tool_cache: var("RUNNER_TOOL_CACHE").into(),
+            event,
+        })
+    }
+
+    /// Gets the repository owner and name from [`repository`][Self::repository].
+    pub fn repo(&self) -> Option<(&str, &str)> {
+        self.repository
+            .split_once('/')
+            .filter(|(owner, repo)| !owner.is_empty() && !repo.is_empty())
+    }
+}
+
+fn var(name: &str) -> String {
+    env::var(name).unwrap_or_default()
+}
+
+fn var_opt(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn var_u64(name: &'static str) -> Result<u64> {
+    match var_opt(name) {
+        Some(value) => value.parse().map_err(|_| Error::InvalidVar { name, value }),
+        None => Ok(0),
+    }
+}
@@ -0,0 +1,318 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+use crate::Result;
+
+/// Webhook payload of the event that triggered the workflow run.
+///
+/// See [Webhook events and
+/// payloads](https://docs.github.com/en/webhooks-and-events/webhooks/webhook-events-and-payloads)
+/// for the official GitHub documentation. Only commonly used fields are typed.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// `push` event.
+    Push(Box<PushEvent>),
+
+    /// `pull_request` or `pull_request_target` event.
+    PullRequest(Box<PullRequestEvent>),
+
+    /// `issue_comment` event, for comments on issues and pull requests.
+    IssueComment(Box<IssueCommentEvent>),
+
+    /// `workflow_dispatch` event.
+    WorkflowDispatch(Box<WorkflowDispatchEvent>),
+
+    /// `schedule` event.
+    Schedule(Box<ScheduleEvent>),
+
+    /// `release` event.
+    Release(Box<ReleaseEvent>),
+
+    /// Any other event, or no payload.
+    Other(Value),
+}
+
+impl Default for Event {
+    fn default() -> Self {
+        Self::Other(Value::Object(Default::default()))
+    }
+}
+
+impl Event {
+    /// Parses the webhook `payload` of the event named `event_name`.
+    pub fn parse(event_name: &str, payload: Value) -> Result<Self> {
+        Ok(match event_name {
+            "push" => Self::Push(serde_json::from_value(payload)?),
+            "pull_request" | "pull_request_target" => {
+                Self::PullRequest(serde_json::from_value(payload)?)
+            }
+            "issue_comment" => Self::IssueComment(serde_json::from_value(payload)?),
+            "workflow_dispatch" => Self::WorkflowDispatch(serde_json::from_value(payload)?),
+            "schedule" => Self::Schedule(serde_json::from_value(payload)?),
+            "release" => Self::Release(serde_json::from_value(payload)?),
+            _ => Self::Other(payload),
+        })
+    }
+
+    /// Gets the repository the event occurred in, if any.
+    pub fn repository(&self) -> Option<&Repository> {
+        match self {
+            Self::Push(event) => event.repository.as_ref(),
+            Self::PullRequest(event) => event.repository.as_ref(),
+            Self::IssueComment(event) => event.repository.as_ref(),
+            Self::WorkflowDispatch(event) => event.repository.as_ref(),
+            Self::Schedule(event) => event.repository.as_ref(),
+            Self::Release(event) => event.repository.as_ref(),
+            Self::Other(_) => None,
+        }
+    }
+
+    /// Gets the number of the pull request or issue the event occurred on,
+    /// if any.
+    pub fn issue_number(&self) -> Option<u64> {
+        match self {
+            Self::PullRequest(event) => Some(event.number),
+            Self::IssueComment(event) => Some(event.issue.number),
+            _ => None,
+        }
+    }
+}
+
+/// GitHub user or organization.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct User {
+    pub id: u64,
+    pub login: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub html_url: String,
+}
+
+/// GitHub repository.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Repository {
+    pub id: u64,
+    pub name: String,
+    pub full_name: String,
+    pub owner: User,
+    pub private: bool,
+    pub html_url: String,
+    pub clone_url: String,
+    pub default_branch: String,
+}
+
+/// Git author or committer.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Committer {
+    pub name: String,
+    pub email: Option<String>,
+    pub username: Option<String>,
+}
+
+/// Commit of a [`PushEvent`].
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Commit {
+    pub id: String,
+    pub tree_id: String,
+    pub distinct: bool,
+    pub message: String,
+    pub timestamp: String,
+    pub url: String,
+    pub author: Committer,
+    pub committer: Committer,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+/// `push` event payload.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PushEvent {
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    pub before: String,
+    pub after: String,
+    pub base_ref: Option<String>,
+    pub created: bool,
+    pub deleted: bool,
+    pub forced: bool,
+    pub compare: String,
+    pub commits: Vec<Commit>,
+    pub head_commit: Option<Commit>,
+    pub pusher: Committer,
+    pub repository: Option<Repository>,
+    pub sender: Option<User>,
+}
+
+/// Issue or pull request label.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Label {
+    pub id: u64,
+    pub name: String,
+    pub color: String,
+    pub description: Option<String>,
+}
+
+/// Head or base of a [`PullRequest`].
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PullRequestRef {
+    pub label: String,
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    pub sha: String,
+    pub user: User,
+    pub repo: Option<Repository>,
+}
+
+/// Pull request.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PullRequest {
+    pub id: u64,
+    pub number: u64,
+    pub state: String,
+    pub title: String,
+    pub body: Option<String>,
+    pub draft: bool,
+    pub merged: Option<bool>,
+    pub merge_commit_sha: Option<String>,
+    pub html_url: String,
+    pub user: User,
+    pub labels: Vec<Label>,
+    pub head: PullRequestRef,
+    pub base: PullRequestRef,
+}
+
+/// `pull_request` or `pull_request_target` event payload.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PullRequestEvent {
+    pub action: String,
+    pub number: u64,
+    pub pull_request: PullRequest,
+    pub repository: Option<Repository>,
+    pub sender: Option<User>,
+}
+
+/// Issue, or pull request viewed as an issue.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Issue {
+    pub id: u64,
+    pub number: u64,
+    pub state: String,
+    pub title: String,
+    pub body: Option<String>,
+    pub html_url: String,
+    pub user: User,
+    pub labels: Vec<Label>,
+    /// Links to the pull request, if the issue is a pull request.
+    pub pull_request: Option<Value>,
+}
+
+/// Comment on an issue or pull request.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IssueComment {
+    pub id: u64,
+    pub body: String,
+    pub html_url: String,
+    pub user: User,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// `issue_comment` event payload.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IssueCommentEvent {
+    pub action: String,
+    pub issue: Issue,
+    pub comment: IssueComment,
+    pub repository: Option<Repository>,
+    pub sender: Option<User>,
+}
+
+/// `workflow_dispatch` event payload.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WorkflowDispatchEvent {
+    /// Workflow inputs, as strings or booleans depending on the input type.
+    /// Empty for workflows without inputs, where the payload has `null`.
+    #[serde(deserialize_with = "null_as_default")]
+    pub inputs: Map<String, Value>,
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    pub workflow: String,
+    pub repository: Option<Repository>,
+    pub sender: Option<User>,
+}
+
+/// `schedule` event payload.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ScheduleEvent {
+    /// Cron expression of the schedule.
+    pub schedule: String,
+    pub repository: Option<Repository>,
+}
+
+/// Release asset.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReleaseAsset {
+    pub id: u64,
+    pub name: String,
+    pub label: Option<String>,
+    pub state: String,
+    pub content_type: String,
+    pub size: u64,
+    pub url: String,
+    pub browser_download_url: String,
+}
+
+/// Release.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Release {
+    pub id: u64,
+    pub tag_name: String,
+    pub target_commitish: String,
+    pub name: Option<String>,
+    pub body: Option<String>,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub created_at: Option<String>,
+    pub published_at: Option<String>,
+    pub url: String,
+    pub html_url: String,
+    pub upload_url: String,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// `release` event payload.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReleaseEvent {
+    pub action: String,
+    pub release: Release,
+    pub repository: Option<Repository>,
+    pub sender: Option<User>,
+}
+
+/// Deserializes `null` as the default value, e.g. an empty map.
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::deserialize(deserializer)?.unwrap_or_default())
+}
@@ -0,0 +1,301 @@
+//! # GitHub Actions core
+//!
+//! Workflow commands for the GitHub Actions runner, an idiomatic Rust port of
+//! [@actions/core](https://github.com/actions/toolkit/tree/main/packages/core).
+//!
+//! See [Workflow commands for GitHub
+//! Actions](https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions)
+//! for the official GitHub documentation.
+//!
+//! The [`ActionsLayer`] renders [`tracing`] spans and events as workflow
+//! commands:
+//!
+//! ```rust
+//! use tracing_subscriber::prelude::*;
+//!
+//! # use gha_toolkit::core::ActionsLayer;
+//! #
+//! tracing_subscriber::registry().with(ActionsLayer::new()).init();
+//!
+//! tracing::warn!("Cache miss");
+//! ```
+
+use std::env;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::time::SystemTime;
+
+mod layer;
+mod oidc;
+pub mod secrets;
+
+pub use self::layer::ActionsLayer;
+pub use self::oidc::{get_id_token, IdToken, IdTokenClaims};
+
+pub(crate) use self::oidc::decode_jwt_payload;
+
+use crate::{Error, Result};
+
+/// Returns `true` if step debug logging is enabled on the runner, i.e.
+/// `RUNNER_DEBUG` is `1`.
+pub fn is_debug() -> bool {
+    env::var("RUNNER_DEBUG").map_or(false, |v| v == "1")
+}
+
+/// Workflow command, e.g. `::warning file=src/lib.rs,line=1::message`.
+///
+/// The [`Display`][fmt::Display] implementation escapes the message and
+/// property values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command<'a> {
+    name: &'a str,
+    properties: Vec<(&'a str, String)>,
+    message: &'a str,
+}
+
+impl<'a> Command<'a> {
+    /// Creates a new [`Command`] with the given `name` and `message`.
+    pub fn new(name: &'a str, message: &'a str) -> Self {
+        Self {
+            name,
+            properties: vec![],
+            message,
+        }
+    }
+
+    /// Adds a property to the command.
+    pub fn property<T: ToString>(mut self, key: &'a str, value: T) -> Self {
+        self.properties.push((key, value.to_string()));
+        self
+    }
+
+    /// Writes the command to stdout where the runner picks it up.
+    pub fn issue(&self) {
+        println!("{self}");
+    }
+}
+
+impl fmt::Display for Command<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "::{}", self.name)?;
+        for (i, (key, value)) in self.properties.iter().enumerate() {
+            let sep = if i == 0 { ' ' } else { ',' };
+            write!(f, "{sep}{key}={}", escape_property(value))?;
+        }
+        write!(f, "::{}", escape_data(self.message))
+    }
+}
+
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Severity of an [`Annotation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnnotationLevel {
+    Error,
+    Warning,
+    Notice,
+}
+
+impl AnnotationLevel {
+    /// Gets the workflow command name for this level.
+    pub fn command(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Notice => "notice",
+        }
+    }
+}
+
+/// Location and title of an [`Annotation`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnnotationProperties {
+    /// Custom title for the annotation.
+    pub title: Option<String>,
+
+    /// Path of the annotated file, relative to the repository root.
+    pub file: Option<String>,
+
+    /// Start line of the annotation.
+    pub start_line: Option<u32>,
+
+    /// End line of the annotation. Defaults to `start_line`.
+    pub end_line: Option<u32>,
+
+    /// Start column of the annotation.
+    pub start_column: Option<u32>,
+
+    /// End column of the annotation. Defaults to `start_column`.
+    pub end_column: Option<u32>,
+}
+
+/// Error, warning or notice message shown on the workflow run summary and, if
+/// a file is given, on the pull request diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// Severity of the annotation.
+    pub level: AnnotationLevel,
+
+    /// Annotation message.
+    pub message: String,
+
+    /// Location and title of the annotation.
+    pub properties: AnnotationProperties,
+}
+
+impl Annotation {
+    /// Creates a new [`Annotation`] without location.
+    pub fn new<T: Into<String>>(level: AnnotationLevel, message: T) -> Self {
+        Self {
+            level,
+            message: message.into(),
+            properties: Default::default(),
+        }
+    }
+
+    /// Sets the annotation title.
+    pub fn title<T: Into<String>>(mut self, title: T) -> Self {
+        self.properties.title = Some(title.into());
+        self
+    }
+
+    /// Sets the annotated file.
+    pub fn file<T: Into<String>>(mut self, file: T) -> Self {
+        self.properties.file = Some(file.into());
+        self
+    }
+
+    /// Sets the annotated line range.
+    pub fn lines(mut self, start_line: u32, end_line: u32) -> Self {
+        self.properties.start_line = Some(start_line);
+        self.properties.end_line = Some(end_line);
+        self
+    }
+
+    /// Sets the annotated column range.
+    pub fn columns(mut self, start_column: u32, end_column: u32) -> Self {
+        self.properties.start_column = Some(start_column);
+        self.properties.end_column = Some(end_column);
+        self
+    }
+
+    /// Gets the workflow command for this annotation.
+    pub fn command(&self) -> Command<'_> {
+        let props = &self.properties;
+        let mut command = Command::new(self.level.command(), &self.message);
+        if let Some(title) = &props.title {
+            command = command.property("title", title);
+        }
+        if let Some(file) = &props.file {
+            command = command.property("file", file);
+        }
+        if let Some(line) = props.start_line {
+            command = command.property("line", line);
+        }
+        if let Some(end_line) = props.end_line {
+            command = command.property("endLine", end_line);
+        }
+        if let Some(col) = props.start_column {
+            command = command.property("col", col);
+        }
+        if let Some(end_column) = props.end_column {
+            command = command.property("endColumn", end_column);
+        }
+        command
+    }
+}
+
+/// Writes an annotation.
+pub fn annotate(annotation: &Annotation) {
+    annotation.command().issue();
+}
+
+/// Writes a debug message, only shown when step debug logging is enabled.
+pub fn debug(message: &str) {
+    Command::new("debug", message).issue();
+}
+
+/// Writes an error annotation without location.
+pub fn error(message: &str) {
+    Command::new("error", message).issue();
+}
+
+/// Writes a warning annotation without location.
+pub fn warning(message: &str) {
+    Command::new("warning", message).issue();
+}
+
+/// Writes a notice annotation without location.
+pub fn notice(message: &str) {
+    Command::new("notice", message).issue();
+}
+
+/// Registers a secret which is masked in the log.
+///
+/// See [`secrets::register`].
+pub fn set_secret<T: AsRef<str>>(secret: T) {
+    secrets::register(secret);
+}
+
+/// Begins a collapsible group in the log. Groups cannot be nested.
+pub fn start_group(name: &str) {
+    Command::new("group", name).issue();
+}
+
+/// Ends the current collapsible group.
+pub fn end_group() {
+    Command::new("endgroup", "").issue();
+}
+
+/// Saves state for the `post` step of the action, or the `main` step when
+/// saved in the `pre` step.
+///
+/// The state is written to the `GITHUB_STATE` file, falling back to the
+/// deprecated `save-state` command.
+pub fn save_state(name: &str, value: &str) -> Result<()> {
+    issue_file_command("GITHUB_STATE", "save-state", name, value)
+}
+
+/// Gets state saved by a previous step of the action, or an empty string.
+pub fn get_state(name: &str) -> String {
+    env::var(format!("STATE_{name}")).unwrap_or_default()
+}
+
+/// Sets an output of the step.
+///
+/// The output is written to the `GITHUB_OUTPUT` file, falling back to the
+/// deprecated `set-output` command.
+pub fn set_output(name: &str, value: &str) -> Result<()> {
+    issue_file_command("GITHUB_OUTPUT", "set-output", name, value)
+}
+
+fn issue_file_command(file_var: &str, command: &str, name: &str, value: &str) -> Result<()> {
+    let path = match env::var_os(file_var) {
+        Some(path) if !path.is_empty() => path,
+        _ => {
+            Command::new(command, value).property("name", name).issue();
+            return Ok(());
+        }
+    };
+
+    let nonce = SystemTime::UNIX_EPOCH
+        .elapsed()
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let delimiter = format!("ghadelimiter_{nonce:x}{:x}", std::process::id());
+    if name.contains(&delimiter) || value.contains(&delimiter) {
+        return Err(Error::FileCommandDelimiter(delimiter));
+    }
+
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    writeln!(file, "{name}<<{delimiter}\n{value}\n{delimiter}")?;
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use tracing::field::{Field, Visit};
+use tracing::span::Id;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use super::{is_debug, Annotation, AnnotationLevel, Command};
+
+/// [`tracing_subscriber::Layer`] that renders spans and events as workflow
+/// commands.
+///
+/// - `ERROR` and `WARN` events become `::error::` and `::warning::`
+///   annotations pointing at the event's file and line.
+/// - `INFO` events are written as plain log lines.
+/// - `DEBUG` and `TRACE` events become `::debug::` messages when step debug
+///   logging is enabled, see [`is_debug`].
+/// - The first entered span opens a collapsible `::group::` which is closed
+///   with the span. Groups cannot be nested, so spans entered while a group is
+///   open do not open groups of their own.
+///
+/// See [module][super] documentation.
+pub struct ActionsLayer<W = io::Stdout> {
+    writer: Mutex<W>,
+    debug: bool,
+    groups: bool,
+    group: Mutex<Option<Id>>,
+}
+
+impl ActionsLayer {
+    /// Creates a new [`ActionsLayer`] writing to stdout.
+    pub fn new() -> Self {
+        Self::with_writer(io::stdout())
+    }
+}
+
+impl Default for ActionsLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> ActionsLayer<W> {
+    /// Creates a new [`ActionsLayer`] writing to the given `writer`.
+    pub fn with_writer(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            debug: is_debug(),
+            groups: true,
+            group: Mutex::new(None),
+        }
+    }
+
+    /// Sets whether `DEBUG` and `TRACE` events are written. Defaults to
+    /// [`is_debug`].
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Sets whether spans open collapsible groups. Defaults to `true`.
+    pub fn groups(mut self, groups: bool) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    fn write_line(&self, line: &str) {
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writeln!(writer, "{line}");
+        }
+    }
+}
+
+impl<S, W> Layer<S> for ActionsLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: Write + Send + 'static,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let level = *metadata.level();
+        if level > Level::INFO && !self.debug {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let message = visitor.finish();
+
+        let annotation_level = match level {
+            Level::ERROR => AnnotationLevel::Error,
+            Level::WARN => AnnotationLevel::Warning,
+            Level::INFO => return self.write_line(&message),
+            _ => return self.write_line(&Command::new("debug", &message).to_string()),
+        };
+
+        let mut annotation = Annotation::new(annotation_level, message);
+        annotation.properties.file = metadata.file().map(Into::into);
+        annotation.properties.start_line = metadata.line();
+        self.write_line(&annotation.command().to_string());
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if !self.groups {
+            return;
+        }
+
+        let mut group = match self.group.lock() {
+            Ok(group) => group,
+            Err(_) => return,
+        };
+        if group.is_some() {
+            return;
+        }
+
+        if let Some(span) = ctx.span(id) {
+            self.write_line(&Command::new("group", span.name()).to_string());
+            *group = Some(id.clone());
+        }
+    }
+
+    fn on_close(&self, id: Id, _ctx: Context<'_, S>) {
+        let mut group = match self.group.lock() {
+            Ok(group) => group,
+            Err(_) => return,
+        };
+        if group.as_ref() == Some(&id) {
+            self.write_line(&Command::new("endgroup", "").to_string());
+            *group = None;
+        }
+    }
+}
+
+/// Formats the `message` field followed by the remaining fields as
+/// `name=value` pairs.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(mut self) -> String {
+        if self.message.is_empty() {
+            return self.fields;
+        }
+        if !self.fields.is_empty() {
+            self.message.push(' ');
+            self.message.push_str(&self.fields);
+        }
+        self.message
+    }
+
+    fn field(&mut self) -> &mut String {
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        &mut self.fields
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.field(), "{}={value}", field.name());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.field(), "{}={value:?}", field.name());
+        }
+    }
+}
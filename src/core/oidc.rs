@@ -0,0 +1,247 @@
+use std::env;
+use std::fmt;
+
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use tracing::instrument;
+
+use super::secrets;
+use crate::transport::ClientOptions;
+use crate::{Error, Result};
+
+/// GitHub Actions OIDC ID token.
+///
+/// The token is never written by the [`Debug`][fmt::Debug] implementation.
+#[derive(Clone, PartialEq, Eq)]
+pub struct IdToken {
+    /// Encoded JWT.
+    pub token: String,
+
+    /// Claims decoded from the JWT payload.
+    pub claims: IdTokenClaims,
+}
+
+impl IdToken {
+    /// Decodes the claims of an encoded JWT without verifying the signature.
+    pub fn parse<T: Into<String>>(token: T) -> Result<Self> {
+        let token = token.into();
+        let claims = decode_jwt_payload(&token)?;
+        Ok(Self { token, claims })
+    }
+}
+
+impl fmt::Debug for IdToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdToken")
+            .field("token", &secrets::MASK)
+            .field("claims", &self.claims)
+            .finish()
+    }
+}
+
+/// Claims of a GitHub Actions OIDC ID token.
+///
+/// See [Understanding the OIDC
+/// token](https://docs.github.com/en/actions/deployment/security-hardening-your-deployments/about-security-hardening-with-openid-connect#understanding-the-oidc-token)
+/// for the official GitHub documentation.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct IdTokenClaims {
+    /// Issuer, e.g. `https://token.actions.githubusercontent.com`.
+    pub iss: String,
+
+    /// Subject, e.g. `repo:octo-org/octo-repo:ref:refs/heads/main`.
+    pub sub: String,
+
+    /// Audiences the token is intended for.
+    #[serde(deserialize_with = "one_or_many")]
+    pub aud: Vec<String>,
+
+    /// Expiration time as a Unix timestamp.
+    pub exp: u64,
+
+    /// Issue time as a Unix timestamp.
+    pub iat: u64,
+
+    /// Not-before time as a Unix timestamp.
+    #[serde(default)]
+    pub nbf: Option<u64>,
+
+    /// Unique token identifier.
+    #[serde(default)]
+    pub jti: Option<String>,
+
+    /// Git ref that triggered the workflow run.
+    #[serde(rename = "ref", default)]
+    pub ref_: Option<String>,
+
+    /// Type of [`ref_`][Self::ref_], e.g. `branch` or `tag`.
+    #[serde(default)]
+    pub ref_type: Option<String>,
+
+    /// Commit SHA that triggered the workflow run.
+    #[serde(default)]
+    pub sha: Option<String>,
+
+    /// Repository the workflow runs in, e.g. `octo-org/octo-repo`.
+    #[serde(default)]
+    pub repository: Option<String>,
+
+    /// Repository ID.
+    #[serde(default)]
+    pub repository_id: Option<String>,
+
+    /// Repository owner, e.g. `octo-org`.
+    #[serde(default)]
+    pub repository_owner: Option<String>,
+
+    /// Repository owner ID.
+    #[serde(default)]
+    pub repository_owner_id: Option<String>,
+
+    /// Repository visibility, e.g. `private`.
+    #[serde(default)]
+    pub repository_visibility: Option<String>,
+
+    /// Account that initiated the workflow run.
+    #[serde(default)]
+    pub actor: Option<String>,
+
+    /// Account ID that initiated the workflow run.
+    #[serde(default)]
+    pub actor_id: Option<String>,
+
+    /// Name of the workflow.
+    #[serde(default)]
+    pub workflow: Option<String>,
+
+    /// Ref path to the workflow, e.g.
+    /// `octo-org/octo-repo/.github/workflows/ci.yml@refs/heads/main`.
+    #[serde(default)]
+    pub workflow_ref: Option<String>,
+
+    /// Ref path to the reusable workflow of the job, if any.
+    #[serde(default)]
+    pub job_workflow_ref: Option<String>,
+
+    /// Source ref of a pull request.
+    #[serde(default)]
+    pub head_ref: Option<String>,
+
+    /// Target ref of a pull request.
+    #[serde(default)]
+    pub base_ref: Option<String>,
+
+    /// Name of the event that triggered the workflow run.
+    #[serde(default)]
+    pub event_name: Option<String>,
+
+    /// Name of the deployment environment of the job, if any.
+    #[serde(default)]
+    pub environment: Option<String>,
+
+    /// Workflow run ID.
+    #[serde(default)]
+    pub run_id: Option<String>,
+
+    /// Workflow run number.
+    #[serde(default)]
+    pub run_number: Option<String>,
+
+    /// Workflow run attempt.
+    #[serde(default)]
+    pub run_attempt: Option<String>,
+
+    /// Runner environment, e.g. `github-hosted`.
+    #[serde(default)]
+    pub runner_environment: Option<String>,
+
+    /// Other claims, e.g. custom claims configured for the organization.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct IdTokenResponse {
+    value: Option<String>,
+}
+
+/// Gets a GitHub Actions OIDC ID token for the given `audience`.
+///
+/// The workflow or job needs the `id-token: write` permission. The token and
+/// the request token are registered as [secrets][secrets::register], and the
+/// claims are decoded without verifying the signature, which is left to the
+/// relying party.
+///
+/// The following environmental variables are read:
+///
+/// - `ACTIONS_ID_TOKEN_REQUEST_URL` - ID token request URL
+/// - `ACTIONS_ID_TOKEN_REQUEST_TOKEN` - ID token request bearer token
+///
+#[instrument]
+pub async fn get_id_token(audience: Option<&str>) -> Result<IdToken> {
+    let url = env::var("ACTIONS_ID_TOKEN_REQUEST_URL").map_err(|source| Error::VarError {
+        source,
+        name: "ACTIONS_ID_TOKEN_REQUEST_URL",
+    })?;
+    let request_token =
+        env::var("ACTIONS_ID_TOKEN_REQUEST_TOKEN").map_err(|source| Error::VarError {
+            source,
+            name: "ACTIONS_ID_TOKEN_REQUEST_TOKEN",
+        })?;
+    secrets::register(&request_token);
+
+    let mut url = Url::parse(&url)?;
+    if let Some(audience) = audience {
+        url.query_pairs_mut().append_pair("audience", audience);
+    }
+
+    let client = ClientOptions::default().build()?;
+    let response = client
+        .get(url)
+        .bearer_auth(request_token)
+        .header(http::header::ACCEPT, "application/json")
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let message = response.text().await.unwrap_or_else(|err| err.to_string());
+        let message = secrets::redact(&message).into_owned();
+        return Err(Error::IdTokenServiceStatus { status, message });
+    }
+
+    let token = response
+        .json::<IdTokenResponse>()
+        .await?
+        .value
+        .ok_or(Error::InvalidIdToken("response has no value"))?;
+    secrets::register(&token);
+
+    IdToken::parse(token)
+}
+
+/// Decodes the payload of a JWT without verifying the signature.
+pub(crate) fn decode_jwt_payload<T: DeserializeOwned>(token: &str) -> Result<T> {
+    let mut parts = token.split('.');
+    let payload = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(_), Some(payload), Some(_), None) => payload,
+        _ => return Err(Error::InvalidIdToken("expected a JWT")),
+    };
+
+    let payload = base64::decode_config(payload.trim_end_matches('='), base64::URL_SAFE_NO_PAD)?;
+    Ok(serde_json::from_slice(&payload)?)
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
@@ -0,0 +1,94 @@
+//! Process-wide registry of secrets.
+//!
+//! A registered secret is masked in the runner log with `::add-mask::`,
+//! redacted from the toolkit's [`Debug`] implementations and scrubbed from
+//! service error messages.
+//!
+//! ```rust
+//! use gha_toolkit::core::secrets;
+//!
+//! secrets::register("hunter2");
+//!
+//! assert_eq!(secrets::redact("password: hunter2"), "password: ***");
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+use super::Command;
+
+/// Replacement for redacted secrets, the same as used by the runner.
+pub const MASK: &str = "***";
+
+static SECRETS: Lazy<RwLock<Vec<String>>> = Lazy::new(Default::default);
+
+/// Registers a secret with the runner and the toolkit.
+///
+/// The runner masks secrets line by line, so each line of a multiline secret
+/// is registered separately. Blank lines and already registered secrets are
+/// ignored.
+pub fn register<T: AsRef<str>>(secret: T) {
+    let mut secrets = match SECRETS.write() {
+        Ok(secrets) => secrets,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    for line in secret.as_ref().lines() {
+        if line.trim().is_empty() || secrets.iter().any(|s| s == line) {
+            continue;
+        }
+
+        Command::new("add-mask", line).issue();
+
+        // Keep longer secrets first so that secrets containing other secrets
+        // are fully redacted.
+        let index = secrets
+            .iter()
+            .position(|s| s.len() < line.len())
+            .unwrap_or(secrets.len());
+        secrets.insert(index, line.to_string());
+    }
+}
+
+/// Returns `true` if `value` is a registered secret.
+pub fn is_registered(value: &str) -> bool {
+    let secrets = match SECRETS.read() {
+        Ok(secrets) => secrets,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    secrets.iter().any(|s| s == value)
+}
+
+/// Replaces all registered secrets in `value` with [`MASK`].
+pub fn redact(value: &str) -> Cow<'_, str> {
+    let secrets = match SECRETS.read() {
+        Ok(secrets) => secrets,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let mut value = Cow::Borrowed(value);
+    for secret in secrets.iter() {
+        if value.contains(secret.as_str()) {
+            value = Cow::Owned(value.replace(secret.as_str(), MASK));
+        }
+    }
+    value
+}
+
+/// [`Debug`][fmt::Debug] wrapper which redacts registered secrets from the
+/// output of the wrapped value.
+pub struct Redacted<T>(pub T);
+
+impl<T: fmt::Debug> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = if f.alternate() {
+            format!("{:#?}", self.0)
+        } else {
+            format!("{:?}", self.0)
+        };
+        f.write_str(&redact(&value))
+    }
+}
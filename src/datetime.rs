@@ -0,0 +1,119 @@
+//! UTC date and time conversions for the timestamps used by GitHub services.
+
+use std::fmt;
+use std::time::SystemTime;
+
+/// UTC date and time with second precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    /// Converts seconds since the Unix epoch.
+    pub fn from_unix(secs: i64) -> Self {
+        let days = secs.div_euclid(86_400);
+        let secs = secs.rem_euclid(86_400) as u32;
+
+        // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: secs / 3600,
+            minute: secs / 60 % 60,
+            second: secs % 60,
+        }
+    }
+
+    /// Converts to seconds since the Unix epoch.
+    pub fn to_unix(self) -> i64 {
+        // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = self.year - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let mp = (i64::from(self.month) + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+
+        days * 86_400
+            + i64::from(self.hour) * 3600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second)
+    }
+
+    /// Converts a [`SystemTime`], truncating to seconds.
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_secs() as i64,
+            Err(err) => -(err.duration().as_secs() as i64),
+        };
+        Self::from_unix(secs)
+    }
+
+    /// Converts to a [`SystemTime`].
+    #[cfg(feature = "github-app")]
+    pub fn to_system_time(self) -> SystemTime {
+        use std::time::Duration;
+
+        let secs = self.to_unix();
+        if secs >= 0 {
+            SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64)
+        } else {
+            SystemTime::UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+        }
+    }
+
+    /// Parses RFC 3339 in UTC, e.g. `2022-12-01T08:30:00Z`, ignoring
+    /// fractional seconds.
+    #[cfg(feature = "github-app")]
+    pub fn parse(s: &str) -> Option<Self> {
+        let (date, time) = s.strip_suffix('Z')?.split_once('T')?;
+        let time = time.split('.').next()?;
+
+        let mut date = date.splitn(3, '-').map(str::parse::<u32>);
+        let mut time = time.splitn(3, ':').map(str::parse::<u32>);
+        let datetime = Self {
+            year: i64::from(date.next()?.ok()?),
+            month: date.next()?.ok()?,
+            day: date.next()?.ok()?,
+            hour: time.next()?.ok()?,
+            minute: time.next()?.ok()?,
+            second: time.next()?.ok()?,
+        };
+
+        let valid = (1..=12).contains(&datetime.month)
+            && (1..=31).contains(&datetime.day)
+            && datetime.hour < 24
+            && datetime.minute < 60
+            && datetime.second < 61;
+        valid.then(|| datetime)
+    }
+}
+
+/// Formats as RFC 3339, e.g. `2022-12-01T08:30:00Z`.
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
@@ -0,0 +1,399 @@
+//! # GitHub Actions exec
+//!
+//! Functions for running commands in actions, echoing the command line and
+//! streaming its output line by line. This is an idiomatic Rust port of
+//! [@actions/exec](https://github.com/actions/toolkit/tree/main/packages/exec).
+//!
+//! ```rust,no_run
+//! use gha_toolkit::exec::{self, ExecOptions};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! exec::exec("cargo build --release", &[], &ExecOptions::default()).await?;
+//!
+//! let output = exec::get_exec_output("git", &["rev-parse", "HEAD"], &ExecOptions::default()).await?;
+//! println!("Built {}", output.stdout.trim());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use futures::future::{self, Either};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tracing::{debug, instrument};
+
+use crate::{Error, Result};
+
+/// Callback for the raw output of a command.
+pub type OutputListener = Box<dyn Fn(&[u8]) + Send + Sync>;
+
+/// Callback for a line of output of a command, without the line ending.
+pub type LineListener = Box<dyn Fn(&str) + Send + Sync>;
+
+/// Options for [`exec`] and [`get_exec_output`].
+pub struct ExecOptions {
+    /// Working directory of the command. Defaults to the current directory.
+    pub cwd: Option<PathBuf>,
+
+    /// Environment of the command, replacing the environment of the action.
+    /// Defaults to the environment of the action.
+    pub env: Option<HashMap<String, String>>,
+
+    /// Whether to not echo the command line and its output to stdout.
+    pub silent: bool,
+
+    /// Whether to fail if anything is written to stderr.
+    pub fail_on_stderr: bool,
+
+    /// Whether to succeed with a non-zero exit code.
+    pub ignore_return_code: bool,
+
+    /// Time to wait for stdout and stderr to close after the command exited,
+    /// e.g. when they're inherited by a background process. Defaults to 10
+    /// seconds.
+    pub delay: Duration,
+
+    /// Input written to stdin. Stdin is empty by default.
+    pub input: Option<Vec<u8>>,
+
+    /// Callbacks for the output of the command.
+    pub listeners: ExecListeners,
+}
+
+impl Default for ExecOptions {
+    fn default() -> Self {
+        Self {
+            cwd: None,
+            env: None,
+            silent: false,
+            fail_on_stderr: false,
+            ignore_return_code: false,
+            delay: Duration::from_secs(10),
+            input: None,
+            listeners: ExecListeners::default(),
+        }
+    }
+}
+
+impl fmt::Debug for ExecOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecOptions")
+            .field("cwd", &self.cwd)
+            .field(
+                "env",
+                &self.env.as_ref().map(|env| env.keys().collect::<Vec<_>>()),
+            )
+            .field("silent", &self.silent)
+            .field("fail_on_stderr", &self.fail_on_stderr)
+            .field("ignore_return_code", &self.ignore_return_code)
+            .field("delay", &self.delay)
+            .field("input", &self.input.as_ref().map(Vec::len))
+            .field("listeners", &self.listeners)
+            .finish()
+    }
+}
+
+/// Callbacks for the output of a command.
+#[derive(Default)]
+pub struct ExecListeners {
+    /// Called with each chunk written to stdout.
+    pub stdout: Option<OutputListener>,
+
+    /// Called with each chunk written to stderr.
+    pub stderr: Option<OutputListener>,
+
+    /// Called with each line written to stdout.
+    pub stdline: Option<LineListener>,
+
+    /// Called with each line written to stderr.
+    pub errline: Option<LineListener>,
+}
+
+impl fmt::Debug for ExecListeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecListeners")
+            .field("stdout", &self.stdout.is_some())
+            .field("stderr", &self.stderr.is_some())
+            .field("stdline", &self.stdline.is_some())
+            .field("errline", &self.errline.is_some())
+            .finish()
+    }
+}
+
+/// Output of [`get_exec_output`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecOutput {
+    /// Exit code of the command, or `-1` if it was terminated by a signal.
+    pub exit_code: i32,
+
+    /// Output written to stdout.
+    pub stdout: String,
+
+    /// Output written to stderr.
+    pub stderr: String,
+}
+
+/// Runs a command and returns its exit code.
+///
+/// `command_line` is split with [`args_string_to_array`] into the tool and
+/// arguments preceding `args`. The command line and the output are echoed
+/// to stdout unless `silent` is set. Fails if the command exits with a
+/// non-zero code, unless `ignore_return_code` is set.
+pub async fn exec(command_line: &str, args: &[&str], options: &ExecOptions) -> Result<i32> {
+    let (exit_code, _, _) = run(command_line, args, options, false).await?;
+    Ok(exit_code)
+}
+
+/// Runs a command like [`exec`] and returns its exit code and output.
+///
+/// Invalid UTF-8 in the output is replaced.
+pub async fn get_exec_output(
+    command_line: &str,
+    args: &[&str],
+    options: &ExecOptions,
+) -> Result<ExecOutput> {
+    let (exit_code, stdout, stderr) = run(command_line, args, options, true).await?;
+    Ok(ExecOutput {
+        exit_code,
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr).into_owned(),
+    })
+}
+
+/// Splits an argument string into arguments at spaces outside of double
+/// quotes, like `@actions/exec`.
+///
+/// Within quotes, a backslash escapes a double quote. Other backslashes are
+/// kept.
+pub fn args_string_to_array(args: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+    let mut arg = String::new();
+
+    fn append(arg: &mut String, escaped: &mut bool, c: char) {
+        // Only double quotes are escaped
+        if *escaped && c != '"' {
+            arg.push('\\');
+        }
+        arg.push(c);
+        *escaped = false;
+    }
+
+    for c in args.chars() {
+        match c {
+            '"' if !escaped => in_quotes = !in_quotes,
+            '"' => append(&mut arg, &mut escaped, c),
+            '\\' if escaped => append(&mut arg, &mut escaped, c),
+            '\\' if in_quotes => escaped = true,
+            ' ' if !in_quotes => {
+                if !arg.is_empty() {
+                    result.push(arg);
+                    arg = String::new();
+                }
+            }
+            _ => append(&mut arg, &mut escaped, c),
+        }
+    }
+
+    if !arg.is_empty() {
+        result.push(arg.trim().to_string());
+    }
+    result
+}
+
+#[instrument(skip(options))]
+async fn run(
+    command_line: &str,
+    args: &[&str],
+    options: &ExecOptions,
+    capture: bool,
+) -> Result<(i32, Vec<u8>, Vec<u8>)> {
+    let mut command_args = args_string_to_array(command_line);
+    if command_args.is_empty() {
+        return Err(Error::IO(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Parameter 'command_line' cannot be empty",
+        )));
+    }
+    let tool = command_args.remove(0);
+    command_args.extend(args.iter().map(|arg| arg.to_string()));
+
+    if !options.silent {
+        let mut line = format!("[command]{tool}");
+        for arg in &command_args {
+            line.push(' ');
+            line.push_str(arg);
+        }
+        println!("{line}");
+    }
+
+    let mut command = Command::new(&tool);
+    command
+        .args(&command_args)
+        .stdin(if options.input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(cwd) = &options.cwd {
+        command.current_dir(cwd);
+    }
+    if let Some(env) = &options.env {
+        command.env_clear().envs(env);
+    }
+
+    let mut child = command.spawn()?;
+    let stdin = child.stdin.take();
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    let mut stdout_buf = Vec::new();
+    let mut stderr_buf = Vec::new();
+    let mut stderr_written = false;
+    let listeners = &options.listeners;
+
+    let write_input = async {
+        if let (Some(mut stdin), Some(input)) = (stdin, &options.input) {
+            match stdin.write_all(input).await {
+                // The process may exit without reading all of its input
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+                result => result?,
+            }
+        }
+        Ok(())
+    };
+    let read_stdout = read_lines(
+        stdout,
+        |data: &[u8]| {
+            if !options.silent {
+                write_stdout(data);
+            }
+            if capture {
+                stdout_buf.extend_from_slice(data);
+            }
+            if let Some(listener) = &listeners.stdout {
+                listener(data);
+            }
+        },
+        listeners.stdline.as_ref(),
+    );
+    let read_stderr = read_lines(
+        stderr,
+        |data: &[u8]| {
+            stderr_written |= !data.is_empty();
+            if !options.silent {
+                // Like `@actions/exec`, stderr is only echoed to stderr when failing on it
+                if options.fail_on_stderr {
+                    let _ = io::stderr().write_all(data);
+                } else {
+                    write_stdout(data);
+                }
+            }
+            if capture {
+                stderr_buf.extend_from_slice(data);
+            }
+            if let Some(listener) = &listeners.stderr {
+                listener(data);
+            }
+        },
+        listeners.errline.as_ref(),
+    );
+
+    let mut streams = Box::pin(future::try_join3(write_input, read_stdout, read_stderr));
+    let status = match future::select(Box::pin(child.wait()), &mut streams).await {
+        Either::Left((status, streams)) => {
+            if tokio::time::timeout(options.delay, streams).await.is_err() {
+                debug!(
+                    "The STDIO streams did not close within {:?} of the exit of {tool}",
+                    options.delay
+                );
+            }
+            Ok(status?)
+        }
+        Either::Right((Ok(_), wait)) => Ok(wait.await?),
+        Either::Right((Err(err), _)) => Err(err),
+    };
+    drop(streams);
+    let status = match status {
+        Ok(status) => status,
+        Err(err) => {
+            // Don't leave the process running when its streams failed
+            let _ = child.kill().await;
+            return Err(err);
+        }
+    };
+
+    debug!("Process {tool} exited with {status}");
+    if !status.success() && !options.ignore_return_code {
+        return Err(Error::ExecExitStatus { tool, status });
+    }
+    if stderr_written && options.fail_on_stderr {
+        return Err(Error::ExecStderr(tool));
+    }
+
+    Ok((status.code().unwrap_or(-1), stdout_buf, stderr_buf))
+}
+
+/// Reads a stream, calling `on_data` with each chunk and `on_line` with each
+/// line.
+async fn read_lines<R, F>(
+    reader: Option<R>,
+    mut on_data: F,
+    on_line: Option<&LineListener>,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    F: FnMut(&[u8]),
+{
+    let mut reader = match reader {
+        Some(reader) => reader,
+        None => return Ok(()),
+    };
+
+    let mut buf = vec![0; 8 << 10];
+    let mut line = Vec::new();
+    loop {
+        let len = reader.read(&mut buf).await?;
+        if len == 0 {
+            break;
+        }
+        on_data(&buf[..len]);
+
+        if let Some(on_line) = on_line {
+            line.extend_from_slice(&buf[..len]);
+            while let Some(end) = line.iter().position(|&b| b == b'\n') {
+                let rest = line.split_off(end + 1);
+                emit_line(on_line, &line);
+                line = rest;
+            }
+        }
+    }
+
+    if let Some(on_line) = on_line {
+        if !line.is_empty() {
+            emit_line(on_line, &line);
+        }
+    }
+    Ok(())
+}
+
+fn emit_line(on_line: &LineListener, line: &[u8]) {
+    let line = String::from_utf8_lossy(line);
+    on_line(line.trim_end_matches('\n').trim_end_matches('\r'));
+}
+
+fn write_stdout(data: &[u8]) {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(data);
+    let _ = stdout.flush();
+}
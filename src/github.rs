@@ -0,0 +1,745 @@
+//! # GitHub REST API client
+//!
+//! The [`GitHubClient`] calls the [GitHub REST API](https://docs.github.com/en/rest)
+//! with the token of the workflow run, similar to the Octokit client of
+//! [@actions/github](https://github.com/actions/toolkit/tree/main/packages/github).
+//! Requests and responses are (de)serialized with serde, so callers declare
+//! only the fields they need.
+//!
+//! Paginated endpoints are read as a [`Stream`] following the `Link` header.
+//! Requests exceeding the primary or secondary rate limit are retried after
+//! the limit resets, and the most recently used `GET` responses are cached
+//! with their `ETag` so unchanged resources don't count against the rate
+//! limit.
+//!
+//! ```rust,no_run
+//! use futures::prelude::*;
+//! use gha_toolkit::github::GitHubClient;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Label {
+//!     name: String,
+//! }
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let client = GitHubClient::from_env()?.build()?;
+//!
+//! let mut labels = client.paginate::<Label>("/repos/octo-org/octo-repo/labels?per_page=100");
+//! while let Some(label) = labels.try_next().await? {
+//!     println!("{}", label.name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use futures::prelude::*;
+use http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use reqwest::Url;
+use reqwest_middleware::ClientWithMiddleware;
+#[cfg(doc)]
+use reqwest_retry::policies::ExponentialBackoff;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument, warn};
+
+use crate::core::secrets;
+use crate::transport::{self, ClientOptions};
+use crate::{Error, Result};
+
+#[cfg(feature = "github-app")]
+pub mod app;
+pub mod checks;
+pub mod comments;
+pub mod graphql;
+pub mod releases;
+pub mod workflows;
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+const API_VERSION: &str = "2022-11-28";
+const DEFAULT_MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Time to wait after exceeding a secondary rate limit without a
+/// `retry-after` header.
+const SECONDARY_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Keys of the metadata of paginated responses wrapping the items in an
+/// object, e.g. `{"total_count": 1, "workflow_runs": [...]}`.
+const PAGINATION_METADATA_KEYS: &[&str] =
+    &["incomplete_results", "repository_selection", "total_count"];
+
+/// Maximum number of cached `GET` responses.
+const MAX_CACHED_RESPONSES: usize = 256;
+
+/// GitHub REST API client builder.
+///
+/// The access token is never written by the [`Debug`][fmt::Debug]
+/// implementation.
+///
+/// See [module][self] documentation.
+#[derive(Clone, PartialEq, Eq)]
+pub struct GitHubClientBuilder {
+    /// GitHub REST API base URL, e.g. `https://HOST/api/v3` for GitHub
+    /// Enterprise Server.
+    pub base_url: String,
+
+    /// GitHub GraphQL API URL. Defaults to the GraphQL API of the REST API
+    /// base URL, e.g. `https://HOST/api/graphql` for GitHub Enterprise
+    /// Server.
+    pub graphql_url: Option<String>,
+
+    /// GitHub access token. Requests are anonymous if empty.
+    pub token: String,
+
+    /// User agent for HTTP requests.
+    pub user_agent: String,
+
+    /// Maximum number of retries.
+    pub max_retries: u32,
+
+    /// Minimum retry interval. See [`ExponentialBackoff::min_retry_interval`].
+    pub min_retry_interval: Duration,
+
+    /// Maximum retry interval. See [`ExponentialBackoff::max_retry_interval`].
+    pub max_retry_interval: Duration,
+
+    /// Retry backoff factor base. See [`ExponentialBackoff::backoff_exponent`].
+    pub backoff_factor_base: u32,
+
+    /// Maximum total time to wait for rate limits to reset before failing
+    /// with [`Error::GitHubRateLimited`].
+    pub max_rate_limit_wait: Duration,
+
+    /// Whether to cache the most recently used `GET` responses and
+    /// revalidate them with their `ETag`.
+    pub conditional_requests: bool,
+}
+
+impl Default for GitHubClientBuilder {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_API_URL.into(),
+            graphql_url: None,
+            token: Default::default(),
+            user_agent: transport::DEFAULT_USER_AGENT.into(),
+            max_retries: transport::DEFAULT_MAX_RETRIES,
+            min_retry_interval: transport::DEFAULT_MIN_RETRY_INTERVAL,
+            max_retry_interval: transport::DEFAULT_MAX_RETRY_INTERVAL,
+            backoff_factor_base: transport::DEFAULT_BACKOFF_FACTOR_BASE,
+            max_rate_limit_wait: DEFAULT_MAX_RATE_LIMIT_WAIT,
+            conditional_requests: true,
+        }
+    }
+}
+
+impl fmt::Debug for GitHubClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitHubClientBuilder")
+            .field("base_url", &self.base_url)
+            .field("graphql_url", &self.graphql_url)
+            .field("token", &secrets::MASK)
+            .field("user_agent", &secrets::Redacted(&self.user_agent))
+            .field("max_retries", &self.max_retries)
+            .field("min_retry_interval", &self.min_retry_interval)
+            .field("max_retry_interval", &self.max_retry_interval)
+            .field("backoff_factor_base", &self.backoff_factor_base)
+            .field("max_rate_limit_wait", &self.max_rate_limit_wait)
+            .field("conditional_requests", &self.conditional_requests)
+            .finish()
+    }
+}
+
+impl GitHubClientBuilder {
+    /// Creates a new [`GitHubClientBuilder`] for the given GitHub REST API
+    /// base URL and access token.
+    pub fn new<B: Into<String>, T: Into<String>>(base_url: B, token: T) -> Self {
+        Self {
+            base_url: base_url.into(),
+            token: token.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new [`GitHubClientBuilder`] from GitHub Actions
+    /// environmental variables.
+    ///
+    /// The following environmental variables are read:
+    ///
+    /// - `GITHUB_TOKEN` - GitHub access token
+    /// - `GITHUB_API_URL` - GitHub REST API base URL (optional)
+    /// - `GITHUB_GRAPHQL_URL` - GitHub GraphQL API URL (optional)
+    ///
+    pub fn from_env() -> Result<Self> {
+        let token = env::var("GITHUB_TOKEN").map_err(|source| Error::VarError {
+            source,
+            name: "GITHUB_TOKEN",
+        })?;
+
+        let mut builder = Self::new(DEFAULT_API_URL, token);
+        if let Ok(api_url) = env::var("GITHUB_API_URL") {
+            builder.base_url = api_url;
+        }
+        builder.graphql_url = env::var("GITHUB_GRAPHQL_URL").ok();
+        Ok(builder)
+    }
+
+    /// Sets the GitHub REST API base URL.
+    pub fn base_url<T: Into<String>>(mut self, base_url: T) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Sets the GitHub GraphQL API URL.
+    pub fn graphql_url<T: Into<String>>(mut self, graphql_url: T) -> Self {
+        self.graphql_url = Some(graphql_url.into());
+        self
+    }
+
+    /// Sets the GitHub REST and GraphQL API URLs of a GitHub Enterprise
+    /// Server instance from its server URL, e.g. `https://github.example.com`.
+    pub fn enterprise<T: AsRef<str>>(mut self, server_url: T) -> Self {
+        let server_url = server_url.as_ref().trim_end_matches('/');
+        self.base_url = format!("{server_url}/api/v3");
+        self.graphql_url = Some(format!("{server_url}/api/graphql"));
+        self
+    }
+
+    /// Sets the GitHub access token.
+    pub fn token<T: Into<String>>(mut self, token: T) -> Self {
+        self.token = token.into();
+        self
+    }
+
+    /// Sets the user agent for HTTP requests.
+    pub fn user_agent<T: Into<String>>(mut self, user_agent: T) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets the maximum number of retries.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the minimum retry interval.
+    pub fn min_retry_interval(mut self, min_retry_interval: Duration) -> Self {
+        self.min_retry_interval = min_retry_interval;
+        self
+    }
+
+    /// Sets the maximum retry interval.
+    pub fn max_retry_interval(mut self, max_retry_interval: Duration) -> Self {
+        self.max_retry_interval = max_retry_interval;
+        self
+    }
+
+    /// Sets the retry backoff factor base.
+    pub fn backoff_factor_base(mut self, backoff_factor_base: u32) -> Self {
+        self.backoff_factor_base = backoff_factor_base;
+        self
+    }
+
+    /// Sets the maximum total time to wait for rate limits to reset.
+    pub fn max_rate_limit_wait(mut self, max_rate_limit_wait: Duration) -> Self {
+        self.max_rate_limit_wait = max_rate_limit_wait;
+        self
+    }
+
+    /// Sets whether to cache the most recently used `GET` responses and
+    /// revalidate them with their `ETag`.
+    pub fn conditional_requests(mut self, conditional_requests: bool) -> Self {
+        self.conditional_requests = conditional_requests;
+        self
+    }
+
+    /// Consumes this [`GitHubClientBuilder`] and build a [`GitHubClient`].
+    ///
+    /// The access token is registered as a [secret][secrets::register].
+    pub fn build(self) -> Result<GitHubClient> {
+        self.try_into()
+    }
+}
+
+/// GitHub REST API client.
+///
+/// See [module][self] documentation.
+pub struct GitHubClient {
+    client: ClientWithMiddleware,
+    /// Client without retries for streaming uploads.
+    upload_client: reqwest::Client,
+    base_url: String,
+    graphql_url: Url,
+    api_headers: HeaderMap,
+    max_rate_limit_wait: Duration,
+    conditional_requests: bool,
+
+    rate_limit: Mutex<Option<RateLimit>>,
+    cache: Mutex<ResponseCache>,
+}
+
+impl TryInto<GitHubClient> for GitHubClientBuilder {
+    type Error = Error;
+
+    fn try_into(self) -> Result<GitHubClient, Self::Error> {
+        let mut api_headers = HeaderMap::new();
+        api_headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static("application/vnd.github+json"),
+        );
+        api_headers.insert(
+            "x-github-api-version",
+            HeaderValue::from_static(API_VERSION),
+        );
+
+        if !self.token.is_empty() {
+            secrets::register(&self.token);
+
+            let auth_value = Bytes::from(format!("Bearer {}", self.token));
+            let mut auth_value = HeaderValue::from_maybe_shared(auth_value)?;
+            auth_value.set_sensitive(true);
+            api_headers.insert(header::AUTHORIZATION, auth_value);
+        }
+
+        let options = ClientOptions {
+            user_agent: &self.user_agent,
+            max_retries: self.max_retries,
+            min_retry_interval: self.min_retry_interval,
+            max_retry_interval: self.max_retry_interval,
+            backoff_factor_base: self.backoff_factor_base,
+            ca_bundle: None,
+        };
+        let upload_client = options.build_client()?;
+        let client = options.with_middleware(upload_client.clone(), &[]);
+
+        // Fail early on an invalid base URL
+        let base_url = self.base_url.trim_end_matches('/').to_string();
+        Url::parse(&base_url)?;
+        let graphql_url = match &self.graphql_url {
+            Some(graphql_url) => Url::parse(graphql_url)?,
+            None => match base_url.strip_suffix("/api/v3") {
+                Some(server_url) => Url::parse(&format!("{server_url}/api/graphql"))?,
+                None => Url::parse(&format!("{base_url}/graphql"))?,
+            },
+        };
+
+        Ok(GitHubClient {
+            client,
+            upload_client,
+            base_url,
+            graphql_url,
+            api_headers,
+            max_rate_limit_wait: self.max_rate_limit_wait,
+            conditional_requests: self.conditional_requests,
+            rate_limit: Mutex::new(None),
+            cache: Mutex::new(ResponseCache::default()),
+        })
+    }
+}
+
+/// Rate limit status of the GitHub REST API from the `x-ratelimit-*`
+/// headers of the last response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Maximum number of requests per hour.
+    pub limit: u64,
+
+    /// Number of requests remaining in the current window.
+    pub remaining: u64,
+
+    /// Number of requests made in the current window.
+    pub used: u64,
+
+    /// Time the current window resets.
+    pub reset: SystemTime,
+
+    /// Rate limit resource, e.g. `core` or `search`.
+    pub resource: String,
+}
+
+impl RateLimit {
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name)?.to_str().ok();
+        let number = |name: &str| header(name)?.parse::<u64>().ok();
+
+        let remaining = number("x-ratelimit-remaining")?;
+        Some(Self {
+            limit: number("x-ratelimit-limit").unwrap_or_default(),
+            remaining,
+            used: number("x-ratelimit-used").unwrap_or_default(),
+            reset: SystemTime::UNIX_EPOCH
+                + Duration::from_secs(number("x-ratelimit-reset").unwrap_or_default()),
+            resource: header("x-ratelimit-resource")
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+}
+
+/// Response of the GitHub REST API.
+#[derive(Debug, Clone)]
+pub(crate) struct Response {
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl Response {
+    /// Deserializes the JSON body, treating an empty body as `null`.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        let body: &[u8] = if self.body.is_empty() {
+            b"null"
+        } else {
+            &self.body
+        };
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+/// Cached `GET` responses with an `ETag`, evicting the least recently used
+/// response beyond [`MAX_CACHED_RESPONSES`].
+#[derive(Default)]
+struct ResponseCache {
+    responses: HashMap<Url, Response>,
+    /// URLs from the least to the most recently used.
+    order: VecDeque<Url>,
+}
+
+impl ResponseCache {
+    fn get(&mut self, url: &Url) -> Option<&Response> {
+        self.touch(url);
+        self.responses.get(url)
+    }
+
+    fn insert(&mut self, url: Url, response: Response) {
+        if self.responses.insert(url.clone(), response).is_some() {
+            self.touch(&url);
+            return;
+        }
+        if self.order.len() >= MAX_CACHED_RESPONSES {
+            if let Some(evicted) = self.order.pop_front() {
+                self.responses.remove(&evicted);
+            }
+        }
+        self.order.push_back(url);
+    }
+
+    fn touch(&mut self, url: &Url) {
+        if let Some(index) = self.order.iter().position(|cached| cached == url) {
+            self.order.remove(index);
+            self.order.push_back(url.clone());
+        }
+    }
+}
+
+impl GitHubClient {
+    /// Creates a new [`GitHubClientBuilder`].
+    ///
+    /// See [`GitHubClientBuilder::new`].
+    pub fn builder<B: Into<String>, T: Into<String>>(base_url: B, token: T) -> GitHubClientBuilder {
+        GitHubClientBuilder::new(base_url, token)
+    }
+
+    /// Creates a new [`GitHubClientBuilder`] from environmental variables.
+    ///
+    /// See [`GitHubClientBuilder::from_env`].
+    pub fn from_env() -> Result<GitHubClientBuilder> {
+        GitHubClientBuilder::from_env()
+    }
+
+    /// Gets the GitHub REST API base URL.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Gets the GitHub GraphQL API URL.
+    pub fn graphql_url(&self) -> &str {
+        self.graphql_url.as_str()
+    }
+
+    /// Gets the rate limit status from the last response, if any.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit.lock().unwrap().clone()
+    }
+
+    /// Sends a `GET` request and deserializes the JSON response.
+    ///
+    /// `path` is relative to the base URL, e.g. `/repos/OWNER/REPO`, or an
+    /// absolute URL. The token is only sent to the REST and GraphQL API
+    /// hosts.
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(Method::GET, path, None).await?.json()
+    }
+
+    /// Sends a `POST` request with a JSON body and deserializes the JSON
+    /// response.
+    pub async fn post<B, T>(&self, path: &str, body: &B) -> Result<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        self.send_json(Method::POST, path, body).await
+    }
+
+    /// Sends a `PATCH` request with a JSON body and deserializes the JSON
+    /// response.
+    pub async fn patch<B, T>(&self, path: &str, body: &B) -> Result<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        self.send_json(Method::PATCH, path, body).await
+    }
+
+    /// Sends a `PUT` request with a JSON body and deserializes the JSON
+    /// response.
+    pub async fn put<B, T>(&self, path: &str, body: &B) -> Result<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        self.send_json(Method::PUT, path, body).await
+    }
+
+    /// Sends a `DELETE` request.
+    pub async fn delete(&self, path: &str) -> Result<()> {
+        self.send(Method::DELETE, path, None).await?;
+        Ok(())
+    }
+
+    /// Reads all items of a paginated endpoint, following the `next` link of
+    /// each page.
+    ///
+    /// Pages are either arrays of items or objects with an array of items
+    /// and metadata like `total_count`.
+    pub fn paginate<'a, T: DeserializeOwned + 'a>(
+        &'a self,
+        path: &str,
+    ) -> impl Stream<Item = Result<T>> + Unpin + 'a {
+        let state = (Some(path.to_string()), VecDeque::new());
+        Box::pin(stream::try_unfold(
+            state,
+            move |(mut next, mut items): (Option<String>, VecDeque<T>)| async move {
+                loop {
+                    if let Some(item) = items.pop_front() {
+                        return Ok(Some((item, (next, items))));
+                    }
+                    let path = match next.take() {
+                        Some(path) => path,
+                        None => return Ok(None),
+                    };
+
+                    let response = self.send(Method::GET, &path, None).await?;
+                    next = next_link(&response.headers);
+                    items = page_items(&response.body)?.into();
+                }
+            },
+        ))
+    }
+
+    async fn send_json<B, T>(&self, method: Method, path: &str, body: &B) -> Result<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let body = Bytes::from(serde_json::to_vec(body)?);
+        self.send(method, path, Some(body)).await?.json()
+    }
+
+    /// Sends a request with an optional JSON body, waiting for rate limits to
+    /// reset and revalidating cached `GET` responses.
+    #[instrument(skip(self, body))]
+    pub(crate) async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Bytes>,
+    ) -> Result<Response> {
+        let url = self.url(path)?;
+        let cacheable = self.conditional_requests && method == Method::GET;
+        let mut waited = Duration::ZERO;
+
+        loop {
+            let mut request = self
+                .client
+                .request(method.clone(), url.clone())
+                .headers(self.headers_for(&url));
+            if cacheable {
+                let etag = self
+                    .cache
+                    .lock()
+                    .unwrap()
+                    .get(&url)
+                    .and_then(|cached| cached.headers.get(header::ETAG).cloned());
+                if let Some(etag) = etag {
+                    request = request.header(header::IF_NONE_MATCH, etag);
+                }
+            }
+            if let Some(body) = &body {
+                request = request
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(body.clone());
+            }
+
+            let response = request.send().await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response.bytes().await?;
+
+            if let Some(rate_limit) = RateLimit::from_headers(&headers) {
+                *self.rate_limit.lock().unwrap() = Some(rate_limit);
+            }
+
+            if status == StatusCode::NOT_MODIFIED && cacheable {
+                let cached = self.cache.lock().unwrap().get(&url).cloned();
+                if let Some(cached) = cached {
+                    debug!("Not modified: {url}");
+                    return Ok(cached);
+                }
+            }
+
+            if status.is_success() {
+                let response = Response { headers, body };
+                if cacheable && response.headers.contains_key(header::ETAG) {
+                    self.cache.lock().unwrap().insert(url, response.clone());
+                }
+                return Ok(response);
+            }
+
+            if let Some(wait) = rate_limit_wait(status, &headers, &body) {
+                if waited + wait > self.max_rate_limit_wait {
+                    return Err(Error::GitHubRateLimited { retry_after: wait });
+                }
+                warn!("GitHub API rate limit exceeded, retrying in {wait:?}");
+                tokio::time::sleep(wait).await;
+                waited += wait;
+                continue;
+            }
+
+            return Err(status_error(status, &body));
+        }
+    }
+
+    /// Gets the API headers for `url`, without the token unless `url` is on
+    /// the origin of the REST or GraphQL API, e.g. for `next` links.
+    fn headers_for(&self, url: &Url) -> HeaderMap {
+        let mut headers = self.api_headers.clone();
+        let trusted = url.origin() == self.graphql_url.origin()
+            || Url::parse(&self.base_url)
+                .map_or(false, |base_url| base_url.origin() == url.origin());
+        if !trusted {
+            headers.remove(header::AUTHORIZATION);
+        }
+        headers
+    }
+
+    /// Resolves a path relative to the base URL, or an absolute URL.
+    fn url(&self, path: &str) -> Result<Url> {
+        if path.starts_with("https://") || path.starts_with("http://") {
+            Ok(Url::parse(path)?)
+        } else {
+            Ok(Url::parse(&format!(
+                "{}/{}",
+                self.base_url,
+                path.trim_start_matches('/')
+            ))?)
+        }
+    }
+}
+
+/// Gets the time to wait before retrying a request that exceeded the primary
+/// or secondary rate limit, or `None` if it didn't.
+///
+/// See [Rate limits for the REST
+/// API](https://docs.github.com/en/rest/using-the-rest-api/rate-limits-for-the-rest-api).
+fn rate_limit_wait(status: StatusCode, headers: &HeaderMap, body: &[u8]) -> Option<Duration> {
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    if let Some(retry_after) = headers
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+    {
+        return Some(Duration::from_secs(retry_after));
+    }
+
+    if let Some(rate_limit) = RateLimit::from_headers(headers) {
+        if rate_limit.remaining == 0 {
+            let wait = rate_limit
+                .reset
+                .duration_since(SystemTime::now())
+                .unwrap_or_default();
+            // The reset time is rounded down to seconds
+            return Some(wait + Duration::from_secs(1));
+        }
+    }
+
+    let message = String::from_utf8_lossy(body).to_lowercase();
+    if message.contains("secondary rate limit") {
+        return Some(SECONDARY_RATE_LIMIT_WAIT);
+    }
+    None
+}
+
+/// Creates an [`Error::GitHubApiStatus`] from an unsuccessful response with
+/// registered secrets scrubbed from the message.
+fn status_error(status: StatusCode, body: &[u8]) -> Error {
+    #[derive(Deserialize)]
+    struct ErrorResponse {
+        message: String,
+    }
+
+    let message = match serde_json::from_slice::<ErrorResponse>(body) {
+        Ok(response) => response.message,
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    };
+    let message = secrets::redact(&message).into_owned();
+    Error::GitHubApiStatus { status, message }
+}
+
+/// Gets the URL of the next page from a `Link` header, e.g.
+/// `<https://api.github.com/...?page=2>; rel="next"`.
+fn next_link(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(header::LINK)?.to_str().ok()?;
+    link.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#)
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+/// Deserializes the items of a page.
+fn page_items<T: DeserializeOwned>(body: &[u8]) -> Result<Vec<T>> {
+    let items = match serde_json::from_slice(body)? {
+        serde_json::Value::Object(mut page) => {
+            for key in PAGINATION_METADATA_KEYS {
+                page.remove(*key);
+            }
+            page.into_iter()
+                .map(|(_, value)| value)
+                .find(serde_json::Value::is_array)
+                .unwrap_or_default()
+        }
+        items => items,
+    };
+    if items.is_null() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_value(items)?)
+}
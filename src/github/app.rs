@@ -0,0 +1,286 @@
+//! GitHub App authentication.
+//!
+//! A [`GitHubApp`] signs JSON Web Tokens with the private key of a GitHub App
+//! and exchanges them for installation access tokens, to call the API as the
+//! app instead of with the `GITHUB_TOKEN` of the workflow run, e.g. to push
+//! to another repository or trigger workflows. JSON Web Tokens and
+//! installation tokens are cached until shortly before they expire and
+//! registered as [secrets][crate::core::secrets]. Requires the `github-app`
+//! feature.
+//!
+//! ```rust,no_run
+//! use gha_toolkit::github::app::{GitHubApp, TokenScope};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let private_key = std::env::var("APP_PRIVATE_KEY")?;
+//! let app = GitHubApp::new("123456", private_key.as_bytes())?;
+//!
+//! let installation_id = app.repository_installation_id("octo-org/octo-repo").await?;
+//! let scope = TokenScope::default()
+//!     .repository("octo-repo")
+//!     .permission("contents", "write");
+//! let client = app.installation_client(installation_id, &scope).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use async_lock::Mutex;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::sign::Signer;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, instrument};
+
+use super::{GitHubClient, GitHubClientBuilder};
+use crate::core::secrets;
+use crate::datetime::DateTime;
+use crate::Result;
+
+/// Time to backdate the issue time of JWTs to allow for clock drift.
+const JWT_CLOCK_DRIFT: Duration = Duration::from_secs(60);
+
+/// Lifetime of JWTs, at most 10 minutes.
+const JWT_LIFETIME: Duration = Duration::from_secs(9 * 60);
+
+/// Time before expiry to replace cached JWTs.
+const JWT_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Time before expiry to replace cached installation tokens.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// Repositories and permissions of an installation access token.
+///
+/// The token has all repositories and permissions of the installation if
+/// empty.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq, Hash)]
+pub struct TokenScope {
+    /// Names of repositories without the owner, e.g. `octo-repo`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub repositories: Vec<String>,
+
+    /// Permissions by name, e.g. `contents` to `write`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub permissions: BTreeMap<String, String>,
+}
+
+impl TokenScope {
+    /// Adds a repository.
+    pub fn repository<T: Into<String>>(mut self, repository: T) -> Self {
+        self.repositories.push(repository.into());
+        self
+    }
+
+    /// Adds a permission, e.g. `contents` with level `read` or `write`.
+    pub fn permission<N: Into<String>, L: Into<String>>(mut self, name: N, level: L) -> Self {
+        self.permissions.insert(name.into(), level.into());
+        self
+    }
+}
+
+/// Installation access token.
+///
+/// The token is never written by the [`Debug`][fmt::Debug] implementation.
+#[derive(Clone, Deserialize, PartialEq, Eq)]
+pub struct InstallationToken {
+    /// Access token.
+    pub token: String,
+
+    /// Expiry time, e.g. `2022-12-01T08:30:00Z`.
+    pub expires_at: String,
+
+    /// Permissions of the token by name.
+    #[serde(default)]
+    pub permissions: BTreeMap<String, String>,
+}
+
+impl fmt::Debug for InstallationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstallationToken")
+            .field("token", &secrets::MASK)
+            .field("expires_at", &self.expires_at)
+            .field("permissions", &self.permissions)
+            .finish()
+    }
+}
+
+impl InstallationToken {
+    /// Whether the token expires within `margin`. Tokens with an invalid
+    /// expiry time are treated as expired.
+    fn expires_within(&self, margin: Duration) -> bool {
+        match DateTime::parse(&self.expires_at) {
+            Some(expires_at) => SystemTime::now() + margin >= expires_at.to_system_time(),
+            None => true,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Installation {
+    id: u64,
+}
+
+/// GitHub App.
+///
+/// See [module][self] documentation.
+pub struct GitHubApp {
+    app_id: String,
+    key: PKey<Private>,
+    builder: GitHubClientBuilder,
+
+    /// Cached JWT with its expiry time.
+    jwt: std::sync::Mutex<Option<(String, SystemTime)>>,
+    /// Cached installation tokens by installation ID and scope.
+    tokens: Mutex<HashMap<(u64, TokenScope), InstallationToken>>,
+}
+
+impl fmt::Debug for GitHubApp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitHubApp")
+            .field("app_id", &self.app_id)
+            .field("key", &secrets::MASK)
+            .field("builder", &self.builder)
+            .finish()
+    }
+}
+
+impl GitHubApp {
+    /// Creates a new [`GitHubApp`] from its app ID, or client ID, and its
+    /// PEM encoded RSA private key.
+    ///
+    /// Requests are sent to `https://api.github.com`, see
+    /// [`client_builder`][Self::client_builder].
+    pub fn new<T: Into<String>>(app_id: T, private_key_pem: &[u8]) -> Result<Self> {
+        let key = PKey::private_key_from_pem(private_key_pem)?;
+        // Fail early on keys that can't sign with RS256
+        key.rsa()?;
+
+        Ok(Self {
+            app_id: app_id.into(),
+            key,
+            builder: Default::default(),
+            jwt: Default::default(),
+            tokens: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Sets the builder of API clients, e.g. with the API URLs of a GitHub
+    /// Enterprise Server instance. Its token is replaced by the JWT or
+    /// installation access token.
+    pub fn client_builder(mut self, builder: GitHubClientBuilder) -> Self {
+        self.builder = builder;
+        self
+    }
+
+    /// Gets a JSON Web Token authenticating as the app, valid for 9 minutes,
+    /// reusing a cached token unless it expires within a minute.
+    ///
+    /// The token is registered as a [secret][secrets::register].
+    pub fn jwt(&self) -> Result<String> {
+        let mut cached = self.jwt.lock().unwrap();
+        if let Some((jwt, expires_at)) = &*cached {
+            if SystemTime::now() + JWT_REFRESH_MARGIN < *expires_at {
+                return Ok(jwt.clone());
+            }
+        }
+
+        let issued_at = SystemTime::now();
+        let now = issued_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let header = json!({"alg": "RS256", "typ": "JWT"});
+        let claims = json!({
+            "iat": (now - JWT_CLOCK_DRIFT).as_secs(),
+            "exp": (now + JWT_LIFETIME).as_secs(),
+            "iss": self.app_id,
+        });
+
+        let message = format!(
+            "{}.{}",
+            base64::encode_config(serde_json::to_vec(&header)?, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(serde_json::to_vec(&claims)?, base64::URL_SAFE_NO_PAD),
+        );
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+        signer.update(message.as_bytes())?;
+        let signature = signer.sign_to_vec()?;
+
+        let jwt = format!(
+            "{message}.{}",
+            base64::encode_config(signature, base64::URL_SAFE_NO_PAD)
+        );
+        secrets::register(&jwt);
+
+        *cached = Some((jwt.clone(), issued_at + JWT_LIFETIME));
+        Ok(jwt)
+    }
+
+    /// Creates a [`GitHubClient`] authenticating as the app, for the `/app`
+    /// endpoints.
+    pub fn app_client(&self) -> Result<GitHubClient> {
+        self.builder
+            .clone()
+            .token(self.jwt()?)
+            .conditional_requests(false)
+            .build()
+    }
+
+    /// Gets the ID of the installation of the app on `repository`, e.g.
+    /// `octo-org/octo-repo`.
+    #[instrument(skip(self))]
+    pub async fn repository_installation_id(&self, repository: &str) -> Result<u64> {
+        let installation: Installation = self
+            .app_client()?
+            .get(&format!("/repos/{repository}/installation"))
+            .await?;
+        Ok(installation.id)
+    }
+
+    /// Gets an installation access token with `scope`, reusing a cached
+    /// token unless it expires within 5 minutes.
+    ///
+    /// The token is registered as a [secret][secrets::register].
+    #[instrument(skip(self))]
+    pub async fn installation_token(
+        &self,
+        installation_id: u64,
+        scope: &TokenScope,
+    ) -> Result<InstallationToken> {
+        // Held while creating a token so concurrent callers share it
+        let mut tokens = self.tokens.lock().await;
+        let key = (installation_id, scope.clone());
+        if let Some(token) = tokens.get(&key) {
+            if !token.expires_within(TOKEN_REFRESH_MARGIN) {
+                return Ok(token.clone());
+            }
+        }
+
+        debug!("Creating installation access token");
+        let token: InstallationToken = self
+            .app_client()?
+            .post(
+                &format!("/app/installations/{installation_id}/access_tokens"),
+                scope,
+            )
+            .await?;
+        secrets::register(&token.token);
+
+        tokens.insert(key, token.clone());
+        Ok(token)
+    }
+
+    /// Creates a [`GitHubClient`] authenticating as the installation with an
+    /// [installation access token][Self::installation_token].
+    pub async fn installation_client(
+        &self,
+        installation_id: u64,
+        scope: &TokenScope,
+    ) -> Result<GitHubClient> {
+        let token = self.installation_token(installation_id, scope).await?;
+        self.builder.clone().token(token.token).build()
+    }
+}
@@ -0,0 +1,415 @@
+//! GitHub check runs.
+//!
+//! A check run reports the result of a tool on a commit with a summary and
+//! annotations on the changed files, shown on the pull request. The token
+//! needs the `checks: write` permission.
+//!
+//! The API accepts at most 50 annotations per request, so
+//! [`GitHubClient::create_check_run`] and [`GitHubClient::update_check_run`]
+//! send the rest in follow-up updates.
+//!
+//! ```rust,no_run
+//! use gha_toolkit::core::{Annotation, AnnotationLevel};
+//! use gha_toolkit::github::checks::*;
+//! use gha_toolkit::github::GitHubClient;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let client = GitHubClient::from_env()?.build()?;
+//!
+//! let warning = Annotation::new(AnnotationLevel::Warning, "Unused variable")
+//!     .file("src/main.rs")
+//!     .lines(3, 3);
+//!
+//! let mut options = CheckRunOptions::from_env("lint")?;
+//! options.conclusion = Some(CheckConclusion::Neutral);
+//! options.output = Some(CheckRunOutput {
+//!     title: "1 warning".into(),
+//!     summary: "Found 1 warning".into(),
+//!     annotations: CheckAnnotation::from_annotation(&warning).into_iter().collect(),
+//!     ..Default::default()
+//! });
+//!
+//! let check_run = client.create_check_run("octo-org/octo-repo", &options).await?;
+//! println!("Created check run {}", check_run.id);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::env;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use super::GitHubClient;
+use crate::core::{Annotation, AnnotationLevel};
+use crate::{Error, Result};
+
+/// Maximum number of annotations per request.
+const MAX_ANNOTATIONS: usize = 50;
+
+/// Maximum length in bytes of the summary and text of an output.
+const MAX_OUTPUT_LENGTH: usize = 65535;
+
+/// Status of a check run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Queued,
+    InProgress,
+    Completed,
+    Waiting,
+    Requested,
+    Pending,
+    /// Status unknown to this version of the crate.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Conclusion of a completed check run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckConclusion {
+    ActionRequired,
+    Cancelled,
+    Failure,
+    Neutral,
+    Success,
+    Skipped,
+    Stale,
+    TimedOut,
+}
+
+/// Severity of a [`CheckAnnotation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckAnnotationLevel {
+    Notice,
+    Warning,
+    Failure,
+}
+
+impl From<AnnotationLevel> for CheckAnnotationLevel {
+    fn from(level: AnnotationLevel) -> Self {
+        match level {
+            AnnotationLevel::Error => Self::Failure,
+            AnnotationLevel::Warning => Self::Warning,
+            AnnotationLevel::Notice => Self::Notice,
+        }
+    }
+}
+
+/// Annotation of a line range of a file in a check run.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CheckAnnotation {
+    /// Path of the annotated file, relative to the repository root.
+    pub path: String,
+
+    /// Start line of the annotation.
+    pub start_line: u32,
+
+    /// End line of the annotation.
+    pub end_line: u32,
+
+    /// Start column of the annotation, only if on a single line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_column: Option<u32>,
+
+    /// End column of the annotation, only if on a single line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_column: Option<u32>,
+
+    /// Severity of the annotation.
+    pub annotation_level: CheckAnnotationLevel,
+
+    /// Annotation message.
+    pub message: String,
+
+    /// Custom title for the annotation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// Details of the annotation, e.g. the output of the tool.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_details: Option<String>,
+}
+
+impl CheckAnnotation {
+    /// Converts a workflow command [`Annotation`], or returns `None` if it has
+    /// no file.
+    ///
+    /// The annotation defaults to the first line of the file. Columns are
+    /// dropped if the annotation spans multiple lines, which the API
+    /// rejects.
+    pub fn from_annotation(annotation: &Annotation) -> Option<Self> {
+        let props = &annotation.properties;
+        let path = props.file.clone()?;
+        let start_line = props.start_line.unwrap_or(1);
+        let end_line = props.end_line.unwrap_or(start_line);
+
+        let (start_column, end_column) = if start_line == end_line {
+            (props.start_column, props.end_column.or(props.start_column))
+        } else {
+            (None, None)
+        };
+
+        Some(Self {
+            path,
+            start_line,
+            end_line,
+            start_column,
+            end_column,
+            annotation_level: annotation.level.into(),
+            message: annotation.message.clone(),
+            title: props.title.clone(),
+            raw_details: None,
+        })
+    }
+}
+
+/// Output of a check run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckRunOutput {
+    /// Title of the output.
+    pub title: String,
+
+    /// Summary in Markdown, truncated to the maximum of 65535 bytes.
+    pub summary: String,
+
+    /// Details in Markdown, truncated to the maximum of 65535 bytes.
+    pub text: Option<String>,
+
+    /// Annotations, sent in batches of 50.
+    pub annotations: Vec<CheckAnnotation>,
+}
+
+/// Options for [`GitHubClient::create_check_run`] and
+/// [`GitHubClient::update_check_run`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckRunOptions {
+    /// Name of the check run.
+    pub name: String,
+
+    /// SHA of the checked commit. Not changed by updates.
+    pub head_sha: String,
+
+    /// Status of the check run. Defaults to `queued` for new check runs.
+    pub status: Option<CheckStatus>,
+
+    /// Conclusion of the check run, which completes it.
+    pub conclusion: Option<CheckConclusion>,
+
+    /// URL of the full details of the check run.
+    pub details_url: Option<String>,
+
+    /// ID of the check run in the tool.
+    pub external_id: Option<String>,
+
+    /// Output of the check run.
+    pub output: Option<CheckRunOutput>,
+}
+
+impl CheckRunOptions {
+    /// Creates new [`CheckRunOptions`] for a commit.
+    pub fn new<N: Into<String>, S: Into<String>>(name: N, head_sha: S) -> Self {
+        Self {
+            name: name.into(),
+            head_sha: head_sha.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates new [`CheckRunOptions`] for the commit of the workflow run,
+    /// `GITHUB_SHA`.
+    pub fn from_env<N: Into<String>>(name: N) -> Result<Self> {
+        let head_sha = env::var("GITHUB_SHA").map_err(|source| Error::VarError {
+            source,
+            name: "GITHUB_SHA",
+        })?;
+        Ok(Self::new(name, head_sha))
+    }
+}
+
+/// Check run.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CheckRun {
+    /// ID of the check run.
+    pub id: u64,
+
+    /// Name of the check run.
+    pub name: String,
+
+    /// SHA of the checked commit.
+    pub head_sha: String,
+
+    /// Status of the check run.
+    pub status: CheckStatus,
+
+    /// Conclusion of the completed check run.
+    pub conclusion: Option<CheckConclusion>,
+
+    /// URL of the check run on GitHub.
+    pub html_url: Option<String>,
+
+    /// URL of the full details of the check run.
+    pub details_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CheckRunRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head_sha: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<CheckStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conclusion: Option<CheckConclusion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    external_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<CheckRunOutputRequest<'a>>,
+}
+
+#[derive(Serialize)]
+struct CheckRunOutputRequest<'a> {
+    title: &'a str,
+    summary: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    annotations: &'a [CheckAnnotation],
+}
+
+impl<'a> CheckRunOutputRequest<'a> {
+    fn new(output: &'a CheckRunOutput, annotations: &'a [CheckAnnotation]) -> Self {
+        Self {
+            title: &output.title,
+            summary: truncate(&output.summary, MAX_OUTPUT_LENGTH),
+            text: output
+                .text
+                .as_deref()
+                .map(|text| truncate(text, MAX_OUTPUT_LENGTH)),
+            annotations,
+        }
+    }
+}
+
+impl GitHubClient {
+    /// Creates a check run in `repository`, e.g. `octo-org/octo-repo`.
+    ///
+    /// Annotations beyond the first 50 are added with updates.
+    #[instrument(skip(self, options), fields(name = %options.name))]
+    pub async fn create_check_run(
+        &self,
+        repository: &str,
+        options: &CheckRunOptions,
+    ) -> Result<CheckRun> {
+        let mut batches = annotation_batches(options);
+        let request = CheckRunRequest {
+            name: Some(&options.name),
+            head_sha: Some(&options.head_sha),
+            status: options.status,
+            conclusion: options.conclusion,
+            details_url: options.details_url.as_deref(),
+            external_id: options.external_id.as_deref(),
+            output: options
+                .output
+                .as_ref()
+                .map(|output| CheckRunOutputRequest::new(output, batches.next().unwrap_or(&[]))),
+        };
+        let check_run: CheckRun = self
+            .post(&format!("/repos/{repository}/check-runs"), &request)
+            .await?;
+        debug!("Created check run {}", check_run.id);
+
+        self.add_check_run_annotations(repository, check_run, options, batches)
+            .await
+    }
+
+    /// Updates check run `id` in `repository`, e.g. `octo-org/octo-repo`.
+    ///
+    /// Annotations are added to the existing annotations of the check run,
+    /// 50 per request.
+    #[instrument(skip(self, options), fields(name = %options.name))]
+    pub async fn update_check_run(
+        &self,
+        repository: &str,
+        id: u64,
+        options: &CheckRunOptions,
+    ) -> Result<CheckRun> {
+        let mut batches = annotation_batches(options);
+        let request = CheckRunRequest {
+            name: (!options.name.is_empty()).then(|| options.name.as_str()),
+            head_sha: None,
+            status: options.status,
+            conclusion: options.conclusion,
+            details_url: options.details_url.as_deref(),
+            external_id: options.external_id.as_deref(),
+            output: options
+                .output
+                .as_ref()
+                .map(|output| CheckRunOutputRequest::new(output, batches.next().unwrap_or(&[]))),
+        };
+        let check_run = self
+            .patch(&format!("/repos/{repository}/check-runs/{id}"), &request)
+            .await?;
+
+        self.add_check_run_annotations(repository, check_run, options, batches)
+            .await
+    }
+
+    async fn add_check_run_annotations<'a>(
+        &self,
+        repository: &str,
+        mut check_run: CheckRun,
+        options: &'a CheckRunOptions,
+        batches: impl Iterator<Item = &'a [CheckAnnotation]>,
+    ) -> Result<CheckRun> {
+        let output = match &options.output {
+            Some(output) => output,
+            None => return Ok(check_run),
+        };
+
+        let path = format!("/repos/{repository}/check-runs/{}", check_run.id);
+        for annotations in batches {
+            debug!(
+                "Adding {} annotations to check run {}",
+                annotations.len(),
+                check_run.id
+            );
+            let request = CheckRunRequest {
+                name: None,
+                head_sha: None,
+                status: None,
+                conclusion: None,
+                details_url: None,
+                external_id: None,
+                output: Some(CheckRunOutputRequest::new(output, annotations)),
+            };
+            check_run = self.patch(&path, &request).await?;
+        }
+        Ok(check_run)
+    }
+}
+
+fn annotation_batches(options: &CheckRunOptions) -> impl Iterator<Item = &[CheckAnnotation]> {
+    options
+        .output
+        .iter()
+        .flat_map(|output| output.annotations.chunks(MAX_ANNOTATIONS))
+}
+
+/// Truncates a string to at most `max_len` bytes at a character boundary.
+fn truncate(s: &str, max_len: usize) -> &str {
+    if s.len() <= max_len {
+        return s;
+    }
+    let mut end = max_len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
@@ -0,0 +1,140 @@
+//! Sticky comments on issues and pull requests.
+//!
+//! A sticky comment is posted once and updated on later workflow runs, e.g.
+//! for a benchmark report. It's found by a hidden HTML comment with a marker
+//! at the start of its body, among the comments of the token's user so that
+//! other users can't plant a comment to be updated. The token needs the
+//! `pull-requests: write` or `issues: write` permission.
+//!
+//! ```rust,no_run
+//! use gha_toolkit::context::Context;
+//! use gha_toolkit::github::GitHubClient;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let context = Context::from_env()?;
+//! let client = GitHubClient::from_env()?.build()?;
+//!
+//! let body = "## Benchmarks\n\nNo regressions";
+//! let comment = client
+//!     .upsert_event_comment(&context, "benchmarks", None, body)
+//!     .await?;
+//! println!("Updated {}", comment.html_url);
+//! # Ok(())
+//! # }
+//! ```
+
+use futures::prelude::*;
+use serde::Serialize;
+use tracing::{debug, instrument};
+
+use super::GitHubClient;
+use crate::context::{Context, IssueComment};
+use crate::{Error, Result};
+
+#[derive(Serialize)]
+struct CommentRequest<'a> {
+    body: &'a str,
+}
+
+impl GitHubClient {
+    /// Finds the first comment of `author` starting with `marker` on issue or
+    /// pull request `issue_number` in `repository`, e.g. `octo-org/octo-repo`.
+    ///
+    /// `author` is the login of the token's user. Comments of any bot, e.g.
+    /// `github-actions[bot]` for the `GITHUB_TOKEN` or a GitHub App, are
+    /// matched if it's `None`.
+    ///
+    /// Fails with [`Error::InvalidCommentMarker`] if `marker` contains `-->`.
+    #[instrument(skip(self))]
+    pub async fn find_comment(
+        &self,
+        repository: &str,
+        issue_number: u64,
+        marker: &str,
+        author: Option<&str>,
+    ) -> Result<Option<IssueComment>> {
+        let marker = comment_marker(marker)?;
+        let path = format!("/repos/{repository}/issues/{issue_number}/comments?per_page=100");
+        let comment = self
+            .paginate::<IssueComment>(&path)
+            .try_filter(|comment| {
+                let is_author = match author {
+                    Some(author) => comment.user.login == author,
+                    None => comment.user.type_ == "Bot",
+                };
+                future::ready(is_author && comment.body.starts_with(&marker))
+            })
+            .try_next()
+            .await?;
+        Ok(comment)
+    }
+
+    /// Updates the comment of `author` with `marker` on issue or pull request
+    /// `issue_number` in `repository`, or creates it.
+    ///
+    /// The marker is added to the body as a hidden HTML comment, so it must
+    /// not contain `-->`, see [`find_comment`][Self::find_comment].
+    #[instrument(skip(self, body))]
+    pub async fn upsert_comment(
+        &self,
+        repository: &str,
+        issue_number: u64,
+        marker: &str,
+        author: Option<&str>,
+        body: &str,
+    ) -> Result<IssueComment> {
+        let body = format!("{}\n{body}", comment_marker(marker)?);
+        let request = CommentRequest { body: &body };
+
+        match self
+            .find_comment(repository, issue_number, marker, author)
+            .await?
+        {
+            Some(comment) => {
+                debug!("Updating comment {}", comment.id);
+                self.patch(
+                    &format!("/repos/{repository}/issues/comments/{}", comment.id),
+                    &request,
+                )
+                .await
+            }
+            None => {
+                debug!("Creating comment on #{issue_number}");
+                self.post(
+                    &format!("/repos/{repository}/issues/{issue_number}/comments"),
+                    &request,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Updates or creates the comment with `marker` on the pull request or
+    /// issue of a `pull_request`, `pull_request_target` or `issue_comment`
+    /// event.
+    ///
+    /// See [`upsert_comment`][Self::upsert_comment].
+    pub async fn upsert_event_comment(
+        &self,
+        context: &Context,
+        marker: &str,
+        author: Option<&str>,
+        body: &str,
+    ) -> Result<IssueComment> {
+        let issue_number = context
+            .event
+            .issue_number()
+            .ok_or_else(|| Error::MissingIssueNumber(context.event_name.clone()))?;
+        self.upsert_comment(&context.repository, issue_number, marker, author, body)
+            .await
+    }
+}
+
+/// Gets the hidden HTML comment identifying a sticky comment.
+fn comment_marker(marker: &str) -> Result<String> {
+    if marker.contains("-->") {
+        return Err(Error::InvalidCommentMarker(marker.to_string()));
+    }
+    Ok(format!("<!-- {marker} -->"))
+}
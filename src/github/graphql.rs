@@ -0,0 +1,228 @@
+//! GitHub GraphQL API.
+//!
+//! Queries are sent with [`GitHubClient::graphql`] to the GraphQL API URL of
+//! the client, `GITHUB_GRAPHQL_URL` in workflow runs, and the `data` of the
+//! response is deserialized into a caller type. Connections are read with
+//! [`GitHubClient::graphql_paginate`], which passes the `endCursor` of each
+//! page as the `$cursor` variable of the next:
+//!
+//! ```rust,no_run
+//! use futures::prelude::*;
+//! use gha_toolkit::github::graphql::Connection;
+//! use gha_toolkit::github::GitHubClient;
+//! use serde::Deserialize;
+//! use serde_json::json;
+//!
+//! #[derive(Deserialize)]
+//! struct Data {
+//!     repository: Repository,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! #[serde(rename_all = "camelCase")]
+//! struct Repository {
+//!     pull_request: PullRequest,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! #[serde(rename_all = "camelCase")]
+//! struct PullRequest {
+//!     review_threads: Connection<ReviewThread>,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! #[serde(rename_all = "camelCase")]
+//! struct ReviewThread {
+//!     id: String,
+//!     is_resolved: bool,
+//! }
+//!
+//! const QUERY: &str = "
+//!     query($owner: String!, $repo: String!, $number: Int!, $cursor: String) {
+//!         repository(owner: $owner, name: $repo) {
+//!             pullRequest(number: $number) {
+//!                 reviewThreads(first: 100, after: $cursor) {
+//!                     nodes { id isResolved }
+//!                     pageInfo { endCursor hasNextPage }
+//!                 }
+//!             }
+//!         }
+//!     }
+//! ";
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let client = GitHubClient::from_env()?.build()?;
+//!
+//! let variables = json!({"owner": "octo-org", "repo": "octo-repo", "number": 1});
+//! let mut threads = client.graphql_paginate(QUERY, &variables, |data: Data| {
+//!     data.repository.pull_request.review_threads
+//! });
+//! while let Some(thread) = threads.try_next().await? {
+//!     println!("{} resolved: {}", thread.id, thread.is_resolved);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use bytes::Bytes;
+use futures::prelude::*;
+use http::Method;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::instrument;
+
+use super::GitHubClient;
+use crate::{Error, Result};
+
+/// Page of a GraphQL connection, selected with
+/// `nodes { ... } pageInfo { endCursor hasNextPage }`.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection<T> {
+    /// Nodes of the page.
+    pub nodes: Vec<T>,
+
+    /// Cursor of the page.
+    pub page_info: PageInfo,
+}
+
+/// Cursor of a page of a GraphQL connection.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    /// Cursor of the last node of the page, to request the next page with
+    /// `after`.
+    pub end_cursor: Option<String>,
+
+    /// Whether there are more nodes after this page.
+    pub has_next_page: bool,
+}
+
+/// Error in the `errors` of a GraphQL response.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct GraphQlError {
+    /// Error message.
+    pub message: String,
+
+    /// Error type, e.g. `NOT_FOUND`.
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+
+    /// Path of the field in the query, e.g. `["repository", "pullRequest"]`.
+    #[serde(default)]
+    pub path: Vec<Value>,
+}
+
+impl fmt::Display for GraphQlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.type_ {
+            Some(type_) => write!(f, "{type_}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct GraphQlRequest<'a, V: ?Sized> {
+    query: &'a str,
+    variables: &'a V,
+}
+
+#[derive(Serialize)]
+struct PageVariables<'a, V: ?Sized> {
+    #[serde(flatten)]
+    variables: &'a V,
+    cursor: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+impl GitHubClient {
+    /// Sends a GraphQL query with variables and deserializes the `data` of
+    /// the response.
+    ///
+    /// Fails with [`Error::GitHubGraphQl`] if the response has `errors`, even
+    /// if it has partial `data`.
+    #[instrument(skip(self, variables))]
+    pub async fn graphql<V, T>(&self, query: &str, variables: &V) -> Result<T>
+    where
+        V: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let body = serde_json::to_vec(&GraphQlRequest { query, variables })?;
+        let graphql_url = self.graphql_url.as_str();
+        let response: GraphQlResponse<T> = self
+            .send(Method::POST, graphql_url, Some(Bytes::from(body)))
+            .await?
+            .json()?;
+
+        if !response.errors.is_empty() {
+            return Err(Error::GitHubGraphQl(response.errors));
+        }
+        response.data.ok_or_else(|| {
+            Error::GitHubGraphQl(vec![GraphQlError {
+                message: "Response has no data".into(),
+                type_: None,
+                path: Vec::new(),
+            }])
+        })
+    }
+
+    /// Reads all nodes of a GraphQL connection, following the `pageInfo` of
+    /// each page.
+    ///
+    /// The query must declare a `$cursor: String` variable passed as the
+    /// `after` argument of the connection, and `variables` must serialize to
+    /// an object. `connection` selects the connection from the `data` of
+    /// each response.
+    pub fn graphql_paginate<'a, V, T, R, F>(
+        &'a self,
+        query: &'a str,
+        variables: &'a V,
+        connection: F,
+    ) -> impl Stream<Item = Result<T>> + Unpin + 'a
+    where
+        V: Serialize + ?Sized,
+        T: 'a,
+        R: DeserializeOwned + 'a,
+        F: Fn(R) -> Connection<T> + 'a,
+    {
+        // The cursor of the next page, or `None` after the last page
+        let state = (Some(None), VecDeque::new(), connection);
+
+        Box::pin(stream::try_unfold(
+            state,
+            move |(mut next, mut nodes, connection): (Option<Option<String>>, VecDeque<T>, F)| async move {
+                loop {
+                    if let Some(node) = nodes.pop_front() {
+                        return Ok(Some((node, (next, nodes, connection))));
+                    }
+                    let cursor = match next.take() {
+                        Some(cursor) => cursor,
+                        None => return Ok(None),
+                    };
+
+                    let variables = PageVariables {
+                        variables,
+                        cursor: cursor.as_deref(),
+                    };
+                    let page = connection(self.graphql(query, &variables).await?);
+                    if page.page_info.has_next_page && page.page_info.end_cursor.is_some() {
+                        next = Some(page.page_info.end_cursor);
+                    }
+                    nodes = page.nodes.into();
+                }
+            },
+        ))
+    }
+}
@@ -0,0 +1,430 @@
+//! GitHub releases and release assets.
+//!
+//! Assets are uploaded as a stream from a file to the `upload_url` of the
+//! release, `https://uploads.github.com` or `https://HOST/api/uploads` for
+//! GitHub Enterprise Server, replacing an existing asset with the same name.
+//! A `SHA256SUMS` manifest in the format of `sha256sum` is created with
+//! [`sha256sums`] and checked after downloading assets with
+//! [`verify_sha256sums`]. The token needs the `contents: write` permission.
+//!
+//! ```rust,no_run
+//! use gha_toolkit::github::releases::{self, ReleaseOptions};
+//! use gha_toolkit::github::GitHubClient;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let client = GitHubClient::from_env()?.build()?;
+//!
+//! let options = ReleaseOptions::new("v1.0.0").name("Version 1.0.0");
+//! let release = client.find_or_create_release("octo-org/octo-repo", &options).await?;
+//!
+//! let files = ["target/release/octo-linux.tar.gz", "target/release/octo-macos.tar.gz"];
+//! for file in files {
+//!     let name = file.rsplit('/').next().unwrap();
+//!     client.upload_release_asset(&release, file, name).await?;
+//! }
+//!
+//! tokio::fs::write("SHA256SUMS", releases::sha256sums(&files[..]).await?).await?;
+//! client.upload_release_asset(&release, "SHA256SUMS", "SHA256SUMS").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::{Component, Path};
+
+use futures::prelude::*;
+use http::{header, HeaderValue, Method, StatusCode};
+use reqwest::{Body, Url};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, instrument};
+
+use super::{status_error, GitHubClient};
+use crate::io::sha256_file;
+use crate::transport;
+use crate::{Error, Result};
+
+/// Size of the chunks read from files for streaming uploads.
+const UPLOAD_CHUNK_SIZE: u64 = 1 << 20; // 1 MiB
+
+/// Suffix of the temporary name of an asset uploaded to replace another.
+const REPLACEMENT_SUFFIX: &str = ".partial";
+
+/// GitHub release.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct Release {
+    /// Release ID.
+    pub id: u64,
+
+    /// API URL of the release.
+    pub url: String,
+
+    /// URL of the release page.
+    pub html_url: String,
+
+    /// URI template for uploading assets, e.g.
+    /// `https://uploads.github.com/repos/octo-org/octo-repo/releases/1/assets{?name,label}`.
+    pub upload_url: String,
+
+    /// Tag name, e.g. `v1.0.0`.
+    pub tag_name: String,
+
+    /// Release name.
+    pub name: Option<String>,
+
+    /// Release notes.
+    pub body: Option<String>,
+
+    /// Whether the release is a draft.
+    #[serde(default)]
+    pub draft: bool,
+
+    /// Whether the release is a pre-release.
+    #[serde(default)]
+    pub prerelease: bool,
+
+    /// Assets of the release when it was fetched.
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// GitHub release asset.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct ReleaseAsset {
+    /// Asset ID.
+    pub id: u64,
+
+    /// API URL of the asset.
+    pub url: String,
+
+    /// Download URL of the asset.
+    pub browser_download_url: String,
+
+    /// File name.
+    pub name: String,
+
+    /// Size in bytes.
+    pub size: u64,
+
+    /// Media type, e.g. `application/octet-stream`.
+    pub content_type: String,
+
+    /// State, `uploaded` or `open` for failed uploads.
+    pub state: String,
+}
+
+#[derive(Serialize)]
+struct AssetUpdate<'a> {
+    name: &'a str,
+}
+
+/// Options for creating a release.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct ReleaseOptions {
+    /// Tag name, e.g. `v1.0.0`.
+    pub tag_name: String,
+
+    /// Commit SHA or branch to create the tag from if it doesn't exist.
+    /// Defaults to the default branch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_commitish: Option<String>,
+
+    /// Release name. Defaults to the tag name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Release notes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+
+    /// Whether to create a draft release.
+    pub draft: bool,
+
+    /// Whether to create a pre-release.
+    pub prerelease: bool,
+}
+
+impl ReleaseOptions {
+    /// Creates new [`ReleaseOptions`] for the tag `tag_name`.
+    pub fn new<T: Into<String>>(tag_name: T) -> Self {
+        Self {
+            tag_name: tag_name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the commit SHA or branch to create the tag from.
+    pub fn target_commitish<T: Into<String>>(mut self, target_commitish: T) -> Self {
+        self.target_commitish = Some(target_commitish.into());
+        self
+    }
+
+    /// Sets the release name.
+    pub fn name<T: Into<String>>(mut self, name: T) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the release notes.
+    pub fn body<T: Into<String>>(mut self, body: T) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Sets whether to create a draft release.
+    pub fn draft(mut self, draft: bool) -> Self {
+        self.draft = draft;
+        self
+    }
+
+    /// Sets whether to create a pre-release.
+    pub fn prerelease(mut self, prerelease: bool) -> Self {
+        self.prerelease = prerelease;
+        self
+    }
+}
+
+impl GitHubClient {
+    /// Gets the published release of `tag` in `repository`, e.g.
+    /// `octo-org/octo-repo`, or `None` if there is none.
+    #[instrument(skip(self))]
+    pub async fn release_by_tag(&self, repository: &str, tag: &str) -> Result<Option<Release>> {
+        match self
+            .get(&format!("/repos/{repository}/releases/tags/{tag}"))
+            .await
+        {
+            Ok(release) => Ok(Some(release)),
+            Err(Error::GitHubApiStatus {
+                status: StatusCode::NOT_FOUND,
+                ..
+            }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates a release in `repository`.
+    #[instrument(skip(self))]
+    pub async fn create_release(
+        &self,
+        repository: &str,
+        options: &ReleaseOptions,
+    ) -> Result<Release> {
+        self.post(&format!("/repos/{repository}/releases"), options)
+            .await
+    }
+
+    /// Gets the published release of the tag of `options` in `repository`,
+    /// or creates it.
+    pub async fn find_or_create_release(
+        &self,
+        repository: &str,
+        options: &ReleaseOptions,
+    ) -> Result<Release> {
+        match self.release_by_tag(repository, &options.tag_name).await? {
+            Some(release) => Ok(release),
+            None => {
+                debug!("Creating release {}", options.tag_name);
+                self.create_release(repository, options).await
+            }
+        }
+    }
+
+    /// Reads the current assets of `release`.
+    pub fn release_assets<'a>(
+        &'a self,
+        release: &Release,
+    ) -> impl Stream<Item = Result<ReleaseAsset>> + Unpin + 'a {
+        self.paginate(&format!("{}/assets?per_page=100", release.url))
+    }
+
+    /// Deletes a release asset.
+    #[instrument(skip_all, fields(name = %asset.name))]
+    pub async fn delete_release_asset(&self, asset: &ReleaseAsset) -> Result<()> {
+        self.delete(&asset.url).await
+    }
+
+    /// Renames a release asset.
+    #[instrument(skip_all, fields(name = %asset.name, new_name = name))]
+    pub async fn rename_release_asset(
+        &self,
+        asset: &ReleaseAsset,
+        name: &str,
+    ) -> Result<ReleaseAsset> {
+        self.patch(&asset.url, &AssetUpdate { name }).await
+    }
+
+    /// Uploads the file `path` as the asset `name` of `release`, replacing
+    /// an existing asset with the same name.
+    ///
+    /// The file is streamed, so the upload is not retried. A replacement is
+    /// uploaded under a temporary name with the suffix `.partial` and only
+    /// renamed after deleting the existing asset, so a failed upload leaves
+    /// the existing asset in place. A temporary asset left behind by a failed
+    /// upload is replaced by the next upload.
+    #[instrument(skip(self, release, path), fields(path = %path.as_ref().display()))]
+    pub async fn upload_release_asset<P: AsRef<Path>>(
+        &self,
+        release: &Release,
+        path: P,
+        name: &str,
+    ) -> Result<ReleaseAsset> {
+        let temporary_name = format!("{name}{REPLACEMENT_SUFFIX}");
+        let assets: Vec<ReleaseAsset> = self
+            .release_assets(release)
+            .try_filter(|asset| future::ready(asset.name == name || asset.name == temporary_name))
+            .try_collect()
+            .await?;
+        let existing = assets.iter().find(|asset| asset.name == name);
+        if let Some(asset) = assets.iter().find(|asset| asset.name == temporary_name) {
+            debug!("Deleting stale asset {} of {}", asset.id, release.tag_name);
+            self.delete_release_asset(asset).await?;
+        }
+
+        match existing {
+            Some(existing) => {
+                let asset = self
+                    .upload_asset_file(release, path.as_ref(), &temporary_name)
+                    .await?;
+                debug!("Replacing asset {} of {}", existing.id, release.tag_name);
+                self.delete_release_asset(existing).await?;
+                self.rename_release_asset(&asset, name).await
+            }
+            None => self.upload_asset_file(release, path.as_ref(), name).await,
+        }
+    }
+
+    /// Uploads the file `path` as the asset `name` of `release`.
+    async fn upload_asset_file(
+        &self,
+        release: &Release,
+        path: &Path,
+        name: &str,
+    ) -> Result<ReleaseAsset> {
+        // Strip the `{?name,label}` template of the upload URL
+        let upload_url = match release.upload_url.split_once('{') {
+            Some((upload_url, _)) => upload_url,
+            None => &release.upload_url,
+        };
+        let mut url = Url::parse(upload_url)?;
+        url.query_pairs_mut().append_pair("name", name);
+
+        let file = fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        let body = Body::wrap_stream(transport::read_chunks(file, UPLOAD_CHUNK_SIZE));
+
+        let response = self
+            .upload_client
+            .post(url)
+            .headers(self.api_headers.clone())
+            .header(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/octet-stream"),
+            )
+            .header(header::CONTENT_LENGTH, size)
+            .body(body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.bytes().await?;
+        if !status.is_success() {
+            return Err(status_error(status, &body));
+        }
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Downloads a release asset to the file `dest`.
+    #[instrument(skip(self, asset, dest), fields(name = %asset.name))]
+    pub async fn download_release_asset<P: AsRef<Path>>(
+        &self,
+        asset: &ReleaseAsset,
+        dest: P,
+    ) -> Result<()> {
+        let url = self.url(&asset.url)?;
+        let mut headers = self.headers_for(&url);
+        headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static("application/octet-stream"),
+        );
+
+        // Redirects to storage drop the authorization header
+        let mut response = self
+            .client
+            .request(Method::GET, url)
+            .headers(headers)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.bytes().await?;
+            return Err(status_error(status, &body));
+        }
+
+        let mut file = fs::File::create(dest).await?;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Creates a `SHA256SUMS` manifest of `files` in the format of `sha256sum`,
+/// e.g. `<hex digest>  octo-linux.tar.gz`, with the file names of the paths.
+pub async fn sha256sums<P: AsRef<Path>>(files: &[P]) -> Result<String> {
+    let mut manifest = String::new();
+    for file in files {
+        let file = file.as_ref();
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let digest = sha256_file(file).await?;
+        manifest.push_str(&format!("{digest}  {name}\n"));
+    }
+    Ok(manifest)
+}
+
+/// Verifies the files in `dir` listed in a `SHA256SUMS` `manifest` and
+/// returns their names.
+///
+/// Fails with [`Error::ReleaseAssetChecksum`] on the first file that doesn't
+/// match its digest, and with [`Error::InvalidChecksumManifest`] on names
+/// that aren't plain file names in `dir`, e.g. `../file` or `/etc/file`.
+pub async fn verify_sha256sums<P: AsRef<Path>>(manifest: &str, dir: P) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for line in manifest.lines().filter(|line| !line.trim().is_empty()) {
+        // Binary mode file names start with `*`
+        let (expected, name) = line
+            .split_once("  ")
+            .or_else(|| line.split_once(" *"))
+            .filter(|(digest, name)| {
+                digest.len() == 64
+                    && digest.bytes().all(|b| b.is_ascii_hexdigit())
+                    && is_file_name(name)
+            })
+            .ok_or_else(|| Error::InvalidChecksumManifest(line.to_string()))?;
+
+        let actual = sha256_file(&dir.as_ref().join(name)).await?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::ReleaseAssetChecksum {
+                name: name.to_string(),
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+        names.push(name.to_string());
+    }
+    Ok(names)
+}
+
+/// Checks that `name` is a single normal path component without separators.
+fn is_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    !name.contains(['/', '\\'])
+        && matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        )
+}
@@ -0,0 +1,421 @@
+//! GitHub Actions workflow runs.
+//!
+//! [`GitHubClient::dispatch_workflow_run`] triggers a `workflow_dispatch`
+//! event and finds the resulting run, which the API doesn't return, as the
+//! first new run of the workflow for the ref. Runs are polled with
+//! exponential backoff until they complete, and the logs of their jobs are
+//! read line by line. The token needs the `actions: write` permission in the
+//! repository of the workflow, so the `GITHUB_TOKEN` of another repository
+//! can't be used.
+//!
+//! ```rust,no_run
+//! use futures::prelude::*;
+//! use gha_toolkit::github::workflows::{PollOptions, WorkflowRunConclusion};
+//! use gha_toolkit::github::GitHubClient;
+//! use serde_json::json;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let client = GitHubClient::from_env()?.build()?;
+//! let repository = "octo-org/octo-repo";
+//! let options = PollOptions::default();
+//!
+//! let inputs = json!({"environment": "staging"});
+//! let run = client
+//!     .dispatch_workflow_run(repository, "deploy.yml", "main", &inputs, &options)
+//!     .await?;
+//! println!("Waiting for {}", run.html_url);
+//!
+//! let run = client.wait_for_workflow_run(repository, run.id, &options).await?;
+//! if run.conclusion != Some(WorkflowRunConclusion::Success) {
+//!     let mut jobs = client.workflow_run_jobs(repository, run.id);
+//!     while let Some(job) = jobs.try_next().await? {
+//!         let mut lines = client.job_logs(repository, job.id).await?;
+//!         while let Some(line) = lines.try_next().await? {
+//!             println!("{}: {line}", job.name);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use futures::prelude::*;
+use http::Method;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use super::checks::CheckStatus;
+use super::{status_error, GitHubClient};
+use crate::{Error, Result};
+
+/// Status of a workflow run or job, the same as of a check run.
+pub type WorkflowRunStatus = CheckStatus;
+
+/// Conclusion of a completed workflow run or job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowRunConclusion {
+    ActionRequired,
+    Cancelled,
+    Failure,
+    Neutral,
+    Success,
+    Skipped,
+    Stale,
+    StartupFailure,
+    TimedOut,
+    /// Conclusion unknown to this version of the crate.
+    #[serde(other)]
+    Unknown,
+}
+
+/// GitHub Actions workflow run.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct WorkflowRun {
+    /// Run ID.
+    pub id: u64,
+
+    /// Workflow name.
+    pub name: Option<String>,
+
+    /// Branch or tag of the run.
+    pub head_branch: Option<String>,
+
+    /// Commit SHA of the run.
+    pub head_sha: String,
+
+    /// Event triggering the run, e.g. `workflow_dispatch`.
+    pub event: String,
+
+    /// Run number of the workflow.
+    pub run_number: u64,
+
+    /// Attempt of the run, starting at 1.
+    #[serde(default)]
+    pub run_attempt: Option<u64>,
+
+    /// Status of the run.
+    pub status: Option<WorkflowRunStatus>,
+
+    /// Conclusion of the run once completed.
+    pub conclusion: Option<WorkflowRunConclusion>,
+
+    /// URL of the run page.
+    pub html_url: String,
+
+    /// Creation time, e.g. `2022-12-01T08:30:00Z`.
+    pub created_at: String,
+
+    /// Time of the last update.
+    pub updated_at: String,
+}
+
+/// Job of a GitHub Actions workflow run.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct WorkflowJob {
+    /// Job ID.
+    pub id: u64,
+
+    /// ID of the run of the job.
+    pub run_id: u64,
+
+    /// Job name.
+    pub name: String,
+
+    /// Status of the job.
+    pub status: WorkflowRunStatus,
+
+    /// Conclusion of the job once completed.
+    pub conclusion: Option<WorkflowRunConclusion>,
+
+    /// URL of the job page.
+    pub html_url: Option<String>,
+
+    /// Start time, e.g. `2022-12-01T08:30:00Z`.
+    pub started_at: String,
+
+    /// Completion time.
+    pub completed_at: Option<String>,
+}
+
+/// Options for polling workflow runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollOptions {
+    /// Interval before the first poll.
+    pub min_interval: Duration,
+
+    /// Maximum interval between polls.
+    pub max_interval: Duration,
+
+    /// Factor to increase the interval by after each poll. The interval
+    /// stays at `min_interval` with a factor of 0 or 1.
+    pub backoff_factor: u32,
+
+    /// Maximum time to wait for the run of a dispatched workflow to appear.
+    pub dispatch_timeout: Duration,
+
+    /// Maximum time to wait for a run to complete, or `None` to wait
+    /// indefinitely.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_secs(2),
+            max_interval: Duration::from_secs(60),
+            backoff_factor: 2,
+            dispatch_timeout: Duration::from_secs(120),
+            timeout: None,
+        }
+    }
+}
+
+/// Exponential backoff between polls.
+struct Poll<'a> {
+    options: &'a PollOptions,
+    interval: Duration,
+    start: Instant,
+}
+
+impl<'a> Poll<'a> {
+    fn new(options: &'a PollOptions) -> Self {
+        Self {
+            options,
+            interval: options.min_interval,
+            start: Instant::now(),
+        }
+    }
+
+    /// Waits for the next poll, or returns `false` if it would be after
+    /// `timeout`.
+    async fn wait(&mut self, timeout: Option<Duration>) -> bool {
+        if let Some(timeout) = timeout {
+            if self.start.elapsed() + self.interval > timeout {
+                return false;
+            }
+        }
+        tokio::time::sleep(self.interval).await;
+        self.interval = (self.interval * self.options.backoff_factor)
+            .max(self.options.min_interval)
+            .min(self.options.max_interval);
+        true
+    }
+}
+
+#[derive(Serialize)]
+struct DispatchRequest<'a, I: ?Sized> {
+    #[serde(rename = "ref")]
+    git_ref: &'a str,
+    inputs: &'a I,
+}
+
+#[derive(Serialize)]
+struct RunsQuery<'a> {
+    event: &'static str,
+    branch: &'a str,
+    per_page: u32,
+}
+
+impl GitHubClient {
+    /// Triggers a `workflow_dispatch` event of `workflow`, its file name or
+    /// ID, in `repository` on the branch or tag `git_ref` with `inputs`.
+    #[instrument(skip(self, inputs))]
+    pub async fn dispatch_workflow<I: Serialize + ?Sized>(
+        &self,
+        repository: &str,
+        workflow: &str,
+        git_ref: &str,
+        inputs: &I,
+    ) -> Result<()> {
+        let body = serde_json::to_vec(&DispatchRequest { git_ref, inputs })?;
+        self.send(
+            Method::POST,
+            &format!("/repos/{repository}/actions/workflows/{workflow}/dispatches"),
+            Some(body.into()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Triggers a `workflow_dispatch` event like
+    /// [`dispatch_workflow`][Self::dispatch_workflow] and returns the
+    /// resulting run.
+    ///
+    /// The run is the first run of the workflow for `git_ref` created after
+    /// the dispatch, so concurrent dispatches of the same workflow and ref
+    /// may be mixed up. Fails with [`Error::WorkflowRunNotFound`] if no run
+    /// appears within [`PollOptions::dispatch_timeout`].
+    #[instrument(skip(self, inputs, options))]
+    pub async fn dispatch_workflow_run<I: Serialize + ?Sized>(
+        &self,
+        repository: &str,
+        workflow: &str,
+        git_ref: &str,
+        inputs: &I,
+        options: &PollOptions,
+    ) -> Result<WorkflowRun> {
+        let latest_run_id = self
+            .dispatched_runs(repository, workflow, git_ref)
+            .await?
+            .iter()
+            .map(|run| run.id)
+            .max();
+        self.dispatch_workflow(repository, workflow, git_ref, inputs)
+            .await?;
+
+        let mut poll = Poll::new(options);
+        loop {
+            if !poll.wait(Some(options.dispatch_timeout)).await {
+                return Err(Error::WorkflowRunNotFound(workflow.to_string()));
+            }
+            let run = self
+                .dispatched_runs(repository, workflow, git_ref)
+                .await?
+                .into_iter()
+                .filter(|run| Some(run.id) > latest_run_id)
+                .min_by_key(|run| run.id);
+            if let Some(run) = run {
+                debug!("Found workflow run {}", run.id);
+                return Ok(run);
+            }
+        }
+    }
+
+    /// Gets the latest `workflow_dispatch` runs of `workflow` for `git_ref`.
+    async fn dispatched_runs(
+        &self,
+        repository: &str,
+        workflow: &str,
+        git_ref: &str,
+    ) -> Result<Vec<WorkflowRun>> {
+        #[derive(Deserialize)]
+        struct Runs {
+            workflow_runs: Vec<WorkflowRun>,
+        }
+
+        let branch = git_ref
+            .strip_prefix("refs/heads/")
+            .or_else(|| git_ref.strip_prefix("refs/tags/"))
+            .unwrap_or(git_ref);
+        let query = serde_urlencoded::to_string(RunsQuery {
+            event: "workflow_dispatch",
+            branch,
+            per_page: 20,
+        })?;
+        let runs: Runs = self
+            .get(&format!(
+                "/repos/{repository}/actions/workflows/{workflow}/runs?{query}"
+            ))
+            .await?;
+        Ok(runs.workflow_runs)
+    }
+
+    /// Gets a workflow run of `repository`.
+    #[instrument(skip(self))]
+    pub async fn workflow_run(&self, repository: &str, run_id: u64) -> Result<WorkflowRun> {
+        self.get(&format!("/repos/{repository}/actions/runs/{run_id}"))
+            .await
+    }
+
+    /// Polls a workflow run until it completes and returns it with its
+    /// conclusion.
+    ///
+    /// Fails with [`Error::WorkflowRunTimeout`] if the run doesn't complete
+    /// within [`PollOptions::timeout`].
+    #[instrument(skip(self, options))]
+    pub async fn wait_for_workflow_run(
+        &self,
+        repository: &str,
+        run_id: u64,
+        options: &PollOptions,
+    ) -> Result<WorkflowRun> {
+        let mut poll = Poll::new(options);
+        loop {
+            let run = self.workflow_run(repository, run_id).await?;
+            if run.status == Some(WorkflowRunStatus::Completed) && run.conclusion.is_some() {
+                debug!("Workflow run {run_id} concluded with {:?}", run.conclusion);
+                return Ok(run);
+            }
+            debug!("Workflow run {run_id} is {:?}", run.status);
+            if !poll.wait(options.timeout).await {
+                return Err(Error::WorkflowRunTimeout(run_id));
+            }
+        }
+    }
+
+    /// Reads the jobs of the latest attempt of a workflow run.
+    pub fn workflow_run_jobs<'a>(
+        &'a self,
+        repository: &str,
+        run_id: u64,
+    ) -> impl Stream<Item = Result<WorkflowJob>> + Unpin + 'a {
+        self.paginate(&format!(
+            "/repos/{repository}/actions/runs/{run_id}/jobs?per_page=100"
+        ))
+    }
+
+    /// Reads the logs of a job line by line, without line endings.
+    ///
+    /// Logs are only available once the job completed.
+    #[instrument(skip(self))]
+    pub async fn job_logs(
+        &self,
+        repository: &str,
+        job_id: u64,
+    ) -> Result<impl Stream<Item = Result<String>> + Unpin> {
+        // Redirects to storage drop the authorization header
+        let url = self.url(&format!("/repos/{repository}/actions/jobs/{job_id}/logs"))?;
+        let response = self
+            .client
+            .request(Method::GET, url.clone())
+            .headers(self.headers_for(&url))
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.bytes().await?;
+            return Err(status_error(status, &body));
+        }
+
+        let state = (Some(response), Vec::new(), VecDeque::new());
+        Ok(Box::pin(stream::try_unfold(
+            state,
+            |(mut response, mut buf, mut lines): (
+                Option<reqwest::Response>,
+                Vec<u8>,
+                VecDeque<String>,
+            )| async move {
+                loop {
+                    if let Some(line) = lines.pop_front() {
+                        return Ok(Some((line, (response, buf, lines))));
+                    }
+                    let chunk = match response.as_mut() {
+                        Some(response) => response.chunk().await?,
+                        None => return Ok(None),
+                    };
+
+                    match chunk {
+                        Some(chunk) => buf.extend_from_slice(&chunk),
+                        None => {
+                            // The last line may not end with a newline
+                            response = None;
+                            if !buf.is_empty() {
+                                buf.push(b'\n');
+                            }
+                        }
+                    }
+                    while let Some(end) = buf.iter().position(|&b| b == b'\n') {
+                        let line: Vec<u8> = buf.drain(..=end).collect();
+                        let line = String::from_utf8_lossy(&line);
+                        lines.push_back(line.trim_end_matches(&['\r', '\n'][..]).to_string());
+                    }
+                }
+            },
+        )))
+    }
+}
@@ -0,0 +1,400 @@
+//! # GitHub Actions glob
+//!
+//! Globbing with the same patterns and matching rules as the runner, e.g. for
+//! cache paths and `hashFiles()`. This is an idiomatic Rust port of
+//! [@actions/glob](https://github.com/actions/toolkit/tree/main/packages/glob).
+//!
+//! Patterns are separated by newlines. Lines starting with `#` are comments
+//! and patterns starting with `!` exclude matches. Patterns are relative to
+//! the current directory, or the home directory if they start with `~`. A
+//! pattern matching a directory also matches its descendants:
+//!
+//! ```rust,no_run
+//! use futures::prelude::*;
+//! use gha_toolkit::glob::{GlobOptions, Globber};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let patterns = "
+//!     # Build outputs
+//!     target/**/*.rlib
+//!     !target/debug
+//! ";
+//! let globber = Globber::create(patterns, &GlobOptions::default())?;
+//!
+//! let mut files = globber.glob_stream();
+//! while let Some(file) = files.try_next().await? {
+//!     println!("{}", file.display());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`hash_files`] computes the same hash as the `hashFiles()` expression
+//! function, e.g. for cache keys:
+//!
+//! ```rust,no_run
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let hash = gha_toolkit::glob::hash_files("**/Cargo.lock").await?;
+//! let key = format!("cargo-{hash}");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use futures::prelude::*;
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+use tracing::{debug, instrument};
+
+use crate::Result;
+
+use self::pattern::{Pattern, MATCH_DIRECTORY, MATCH_FILE, MATCH_NONE};
+
+mod pattern;
+
+/// Options for [`Globber::create`].
+#[derive(Debug, Clone)]
+pub struct GlobOptions {
+    /// Whether to follow symbolic links. Defaults to `true`.
+    pub follow_symbolic_links: bool,
+
+    /// Whether a pattern matching a directory also matches its descendants.
+    /// Defaults to `true`.
+    pub implicit_descendants: bool,
+
+    /// Whether directories are matched, not only files. Defaults to `true`.
+    pub match_directories: bool,
+
+    /// Whether to skip broken symbolic links instead of failing. Defaults to
+    /// `true`.
+    pub omit_broken_symbolic_links: bool,
+
+    /// Whether to skip files and directories starting with `.`. Defaults to
+    /// `false`.
+    pub exclude_hidden_files: bool,
+}
+
+impl Default for GlobOptions {
+    fn default() -> Self {
+        Self {
+            follow_symbolic_links: true,
+            implicit_descendants: true,
+            match_directories: true,
+            omit_broken_symbolic_links: true,
+            exclude_hidden_files: false,
+        }
+    }
+}
+
+/// Finds files and directories matching glob patterns.
+#[derive(Debug, Clone)]
+pub struct Globber {
+    options: GlobOptions,
+    patterns: Vec<Pattern>,
+    search_paths: Vec<PathBuf>,
+}
+
+struct SearchState {
+    path: PathBuf,
+    level: usize,
+}
+
+impl Globber {
+    /// Creates a [`Globber`] for newline separated `patterns`.
+    ///
+    /// Fails if a pattern is invalid, e.g. has `..` segments.
+    pub fn create(patterns: &str, options: &GlobOptions) -> Result<Self> {
+        Self::create_in(patterns, options, None)
+    }
+
+    /// Creates a [`Globber`] with relative patterns resolved against `cwd`
+    /// instead of the current directory.
+    fn create_in(patterns: &str, options: &GlobOptions, cwd: Option<&Path>) -> Result<Self> {
+        let patterns = patterns
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|pattern| Pattern::new(pattern, cwd))
+            .collect::<Result<Vec<_>>>()?;
+        let search_paths = search_paths(&patterns);
+
+        Ok(Self {
+            options: options.clone(),
+            patterns,
+            search_paths,
+        })
+    }
+
+    /// Gets the directories searched for matches.
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    /// Finds all matching files and directories.
+    pub async fn glob(&self) -> Result<Vec<PathBuf>> {
+        self.glob_stream().try_collect().await
+    }
+
+    /// Finds matching files and directories as they're found, in the order
+    /// of a depth first search with sorted directory entries.
+    pub fn glob_stream(&self) -> impl Stream<Item = Result<PathBuf>> + Unpin + '_ {
+        let mut patterns = Vec::new();
+        for pattern in &self.patterns {
+            patterns.push(pattern.clone());
+            if self.options.implicit_descendants
+                && (pattern.trailing_separator || !pattern.ends_with_globstar())
+            {
+                patterns.push(pattern.descendants());
+            }
+        }
+
+        let search_paths = search_paths(&patterns);
+        let state = GlobState {
+            options: &self.options,
+            patterns,
+            search_paths: Some(search_paths),
+            stack: Vec::new(),
+            traversal_chain: Vec::new(),
+        };
+
+        Box::pin(stream::try_unfold(state, |mut state| async move {
+            Ok(state.next().await?.map(|path| (path, state)))
+        }))
+    }
+}
+
+/// Computes the hash of the files matching `patterns` like the `hashFiles()`
+/// expression function.
+///
+/// Relative patterns are resolved against `GITHUB_WORKSPACE`, or the current
+/// directory if it isn't set, and symbolic links aren't followed, like the
+/// runner does by default. The SHA-256 digests of the matched files are
+/// hashed in the order they're found. Directories and files outside of the
+/// workspace are skipped. Returns an empty string if no files match.
+#[instrument]
+pub async fn hash_files(patterns: &str) -> Result<String> {
+    let workspace = match env::var_os("GITHUB_WORKSPACE") {
+        Some(workspace) => PathBuf::from(workspace),
+        None => env::current_dir()?,
+    };
+
+    let options = GlobOptions {
+        follow_symbolic_links: false,
+        ..Default::default()
+    };
+    let globber = Globber::create_in(patterns, &options, Some(&workspace))?;
+    let mut files = globber.glob_stream();
+
+    let mut hasher = Sha256::new();
+    let mut has_match = false;
+    let mut buf = vec![0; 64 << 10];
+    while let Some(file) = files.try_next().await? {
+        if !file.starts_with(&workspace) || file == workspace {
+            debug!(
+                "Ignore '{}' since it is not under GITHUB_WORKSPACE.",
+                file.display()
+            );
+            continue;
+        }
+        if fs::metadata(&file).await?.is_dir() {
+            debug!("Skip directory '{}'.", file.display());
+            continue;
+        }
+
+        let mut file_hasher = Sha256::new();
+        let mut reader = fs::File::open(&file).await?;
+        loop {
+            let len = reader.read(&mut buf).await?;
+            if len == 0 {
+                break;
+            }
+            file_hasher.update(&buf[..len]);
+        }
+        hasher.update(file_hasher.finalize());
+        has_match = true;
+    }
+
+    if has_match {
+        Ok(hex::encode(&hasher.finalize()[..]))
+    } else {
+        debug!("No matches found for glob");
+        Ok(String::new())
+    }
+}
+
+struct GlobState<'a> {
+    options: &'a GlobOptions,
+    patterns: Vec<Pattern>,
+    search_paths: Option<Vec<PathBuf>>,
+    stack: Vec<SearchState>,
+    /// Real paths of the directories being traversed, to detect cycles of
+    /// symbolic links.
+    traversal_chain: Vec<PathBuf>,
+}
+
+impl GlobState<'_> {
+    async fn next(&mut self) -> Result<Option<PathBuf>> {
+        if let Some(search_paths) = self.search_paths.take() {
+            for path in search_paths.into_iter().rev() {
+                debug!("Search path '{}'", path.display());
+                match fs::symlink_metadata(&path).await {
+                    Ok(_) => self.stack.push(SearchState { path, level: 1 }),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+
+        while let Some(item) = self.stack.pop() {
+            let item_path = item.path.to_string_lossy();
+            let match_kind = self.match_kind(&item_path);
+            let partial_match = match_kind != MATCH_NONE || self.partial_match(&item_path);
+            if !partial_match {
+                continue;
+            }
+
+            let metadata = match self.stat(&item).await? {
+                Some(metadata) => metadata,
+                None => continue,
+            };
+            if self.options.exclude_hidden_files
+                && item
+                    .path
+                    .file_name()
+                    .map_or(false, |name| name.to_string_lossy().starts_with('.'))
+            {
+                continue;
+            }
+
+            if metadata.is_dir() {
+                let yield_dir = match_kind & MATCH_DIRECTORY != 0 && self.options.match_directories;
+
+                let mut children = Vec::new();
+                let mut entries = fs::read_dir(&item.path).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    children.push(entry.file_name());
+                }
+                children.sort();
+                self.stack
+                    .extend(children.into_iter().rev().map(|name| SearchState {
+                        path: item.path.join(name),
+                        level: item.level + 1,
+                    }));
+
+                if yield_dir {
+                    return Ok(Some(item.path));
+                }
+            } else if match_kind & MATCH_FILE != 0 {
+                return Ok(Some(item.path));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn match_kind(&self, item_path: &str) -> u8 {
+        let mut result = MATCH_NONE;
+        for pattern in &self.patterns {
+            if pattern.negate {
+                result &= !pattern.matches(item_path);
+            } else {
+                result |= pattern.matches(item_path);
+            }
+        }
+        result
+    }
+
+    fn partial_match(&self, item_path: &str) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| !pattern.negate && pattern.partial_match(item_path))
+    }
+
+    /// Gets the metadata of an item, or `None` if it's a broken symbolic
+    /// link or a cycle of symbolic links which is skipped.
+    async fn stat(&mut self, item: &SearchState) -> Result<Option<std::fs::Metadata>> {
+        if !self.options.follow_symbolic_links {
+            return Ok(Some(fs::symlink_metadata(&item.path).await?));
+        }
+
+        let metadata = match fs::metadata(&item.path).await {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                if self.options.omit_broken_symbolic_links {
+                    debug!("Broken symlink '{}'", item.path.display());
+                    return Ok(None);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "No information found for the path '{}'. This may indicate a broken symbolic link.",
+                        item.path.display()
+                    ),
+                )
+                .into());
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if metadata.is_dir() {
+            let real_path = fs::canonicalize(&item.path).await?;
+            self.traversal_chain.truncate(item.level - 1);
+            if self.traversal_chain.contains(&real_path) {
+                debug!(
+                    "Symlink cycle detected for path '{}' and realpath '{}'",
+                    item.path.display(),
+                    real_path.display()
+                );
+                return Ok(None);
+            }
+            self.traversal_chain.push(real_path);
+        }
+
+        Ok(Some(metadata))
+    }
+}
+
+/// Gets the search paths of patterns, skipping negated patterns and search
+/// paths within others.
+fn search_paths(patterns: &[Pattern]) -> Vec<PathBuf> {
+    let key = |path: &Path| {
+        let path = path.to_string_lossy();
+        if cfg!(windows) {
+            path.to_uppercase()
+        } else {
+            path.into_owned()
+        }
+    };
+
+    let patterns: Vec<&Pattern> = patterns.iter().filter(|pattern| !pattern.negate).collect();
+    let mut included: HashMap<String, bool> = patterns
+        .iter()
+        .map(|pattern| (key(&pattern.search_path), false))
+        .collect();
+
+    let mut result = Vec::new();
+    for pattern in patterns {
+        let path_key = key(&pattern.search_path);
+        if included.get(&path_key) == Some(&true) {
+            continue;
+        }
+
+        let has_ancestor = pattern
+            .search_path
+            .ancestors()
+            .skip(1)
+            .any(|ancestor| included.contains_key(&key(ancestor)));
+        if !has_ancestor {
+            result.push(pattern.search_path.clone());
+            included.insert(path_key, true);
+        }
+    }
+    result
+}
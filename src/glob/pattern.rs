@@ -0,0 +1,586 @@
+//! Glob patterns with the syntax and matching rules of `@actions/glob`, which
+//! uses [minimatch](https://github.com/isaacs/minimatch) with the options
+//! `dot`, `nobrace`, `nocomment`, `noext` and `nonegate`.
+//!
+//! Patterns are made absolute relative to the current directory, or the home
+//! directory for a leading `~`. A segment `**` matches any number of path
+//! segments, `*` and `?` match within a segment and `[...]` is a character
+//! class. On Windows matching is case insensitive and backslashes are
+//! separators instead of escapes.
+
+use std::env;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+
+use crate::{Error, Result};
+
+const IS_WINDOWS: bool = cfg!(windows);
+const SEPARATOR: char = MAIN_SEPARATOR;
+
+/// Kinds of items matched by a pattern.
+pub(crate) type MatchKind = u8;
+
+pub(crate) const MATCH_NONE: MatchKind = 0;
+pub(crate) const MATCH_DIRECTORY: MatchKind = 1;
+pub(crate) const MATCH_FILE: MatchKind = 2;
+pub(crate) const MATCH_ALL: MatchKind = MATCH_DIRECTORY | MATCH_FILE;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Pattern {
+    /// Whether matching items are excluded.
+    pub negate: bool,
+
+    /// Whether the pattern was added to match the descendants of another.
+    pub is_implicit: bool,
+
+    /// Whether the pattern ends with a separator and only matches directories.
+    pub trailing_separator: bool,
+
+    /// Literal path preceding the first segment with a glob.
+    pub search_path: PathBuf,
+
+    /// Absolute pattern without a trailing separator.
+    pattern: String,
+
+    /// Whether the last segment is `**`.
+    globstar: bool,
+
+    matcher: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Glob(Vec<Token>),
+    GlobStar,
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Char(char),
+    /// `?`
+    Any,
+    /// `*`
+    Star,
+    /// `[...]`
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Pattern {
+    /// Parses a pattern, made absolute relative to `cwd`, or the current
+    /// directory if it's `None`.
+    pub fn new(pattern: &str, cwd: Option<&Path>) -> Result<Self> {
+        let mut pattern = pattern.trim();
+        let mut negate = false;
+        while let Some(rest) = pattern.strip_prefix('!') {
+            negate = !negate;
+            pattern = rest.trim();
+        }
+
+        Ok(Self::from_absolute(
+            negate,
+            false,
+            fixup_pattern(pattern, cwd)?,
+        ))
+    }
+
+    /// Creates the pattern matching the descendants of this one.
+    pub fn descendants(&self) -> Self {
+        let pattern = if self.pattern.ends_with(SEPARATOR) {
+            format!("{}**", self.pattern)
+        } else {
+            format!("{}{SEPARATOR}**", self.pattern)
+        };
+        Self::from_absolute(self.negate, true, pattern)
+    }
+
+    /// Returns `true` if the last segment is `**`.
+    pub fn ends_with_globstar(&self) -> bool {
+        self.globstar
+    }
+
+    fn from_absolute(negate: bool, is_implicit: bool, pattern: String) -> Self {
+        let trailing_separator = pattern.ends_with(SEPARATOR);
+        let pattern = safe_trim_trailing_separator(&pattern).to_string();
+
+        let (root, rest) = split_root(&pattern);
+        let segments: Vec<&str> = rest.split(SEPARATOR).filter(|s| !s.is_empty()).collect();
+
+        let mut search_path = PathBuf::from(root);
+        for segment in &segments {
+            let literal = get_literal(segment);
+            if literal.is_empty() {
+                break;
+            }
+            search_path.push(literal);
+        }
+
+        let matcher = split_path(&pattern)
+            .into_iter()
+            .map(Segment::compile)
+            .collect();
+
+        Self {
+            negate,
+            is_implicit,
+            trailing_separator,
+            search_path,
+            globstar: segments.last() == Some(&"**"),
+            pattern,
+            matcher,
+        }
+    }
+
+    /// Matches an absolute item path.
+    pub fn matches(&self, item_path: &str) -> MatchKind {
+        let mut item_path = normalize_separators(item_path);
+        if self.globstar {
+            // Otherwise the directory preceding the globstar isn't matched
+            if !item_path.ends_with(SEPARATOR) && !self.is_implicit {
+                item_path.push(SEPARATOR);
+            }
+        } else {
+            item_path = safe_trim_trailing_separator(&item_path).to_string();
+        }
+
+        if match_one(&split_path(&item_path), &self.matcher, false) {
+            if self.trailing_separator {
+                MATCH_DIRECTORY
+            } else {
+                MATCH_ALL
+            }
+        } else {
+            MATCH_NONE
+        }
+    }
+
+    /// Returns `true` if descendants of an absolute item path may match.
+    pub fn partial_match(&self, item_path: &str) -> bool {
+        let item_path = normalize_separators(item_path);
+        let item_path = safe_trim_trailing_separator(&item_path);
+
+        let (root, rest) = split_root(item_path);
+        if rest.is_empty() {
+            // The root only matches patterns with the same root
+            let (pattern_root, _) = split_root(&self.pattern);
+            return eq(root, pattern_root);
+        }
+        match_one(&split_path(item_path), &self.matcher, true)
+    }
+}
+
+impl Segment {
+    fn compile(segment: &str) -> Self {
+        if segment == "**" {
+            return Self::GlobStar;
+        }
+
+        let chars: Vec<char> = segment.chars().collect();
+        let mut tokens = Vec::new();
+        let mut magic = false;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+            match c {
+                '\\' if !IS_WINDOWS && i < chars.len() => {
+                    tokens.push(Token::Char(chars[i]));
+                    i += 1;
+                }
+                '*' => {
+                    magic = true;
+                    if !matches!(tokens.last(), Some(Token::Star)) {
+                        tokens.push(Token::Star);
+                    }
+                }
+                '?' => {
+                    magic = true;
+                    tokens.push(Token::Any);
+                }
+                '[' => match parse_class(&chars[i..]) {
+                    Some((token, len)) => {
+                        magic = true;
+                        tokens.push(token);
+                        i += len;
+                    }
+                    None => tokens.push(Token::Char('[')),
+                },
+                c => tokens.push(Token::Char(c)),
+            }
+        }
+
+        if magic {
+            Self::Glob(tokens)
+        } else {
+            Self::Literal(
+                tokens
+                    .into_iter()
+                    .map(|token| match token {
+                        Token::Char(c) => c,
+                        _ => unreachable!(),
+                    })
+                    .collect(),
+            )
+        }
+    }
+
+    fn matches(&self, segment: &str) -> bool {
+        match self {
+            Self::Literal(literal) => eq(literal, segment),
+            Self::Glob(tokens) => {
+                let chars: Vec<char> = segment.chars().collect();
+                // `*` never matches the `.` and `..` segments
+                !matches!(segment, "." | "..") && match_tokens(tokens, &chars)
+            }
+            Self::GlobStar => true,
+        }
+    }
+}
+
+/// Parses a character class after its `[`, returning the token and the
+/// number of characters consumed, or `None` if it isn't closed.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut members = Vec::new();
+    let mut first = true;
+    loop {
+        let mut c = *chars.get(i)?;
+        i += 1;
+        if c == ']' && !first {
+            break;
+        }
+        first = false;
+        if c == '\\' && !IS_WINDOWS {
+            c = *chars.get(i)?;
+            i += 1;
+        }
+        members.push(c);
+    }
+
+    let mut ranges = Vec::new();
+    let mut j = 0;
+    while j < members.len() {
+        if j + 2 < members.len() && members[j + 1] == '-' {
+            ranges.push((members[j], members[j + 2]));
+            j += 3;
+        } else {
+            ranges.push((members[j], members[j]));
+            j += 1;
+        }
+    }
+
+    Some((Token::Class { negated, ranges }, i))
+}
+
+fn match_tokens(tokens: &[Token], chars: &[char]) -> bool {
+    match tokens.split_first() {
+        None => chars.is_empty(),
+        Some((Token::Star, rest)) => (0..=chars.len()).any(|i| match_tokens(rest, &chars[i..])),
+        Some((token, rest)) => match chars.split_first() {
+            Some((&c, chars)) => {
+                let hit = match token {
+                    Token::Char(expected) => eq_char(*expected, c),
+                    Token::Any => true,
+                    Token::Class { negated, ranges } => {
+                        let c = fold_case(c);
+                        let found = ranges
+                            .iter()
+                            .any(|&(start, end)| fold_case(start) <= c && c <= fold_case(end));
+                        found != *negated
+                    }
+                    Token::Star => unreachable!(),
+                };
+                hit && match_tokens(rest, chars)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Matches path segments against pattern segments like `Minimatch.matchOne`.
+///
+/// In `partial` mode, returns `true` if descendants of the path may match.
+fn match_one(file: &[&str], pattern: &[Segment], partial: bool) -> bool {
+    let (mut fi, mut pi) = (0, 0);
+    while fi < file.len() && pi < pattern.len() {
+        if let Segment::GlobStar = pattern[pi] {
+            // A trailing globstar swallows the rest
+            if pi + 1 == pattern.len() {
+                return file[fi..].iter().all(|f| !matches!(*f, "." | ".."));
+            }
+
+            let mut fr = fi;
+            while fr < file.len() {
+                if match_one(&file[fr..], &pattern[pi + 1..], partial) {
+                    return true;
+                }
+                if matches!(file[fr], "." | "..") {
+                    break;
+                }
+                fr += 1;
+            }
+            return partial && fr == file.len();
+        }
+
+        if !pattern[pi].matches(file[fi]) {
+            return false;
+        }
+        fi += 1;
+        pi += 1;
+    }
+
+    if fi == file.len() && pi == pattern.len() {
+        true
+    } else if fi == file.len() {
+        partial
+    } else {
+        // A trailing separator matches
+        fi == file.len() - 1 && file[fi].is_empty()
+    }
+}
+
+/// Splits a path at separators like minimatch, keeping an empty segment for
+/// a leading or trailing separator.
+fn split_path(path: &str) -> Vec<&str> {
+    let is_separator = |c: char| c == '/' || (IS_WINDOWS && c == '\\');
+    let segments: Vec<&str> = path.split(is_separator).collect();
+    let last = segments.len() - 1;
+    segments
+        .into_iter()
+        .enumerate()
+        .filter(|(i, segment)| !segment.is_empty() || *i == 0 || *i == last)
+        .map(|(_, segment)| segment)
+        .collect()
+}
+
+/// Makes a pattern absolute and normalizes its separators.
+fn fixup_pattern(pattern: &str, cwd: Option<&Path>) -> Result<String> {
+    let invalid = |reason| Error::InvalidGlobPattern {
+        pattern: pattern.to_string(),
+        reason,
+    };
+    if pattern.is_empty() {
+        return Err(invalid("Pattern cannot be empty."));
+    }
+
+    let normalized = normalize_separators(pattern);
+    let (root, rest) = split_root(&normalized);
+    let offset = usize::from(!root.is_empty());
+    let relative_pathing = rest
+        .split(SEPARATOR)
+        .filter(|segment| !segment.is_empty())
+        .enumerate()
+        .any(|(i, segment)| {
+            let literal = get_literal(segment);
+            (literal == "." && i + offset != 0) || literal == ".."
+        });
+    if relative_pathing {
+        return Err(invalid(
+            "Relative pathing '.' and '..' is only allowed at the beginning of the pattern.",
+        ));
+    }
+
+    let current_dir = || match cwd {
+        Some(cwd) => Ok(cwd.to_path_buf()),
+        None => env::current_dir(),
+    };
+    let pattern = if normalized == "." || normalized.starts_with(&format!(".{SEPARATOR}")) {
+        let cwd = current_dir()?;
+        format!(
+            "{}{}",
+            glob_escape(&cwd.to_string_lossy()),
+            &normalized[1..]
+        )
+    } else if normalized == "~" || normalized.starts_with(&format!("~{SEPARATOR}")) {
+        let home = home_dir().ok_or_else(|| invalid("Unable to determine HOME directory."))?;
+        format!(
+            "{}{}",
+            glob_escape(&home.to_string_lossy()),
+            &normalized[1..]
+        )
+    } else if has_absolute_root(&normalized) {
+        normalized
+    } else {
+        let cwd = glob_escape(&current_dir()?.to_string_lossy());
+        if IS_WINDOWS && normalized.starts_with(SEPARATOR) {
+            // Rooted on the drive of the current directory
+            format!("{}{normalized}", &cwd[..2.min(cwd.len())])
+        } else if cwd.ends_with(SEPARATOR) {
+            format!("{cwd}{normalized}")
+        } else {
+            format!("{cwd}{SEPARATOR}{normalized}")
+        }
+    };
+
+    Ok(normalize_separators(&pattern))
+}
+
+/// Gets the literal value of a pattern segment, or an empty string if it
+/// contains globs.
+fn get_literal(segment: &str) -> String {
+    let chars: Vec<char> = segment.chars().collect();
+    let mut literal = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && !IS_WINDOWS && i + 1 < chars.len() {
+            literal.push(chars[i + 1]);
+            i += 2;
+            continue;
+        } else if c == '*' || c == '?' {
+            return String::new();
+        } else if c == '[' && i + 1 < chars.len() {
+            let mut set = String::new();
+            let mut closed = None;
+            let mut j = i + 1;
+            while j < chars.len() {
+                let c = chars[j];
+                if c == '\\' && !IS_WINDOWS && j + 1 < chars.len() {
+                    j += 1;
+                    set.push(chars[j]);
+                } else if c == ']' {
+                    closed = Some(j);
+                    break;
+                } else {
+                    set.push(c);
+                }
+                j += 1;
+            }
+
+            if let Some(closed) = closed {
+                if set.chars().count() > 1 {
+                    return String::new();
+                }
+                if !set.is_empty() {
+                    literal.push_str(&set);
+                    i = closed + 1;
+                    continue;
+                }
+            }
+        }
+        literal.push(c);
+        i += 1;
+    }
+    literal
+}
+
+/// Escapes glob characters in a literal path.
+fn glob_escape(path: &str) -> String {
+    let chars: Vec<char> = path.chars().collect();
+    let mut escaped = String::with_capacity(path.len());
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            '\\' if !IS_WINDOWS => escaped.push_str("\\\\"),
+            // Only brackets which would start a character class
+            '[' if chars[i + 1..]
+                .iter()
+                .take_while(|&&c| c != '/')
+                .skip(1)
+                .any(|&c| c == ']') =>
+            {
+                escaped.push_str("[[]")
+            }
+            '?' => escaped.push_str("[?]"),
+            '*' => escaped.push_str("[*]"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn home_dir() -> Option<PathBuf> {
+    let home = if IS_WINDOWS {
+        env::var_os("USERPROFILE")
+    } else {
+        env::var_os("HOME")
+    };
+    home.filter(|home| !home.is_empty()).map(PathBuf::from)
+}
+
+/// Splits the root of a path, e.g. `/` or `C:\`, from the rest.
+fn split_root(path: &str) -> (&str, &str) {
+    let len = if IS_WINDOWS {
+        let bytes = path.as_bytes();
+        if let Some(unc) = path.strip_prefix("\\\\") {
+            // UNC root `\\server\share\`
+            unc.match_indices('\\')
+                .nth(1)
+                .map_or(path.len(), |(i, _)| i + 3)
+        } else if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+            if bytes.get(2) == Some(&b'\\') {
+                3
+            } else {
+                2
+            }
+        } else {
+            usize::from(path.starts_with('\\'))
+        }
+    } else {
+        usize::from(path.starts_with('/'))
+    };
+    path.split_at(len)
+}
+
+fn has_absolute_root(path: &str) -> bool {
+    if IS_WINDOWS {
+        let (root, _) = split_root(path);
+        root.starts_with("\\\\") || (root.len() == 3 && root.ends_with(":\\"))
+    } else {
+        path.starts_with('/')
+    }
+}
+
+/// Collapses repeated separators, and converts `/` to `\` on Windows.
+fn normalize_separators(path: &str) -> String {
+    let mut normalized = String::with_capacity(path.len());
+    let mut previous_separator = false;
+    for (i, c) in path.chars().enumerate() {
+        let is_separator = c == SEPARATOR || (IS_WINDOWS && c == '/');
+        // Keep the leading `\\` of UNC paths
+        if is_separator && previous_separator && !(IS_WINDOWS && i == 1) {
+            continue;
+        }
+        normalized.push(if is_separator { SEPARATOR } else { c });
+        previous_separator = is_separator;
+    }
+    normalized
+}
+
+/// Removes a trailing separator unless the path is a root.
+fn safe_trim_trailing_separator(path: &str) -> &str {
+    if !path.ends_with(SEPARATOR) {
+        return path;
+    }
+    let (root, rest) = split_root(path);
+    if rest.is_empty() {
+        return root;
+    }
+    path.trim_end_matches(SEPARATOR)
+}
+
+fn fold_case(c: char) -> char {
+    if IS_WINDOWS {
+        c.to_lowercase().next().unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+fn eq_char(a: char, b: char) -> bool {
+    fold_case(a) == fold_case(b)
+}
+
+fn eq(a: &str, b: &str) -> bool {
+    if IS_WINDOWS {
+        a.to_lowercase() == b.to_lowercase()
+    } else {
+        a == b
+    }
+}
@@ -0,0 +1,402 @@
+//! # GitHub Actions io
+//!
+//! File system operations with the semantics of the shell commands actions
+//! commonly use, like `which`, `cp -r`, `mv`, `rm -rf` and `mkdir -p`. This is
+//! an idiomatic Rust port of
+//! [@actions/io](https://github.com/actions/toolkit/tree/main/packages/io).
+//!
+//! ```rust,no_run
+//! use gha_toolkit::io::{self, CopyOptions};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let git = io::which("git", true).await?;
+//! println!("Found git at {git:?}");
+//!
+//! io::mkdir_p("dist").await?;
+//! let options = CopyOptions {
+//!     recursive: true,
+//!     ..Default::default()
+//! };
+//! io::cp("target/release", "dist", &options).await?;
+//! io::rm_rf("target").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::env;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use futures::future::{BoxFuture, FutureExt};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+use tracing::{debug, instrument};
+
+use crate::{Error, Result};
+
+/// Options for [`cp`].
+#[derive(Debug, Clone)]
+pub struct CopyOptions {
+    /// Whether to copy directories recursively. Defaults to `false`.
+    pub recursive: bool,
+
+    /// Whether to overwrite existing files. Defaults to `true`.
+    pub force: bool,
+
+    /// Whether to copy the source directory into an existing destination
+    /// directory, instead of only its contents. Defaults to `true`.
+    pub copy_source_directory: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            force: true,
+            copy_source_directory: true,
+        }
+    }
+}
+
+/// Options for [`mv`].
+#[derive(Debug, Clone)]
+pub struct MoveOptions {
+    /// Whether to overwrite an existing destination. Defaults to `true`.
+    pub force: bool,
+}
+
+impl Default for MoveOptions {
+    fn default() -> Self {
+        Self { force: true }
+    }
+}
+
+/// Copies a file or directory like `cp`.
+///
+/// If `dest` is an existing directory, `source` is copied into it. Symbolic
+/// links are copied as links.
+#[instrument(skip(source, dest), fields(source = %source.as_ref().display(), dest = %dest.as_ref().display()))]
+pub async fn cp<S: AsRef<Path>, D: AsRef<Path>>(
+    source: S,
+    dest: D,
+    options: &CopyOptions,
+) -> Result<()> {
+    let (source, dest) = (source.as_ref(), dest.as_ref());
+
+    let dest_is_dir = matches!(fs::metadata(dest).await, Ok(metadata) if metadata.is_dir());
+    let new_dest = match source.file_name() {
+        Some(name) if dest_is_dir && options.copy_source_directory => dest.join(name),
+        _ => dest.to_path_buf(),
+    };
+
+    let metadata = match fs::metadata(source).await {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(Error::IO(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no such file or directory: {}", source.display()),
+            )));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    if metadata.is_dir() {
+        if !options.recursive {
+            return Err(Error::IO(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Failed to copy. {} is a directory, but tried to copy without recursive flag.",
+                    source.display()
+                ),
+            )));
+        }
+        copy_dir(source.to_path_buf(), new_dest, options.force).await
+    } else {
+        if same_file(source, &new_dest).await {
+            return Err(Error::IO(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "'{}' and '{}' are the same file",
+                    new_dest.display(),
+                    source.display()
+                ),
+            )));
+        }
+        copy_file(source, &new_dest, options.force).await
+    }
+}
+
+/// Moves a file or directory like `mv`.
+///
+/// If `dest` is an existing directory, `source` is moved into it. Fails if
+/// the destination exists, unless `force` is set.
+#[instrument(skip(source, dest), fields(source = %source.as_ref().display(), dest = %dest.as_ref().display()))]
+pub async fn mv<S: AsRef<Path>, D: AsRef<Path>>(
+    source: S,
+    dest: D,
+    options: &MoveOptions,
+) -> Result<()> {
+    let (source, mut dest) = (source.as_ref(), dest.as_ref().to_path_buf());
+
+    if matches!(fs::metadata(&dest).await, Ok(metadata) if metadata.is_dir()) {
+        if let Some(name) = source.file_name() {
+            dest.push(name);
+        }
+    }
+    if fs::symlink_metadata(&dest).await.is_ok() {
+        if !options.force {
+            return Err(Error::IO(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "Destination already exists",
+            )));
+        }
+        rm_rf(&dest).await?;
+    }
+
+    if let Some(parent) = dest.parent() {
+        mkdir_p(parent).await?;
+    }
+    fs::rename(source, &dest).await?;
+    Ok(())
+}
+
+/// Removes a file or directory recursively like `rm -rf`.
+///
+/// Succeeds if the path doesn't exist. Read-only files and directories are
+/// removed too. Symbolic links are removed, not their targets.
+#[instrument(skip(path), fields(path = %path.as_ref().display()))]
+pub async fn rm_rf<P: AsRef<Path>>(path: P) -> Result<()> {
+    remove(path.as_ref().to_path_buf()).await
+}
+
+/// Creates a directory and its parents like `mkdir -p`.
+#[instrument(skip(path), fields(path = %path.as_ref().display()))]
+pub async fn mkdir_p<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    if path.as_os_str().is_empty() {
+        return Err(Error::IO(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "a path argument must be provided",
+        )));
+    }
+    fs::create_dir_all(path).await?;
+    Ok(())
+}
+
+/// Finds the path of an executable like `which`, searching `PATH` and on
+/// Windows trying the extensions in `PATHEXT`.
+///
+/// Returns `None` if the executable isn't found, unless `check` is set in
+/// which case it fails.
+#[instrument]
+pub async fn which(tool: &str, check: bool) -> Result<Option<PathBuf>> {
+    if tool.is_empty() {
+        return Err(Error::IO(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "parameter 'tool' is required",
+        )));
+    }
+
+    let path = find_in_path(tool).await.into_iter().next();
+    if path.is_none() && check {
+        return Err(Error::IO(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "Unable to locate executable file: {tool}. Please verify either the file path \
+                 exists or the file can be found within a directory specified by the PATH \
+                 environment variable. Also check the file mode to verify the file is \
+                 executable."
+            ),
+        )));
+    }
+    Ok(path)
+}
+
+/// Finds all executables named `tool` in `PATH`, in order.
+///
+/// If `tool` is a path, only that path is checked.
+pub async fn find_in_path(tool: &str) -> Vec<PathBuf> {
+    let extensions = executable_extensions();
+    let tool_path = Path::new(tool);
+
+    let dirs: Vec<PathBuf> = if tool_path.is_absolute() || tool.contains(['/', '\\']) {
+        vec![PathBuf::new()]
+    } else {
+        env::var_os("PATH")
+            .map(|path| {
+                env::split_paths(&path)
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut matches = Vec::new();
+    for dir in dirs {
+        let path = dir.join(tool_path);
+        if let Some(path) = try_executable(&path, &extensions).await {
+            debug!("Found {tool} at {}", path.display());
+            matches.push(path);
+        }
+    }
+    matches
+}
+
+/// Gets the extensions of executables from `PATHEXT` on Windows.
+fn executable_extensions() -> Vec<String> {
+    if cfg!(windows) {
+        env::var("PATHEXT")
+            .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string())
+            .split(';')
+            .filter(|extension| !extension.is_empty())
+            .map(str::to_string)
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Gets `path` if it's an executable file, trying each of `extensions`.
+async fn try_executable(path: &Path, extensions: &[String]) -> Option<PathBuf> {
+    let has_extension = path.extension().map_or(false, |extension| {
+        let extension = format!(".{}", extension.to_string_lossy());
+        extensions
+            .iter()
+            .any(|candidate| candidate.eq_ignore_ascii_case(&extension))
+    });
+    if (extensions.is_empty() || has_extension) && is_executable(path).await {
+        return Some(path.to_path_buf());
+    }
+
+    for extension in extensions {
+        let mut candidate = OsString::from(path.as_os_str());
+        candidate.push(extension);
+        let candidate = PathBuf::from(candidate);
+        if is_executable(&candidate).await {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+#[cfg(unix)]
+async fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    matches!(fs::metadata(path).await, Ok(metadata) if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+async fn is_executable(path: &Path) -> bool {
+    matches!(fs::metadata(path).await, Ok(metadata) if metadata.is_file())
+}
+
+async fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a).await, fs::canonicalize(b).await) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn copy_dir(source: PathBuf, dest: PathBuf, force: bool) -> BoxFuture<'static, Result<()>> {
+    async move {
+        fs::create_dir_all(&dest).await?;
+
+        let mut entries = fs::read_dir(&source).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let source = entry.path();
+            let dest = dest.join(entry.file_name());
+            if entry.file_type().await?.is_dir() {
+                copy_dir(source, dest, force).await?;
+            } else {
+                copy_file(&source, &dest, force).await?;
+            }
+        }
+
+        let permissions = fs::metadata(&source).await?.permissions();
+        fs::set_permissions(&dest, permissions).await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+/// Copies a file, recreating symbolic links. Existing files are only
+/// overwritten if `force` is set.
+async fn copy_file(source: &Path, dest: &Path, force: bool) -> Result<()> {
+    let exists = fs::symlink_metadata(dest).await.is_ok();
+
+    if fs::symlink_metadata(source).await?.file_type().is_symlink() {
+        if exists {
+            remove(dest.to_path_buf()).await?;
+        }
+        let target = fs::read_link(source).await?;
+        #[cfg(unix)]
+        fs::symlink(target, dest).await?;
+        #[cfg(windows)]
+        fs::symlink_file(target, dest).await?;
+    } else if !exists || force {
+        if exists {
+            // Read-only files can't be overwritten
+            make_writable(dest).await?;
+        }
+        fs::copy(source, dest).await?;
+    }
+    Ok(())
+}
+
+fn remove(path: PathBuf) -> BoxFuture<'static, Result<()>> {
+    async move {
+        let metadata = match fs::symlink_metadata(&path).await {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        if metadata.is_dir() {
+            make_writable(&path).await?;
+            let mut entries = fs::read_dir(&path).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                remove(entry.path()).await?;
+            }
+            fs::remove_dir(&path).await?;
+        } else {
+            match fs::remove_file(&path).await {
+                Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                    make_writable(&path).await?;
+                    fs::remove_file(&path).await?;
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+    .boxed()
+}
+
+#[allow(clippy::permissions_set_readonly_false)]
+async fn make_writable(path: &Path) -> Result<()> {
+    let mut permissions = fs::metadata(path).await?.permissions();
+    if permissions.readonly() {
+        permissions.set_readonly(false);
+        fs::set_permissions(path, permissions).await?;
+    }
+    Ok(())
+}
+
+/// Gets the hex SHA-256 digest of a file.
+pub(crate) async fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut reader = fs::File::open(path).await?;
+    let mut buf = vec![0; 64 << 10];
+    loop {
+        let len = reader.read(&mut buf).await?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buf[..len]);
+    }
+    Ok(hex::encode(&hasher.finalize()[..]))
+}
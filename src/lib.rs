@@ -1,6 +1,21 @@
 #![doc = include_str!("../README.md")]
 
+pub mod action;
+pub mod archive;
+pub mod artifact;
+#[cfg(feature = "attest")]
+pub mod attest;
 pub mod cache;
+pub mod context;
+pub mod core;
+mod datetime;
+pub mod exec;
+pub mod github;
+pub mod glob;
+pub mod io;
 mod result;
+mod temp;
+pub mod tool_cache;
+mod transport;
 
 pub use crate::result::*;
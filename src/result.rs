@@ -3,6 +3,28 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 #[derive(thiserror::Error, Debug)]
 #[non_exhaustive]
 pub enum Error {
+    #[error("Artifact digest mismatch: expected {expected} got {actual}")]
+    ArtifactDigestMismatch { expected: String, actual: String },
+
+    #[error("Artifact not found: {0}")]
+    ArtifactNotFound(String),
+
+    #[error("Artifact service failed to {0}")]
+    ArtifactServiceFailed(&'static str),
+
+    #[error("Artifact service responded with {status}: {message}")]
+    ArtifactServiceStatus {
+        status: http::StatusCode,
+        message: String,
+    },
+
+    #[error("Artifact service does not support {0}")]
+    ArtifactServiceUnsupported(&'static str),
+
+    #[cfg(feature = "attest")]
+    #[error("Attestation verification failed: {0}")]
+    AttestationVerification(&'static str),
+
     #[error("Invalid chunk checksum")]
     CacheChunkChecksum,
 
@@ -25,21 +47,125 @@ pub enum Error {
     #[error("Cache size of {0} bytes is too large")]
     CacheSizeTooLarge(usize),
 
+    #[error("The process '{tool}' failed with {status}")]
+    ExecExitStatus {
+        tool: String,
+        status: std::process::ExitStatus,
+    },
+
+    #[error(
+        "The process '{0}' failed because one or more lines were written to the STDERR stream"
+    )]
+    ExecStderr(String),
+
+    #[error("File command value contains the delimiter {0}")]
+    FileCommandDelimiter(String),
+
+    #[cfg(feature = "attest")]
+    #[error("Fulcio responded with {status}: {message}")]
+    FulcioStatus {
+        status: http::StatusCode,
+        message: String,
+    },
+
+    #[error("GitHub API responded with {status}: {message}")]
+    GitHubApiStatus {
+        status: http::StatusCode,
+        message: String,
+    },
+
+    #[error(
+        "GitHub GraphQL request failed: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    GitHubGraphQl(Vec<crate::github::graphql::GraphQlError>),
+
+    #[error("GitHub API rate limit exceeded, retry after {retry_after:?}")]
+    GitHubRateLimited { retry_after: std::time::Duration },
+
+    #[error("ID token service responded with {status}: {message}")]
+    IdTokenServiceStatus {
+        status: http::StatusCode,
+        message: String,
+    },
+
+    #[error("Invalid archive path: {}", .0.display())]
+    InvalidArchivePath(std::path::PathBuf),
+
+    #[error("Invalid artifact name: {0}")]
+    InvalidArtifactName(String),
+
+    #[error("Invalid artifact path: {}", .0.display())]
+    InvalidArtifactPath(std::path::PathBuf),
+
+    #[error("Invalid checksum manifest line: {0}")]
+    InvalidChecksumManifest(String),
+
+    #[error("Invalid comment marker: {0} cannot contain \"-->\"")]
+    InvalidCommentMarker(String),
+
     #[error(transparent)]
     InvalidHeaderValue(#[from] http::header::InvalidHeaderValue),
 
+    #[error("Invalid ID token: {0}")]
+    InvalidIdToken(&'static str),
+
+    #[error("Invalid pattern '{pattern}'. {reason}")]
+    InvalidGlobPattern {
+        pattern: String,
+        reason: &'static str,
+    },
+
+    #[error("Invalid runtime token: {0}")]
+    InvalidRuntimeToken(&'static str),
+
+    #[error("Invalid value for env var \"{name}\": {value}")]
+    InvalidVar { name: &'static str, value: String },
+
     #[error("Key Validation Error: {0} cannot contain commas")]
     InvalidKeyComma(String),
 
     #[error("Key Validation Error: {0} cannot be larger than 512 characters")]
     InvalidKeyLength(String),
 
+    #[error("Missing issue or pull request number in the {0} event")]
+    MissingIssueNumber(String),
+
     #[error("Missing one of key or restore keys")]
     MissingKey,
 
+    #[error("Missing workflow run ID")]
+    MissingWorkflowRunId,
+
+    #[error("Release asset {name} checksum mismatch: expected {expected} got {actual}")]
+    ReleaseAssetChecksum {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Unexpected HTTP response from {url}: {status}")]
+    ToolDownloadStatus {
+        status: http::StatusCode,
+        url: String,
+    },
+
+    #[error("No run found for the dispatch of workflow {0}")]
+    WorkflowRunNotFound(String),
+
+    #[error("Timed out waiting for workflow run {0}")]
+    WorkflowRunTimeout(u64),
+
+    #[error(transparent)]
+    Base64Decode(#[from] base64::DecodeError),
+
     #[error(transparent)]
     IO(#[from] std::io::Error),
 
+    #[cfg(any(feature = "attest", feature = "github-app"))]
+    #[error(transparent)]
+    OpenSsl(#[from] openssl::error::ErrorStack),
+
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
 
@@ -0,0 +1,57 @@
+//! Temporary files in the runner temporary directory.
+
+use std::env;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use crate::Result;
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Gets the runner temporary directory `RUNNER_TEMP`, or the system temporary
+/// directory outside of a runner.
+pub(crate) fn dir() -> PathBuf {
+    env::var_os("RUNNER_TEMP")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+}
+
+/// Gets a unique path in the runner temporary directory.
+pub(crate) fn unique_path(extension: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.subsec_nanos());
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+    dir().join(format!(
+        "gha-toolkit-{:x}{nanos:x}{count:x}{extension}",
+        process::id()
+    ))
+}
+
+/// Temporary file which is removed when dropped.
+pub(crate) struct TempFile {
+    pub path: PathBuf,
+    pub file: File,
+}
+
+impl TempFile {
+    /// Creates a new temporary file for reading and writing.
+    pub fn create(extension: &str) -> Result<Self> {
+        let path = unique_path(extension);
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Self { path, file })
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,330 @@
+//! # GitHub Actions tool cache
+//!
+//! Functions for downloading tools, e.g. compilers, and caching them in the
+//! runner tool cache `RUNNER_TOOL_CACHE` for setup actions like
+//! [actions/setup-node](https://github.com/actions/setup-node). This is an
+//! idiomatic Rust port of
+//! [@actions/tool-cache](https://github.com/actions/toolkit/tree/main/packages/tool-cache).
+//!
+//! Tools are cached in `$RUNNER_TOOL_CACHE/<tool>/<version>/<arch>` and found
+//! with [node-semver](https://github.com/npm/node-semver#ranges) version
+//! ranges like `1.x` or `>=1.2 <2`. Downloaded archives are extracted with
+//! [`extract_tar`] and [`extract_zip`] from the [archive][crate::archive]
+//! module:
+//!
+//! ```rust,no_run
+//! use gha_toolkit::tool_cache::{self, DownloadToolOptions};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let dir = match tool_cache::find("jq", "1.6.x", None) {
+//!     Some(dir) => dir,
+//!     None => {
+//!         let url = "https://github.com/stedolan/jq/releases/download/jq-1.6/jq-linux64";
+//!         let path = tool_cache::download_tool(url, &DownloadToolOptions::default()).await?;
+//!         tool_cache::cache_file(path, "jq", "jq", "1.6.0", None)?
+//!     }
+//! };
+//!
+//! println!("jq is cached in {}", dir.display());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use http::{header, HeaderMap, HeaderValue};
+use tracing::{debug, instrument};
+
+use crate::core::secrets;
+use crate::temp;
+use crate::transport::ClientOptions;
+use crate::{Error, Result};
+
+mod manifest;
+mod version;
+
+pub use self::manifest::{
+    find_from_manifest, get_manifest_from_file, get_manifest_from_repo, platform, ToolRelease,
+    ToolReleaseFile,
+};
+#[cfg(feature = "sevenz")]
+pub use crate::archive::extract_7z;
+pub use crate::archive::{extract_tar, extract_zip, ExtractOptions};
+
+const USER_AGENT: &str = "actions/tool-cache";
+
+/// Options for [`download_tool`].
+///
+/// The authorization is never written by the [`Debug`][fmt::Debug]
+/// implementation.
+#[derive(Clone, Default)]
+pub struct DownloadToolOptions {
+    /// Destination path. Defaults to a new file in `RUNNER_TEMP`.
+    pub dest: Option<PathBuf>,
+
+    /// Value of the `Authorization` header, e.g. `token <token>`.
+    pub auth: Option<String>,
+
+    /// Additional request headers.
+    pub headers: HeaderMap,
+}
+
+impl fmt::Debug for DownloadToolOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DownloadToolOptions")
+            .field("dest", &self.dest)
+            .field("auth", &self.auth.as_ref().map(|_| secrets::MASK))
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+/// Downloads a tool from `url` and returns the path of the downloaded file.
+///
+/// Transient request failures are retried. The authorization of `options` is
+/// registered as a [secret][secrets::register]. Fails if the destination file
+/// already exists.
+#[instrument(skip(options))]
+pub async fn download_tool(url: &str, options: &DownloadToolOptions) -> Result<PathBuf> {
+    let dest = match &options.dest {
+        Some(dest) => dest.clone(),
+        None => temp::unique_path(""),
+    };
+    if dest.exists() {
+        return Err(Error::IO(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Destination file path {} already exists", dest.display()),
+        )));
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut headers = options.headers.clone();
+    if let Some(auth) = &options.auth {
+        secrets::register(auth);
+        let mut auth_value = HeaderValue::from_maybe_shared(Bytes::from(auth.clone()))?;
+        auth_value.set_sensitive(true);
+        headers.insert(header::AUTHORIZATION, auth_value);
+    }
+
+    let client = ClientOptions {
+        user_agent: USER_AGENT,
+        ..Default::default()
+    }
+    .build()?;
+
+    let mut response = client.get(url).headers(headers).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let url = secrets::redact(url).into_owned();
+        return Err(Error::ToolDownloadStatus { status, url });
+    }
+
+    debug!("Downloading {url} to {}", dest.display());
+    let mut file = File::create(&dest)?;
+    let result = async {
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => Ok(dest),
+        Err(err) => {
+            drop(file);
+            let _ = fs::remove_file(&dest);
+            Err(err)
+        }
+    }
+}
+
+/// Caches the contents of `source_dir` as `tool` at `version` and returns
+/// the path of the cached directory.
+///
+/// `arch` defaults to the [architecture][arch] of the runner.
+#[instrument(skip(source_dir), fields(source_dir = %source_dir.as_ref().display()))]
+pub fn cache_dir<P: AsRef<Path>>(
+    source_dir: P,
+    tool: &str,
+    version: &str,
+    arch: Option<&str>,
+) -> Result<PathBuf> {
+    let source_dir = source_dir.as_ref();
+    if !source_dir.is_dir() {
+        return Err(Error::IO(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a directory", source_dir.display()),
+        )));
+    }
+
+    let arch = arch.unwrap_or_else(|| self::arch());
+    let dest = create_tool_path(tool, version, arch)?;
+    for entry in fs::read_dir(source_dir)? {
+        let entry = entry?;
+        copy_recursive(&entry.path(), &dest.join(entry.file_name()))?;
+    }
+    complete_tool_path(tool, version, arch)?;
+
+    Ok(dest)
+}
+
+/// Caches `source_file` as the file `target_file` of `tool` at `version`
+/// and returns the path of the cached directory.
+///
+/// `arch` defaults to the [architecture][arch] of the runner.
+#[instrument(skip(source_file), fields(source_file = %source_file.as_ref().display()))]
+pub fn cache_file<P: AsRef<Path>>(
+    source_file: P,
+    target_file: &str,
+    tool: &str,
+    version: &str,
+    arch: Option<&str>,
+) -> Result<PathBuf> {
+    let source_file = source_file.as_ref();
+    if !source_file.is_file() {
+        return Err(Error::IO(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file", source_file.display()),
+        )));
+    }
+
+    let arch = arch.unwrap_or_else(|| self::arch());
+    let dest = create_tool_path(tool, version, arch)?;
+    fs::copy(source_file, dest.join(target_file))?;
+    complete_tool_path(tool, version, arch)?;
+
+    Ok(dest)
+}
+
+/// Finds the latest cached version of `tool` matching `version_spec`, e.g.
+/// `1.2.3`, `1.x` or `>=1.2 <2`, and returns the path of its directory.
+///
+/// `arch` defaults to the [architecture][arch] of the runner.
+pub fn find(tool: &str, version_spec: &str, arch: Option<&str>) -> Option<PathBuf> {
+    let arch = arch.unwrap_or_else(|| self::arch());
+
+    let version = match version::clean(version_spec) {
+        Some(version) => version.to_string(),
+        None => {
+            let versions = find_all_versions(tool, Some(arch));
+            version::evaluate(versions.iter().map(String::as_str), version_spec)?.to_string()
+        }
+    };
+
+    let tool_path = tool_cache_dir().ok()?.join(tool).join(version);
+    let path = tool_path.join(arch);
+    if path.is_dir() && tool_path.join(format!("{arch}.complete")).is_file() {
+        debug!("Found tool in cache {tool} {}", path.display());
+        Some(path)
+    } else {
+        debug!("Unable to find tool in cache {tool} {version_spec} {arch}");
+        None
+    }
+}
+
+/// Finds all cached versions of `tool`, sorted by name.
+///
+/// `arch` defaults to the [architecture][arch] of the runner.
+pub fn find_all_versions(tool: &str, arch: Option<&str>) -> Vec<String> {
+    let arch = arch.unwrap_or_else(|| self::arch());
+    let tool_path = match tool_cache_dir() {
+        Ok(dir) => dir.join(tool),
+        Err(_) => return Vec::new(),
+    };
+    let entries = match fs::read_dir(&tool_path) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut versions: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|version| {
+            let version_path = tool_path.join(version);
+            version_path.join(arch).is_dir()
+                && version_path.join(format!("{arch}.complete")).is_file()
+        })
+        .collect();
+    versions.sort();
+    versions
+}
+
+/// Gets the architecture of the runner with the names used by the tool cache
+/// and Node.js, e.g. `x64` or `arm64`.
+pub fn arch() -> &'static str {
+    match env::consts::ARCH {
+        "x86_64" => "x64",
+        "x86" => "ia32",
+        "aarch64" => "arm64",
+        "powerpc64" => "ppc64",
+        arch => arch,
+    }
+}
+
+fn tool_cache_dir() -> Result<PathBuf> {
+    env::var("RUNNER_TOOL_CACHE")
+        .map(PathBuf::from)
+        .map_err(|source| Error::VarError {
+            source,
+            name: "RUNNER_TOOL_CACHE",
+        })
+}
+
+fn tool_version(version: &str) -> String {
+    version::clean(version).map_or_else(|| version.to_string(), |version| version.to_string())
+}
+
+/// Creates an empty tool directory, removing any previous version.
+fn create_tool_path(tool: &str, version: &str, arch: &str) -> Result<PathBuf> {
+    let tool_path = tool_cache_dir()?.join(tool).join(tool_version(version));
+    let path = tool_path.join(arch);
+    let marker = tool_path.join(format!("{arch}.complete"));
+    debug!("Destination {}", path.display());
+
+    if path.exists() {
+        fs::remove_dir_all(&path)?;
+    }
+    if marker.exists() {
+        fs::remove_file(&marker)?;
+    }
+    fs::create_dir_all(&path)?;
+
+    Ok(path)
+}
+
+/// Marks a tool directory as completely cached.
+fn complete_tool_path(tool: &str, version: &str, arch: &str) -> Result<()> {
+    let tool_path = tool_cache_dir()?.join(tool).join(tool_version(version));
+    fs::write(tool_path.join(format!("{arch}.complete")), "")?;
+    debug!("Finished caching tool");
+    Ok(())
+}
+
+/// Copies a file or directory, recreating symbolic links on Unix.
+fn copy_recursive(source: &Path, dest: &Path) -> Result<()> {
+    #[cfg(unix)]
+    if fs::symlink_metadata(source)?.file_type().is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(source)?, dest)?;
+        return Ok(());
+    }
+
+    if source.is_dir() {
+        fs::create_dir_all(dest)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(source, dest)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! Versions manifests listing the releases of a tool, like the
+//! `versions-manifest.json` of
+//! [actions/python-versions](https://github.com/actions/python-versions).
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use bytes::Bytes;
+use http::{header, HeaderValue};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use super::{version, USER_AGENT};
+use crate::core::secrets;
+use crate::transport::ClientOptions;
+use crate::{Error, Result};
+
+const MANIFEST_FILE: &str = "versions-manifest.json";
+const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Release of a tool in a versions manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolRelease {
+    /// Version of the release, e.g. `3.11.0`.
+    pub version: String,
+
+    /// Whether the release is stable, i.e. not a pre-release.
+    pub stable: bool,
+
+    /// URL of the release page.
+    #[serde(default)]
+    pub release_url: String,
+
+    /// Files of the release for different platforms.
+    pub files: Vec<ToolReleaseFile>,
+}
+
+/// File of a [`ToolRelease`] for a platform.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolReleaseFile {
+    /// Name of the file, e.g. `python-3.11.0-linux-22.04-x64.tar.gz`.
+    pub filename: String,
+
+    /// Platform with the names of Node.js, e.g. `linux`, `darwin` or `win32`.
+    pub platform: String,
+
+    /// Version range of the platform the file is for, e.g. `22.04`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform_version: Option<String>,
+
+    /// Architecture, e.g. `x64` or `arm64`.
+    pub arch: String,
+
+    /// URL for downloading the file.
+    pub download_url: String,
+}
+
+/// Gets the versions manifest `versions-manifest.json` of a GitHub
+/// repository, e.g. `actions/python-versions` at `main`.
+///
+/// `branch` defaults to `master`. Returns an empty manifest if the repository
+/// doesn't have one.
+#[instrument(skip(auth))]
+pub async fn get_manifest_from_repo(
+    owner: &str,
+    repo: &str,
+    auth: Option<&str>,
+    branch: Option<&str>,
+) -> Result<Vec<ToolRelease>> {
+    #[derive(Deserialize)]
+    struct Tree {
+        tree: Vec<TreeItem>,
+    }
+
+    #[derive(Deserialize)]
+    struct TreeItem {
+        path: String,
+        url: String,
+    }
+
+    let api_url = env::var("GITHUB_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string());
+    let branch = branch.unwrap_or("master");
+    let client = ClientOptions {
+        user_agent: USER_AGENT,
+        ..Default::default()
+    }
+    .build()?;
+
+    let mut auth_value = None;
+    if let Some(auth) = auth {
+        secrets::register(auth);
+        let mut value = HeaderValue::from_maybe_shared(Bytes::from(auth.to_string()))?;
+        value.set_sensitive(true);
+        auth_value = Some(value);
+    }
+    let get = |url: &str| {
+        let request = client.get(url);
+        match &auth_value {
+            Some(auth) => request.header(header::AUTHORIZATION, auth.clone()),
+            None => request,
+        }
+    };
+
+    let url = format!(
+        "{}/repos/{owner}/{repo}/git/trees/{branch}",
+        api_url.trim_end_matches('/')
+    );
+    let response = get(&url).send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        return Err(Error::ToolDownloadStatus { status, url });
+    }
+    let tree: Tree = response.json().await?;
+
+    let item = match tree
+        .tree
+        .into_iter()
+        .find(|item| item.path == MANIFEST_FILE)
+    {
+        Some(item) => item,
+        None => {
+            debug!("No {MANIFEST_FILE} in {owner}/{repo}@{branch}");
+            return Ok(Vec::new());
+        }
+    };
+
+    let response = get(&item.url)
+        .header(
+            header::ACCEPT,
+            HeaderValue::from_static("application/vnd.github.VERSION.raw"),
+        )
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        return Err(Error::ToolDownloadStatus {
+            status,
+            url: item.url,
+        });
+    }
+
+    Ok(serde_json::from_slice(&response.bytes().await?)?)
+}
+
+/// Reads a versions manifest from a local file.
+pub fn get_manifest_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<ToolRelease>> {
+    let json = fs::read(path)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Finds the latest release in `manifest` matching `version_spec`, e.g.
+/// `3.11.x`, with a file for `arch` and `platform`. The returned release
+/// only has the matching file.
+///
+/// Pre-releases are only matched if `stable` is `false`. Files with a
+/// `platform_version` must match the version of the operating system.
+/// `arch` and `platform` default to the [architecture][super::arch] and
+/// [platform][platform] of the runner.
+pub fn find_from_manifest(
+    version_spec: &str,
+    stable: bool,
+    manifest: &[ToolRelease],
+    arch: Option<&str>,
+    platform: Option<&str>,
+) -> Option<ToolRelease> {
+    let arch = arch.unwrap_or_else(|| super::arch());
+    let platform = platform.unwrap_or_else(|| self::platform());
+
+    // Only read when a file has a platform version
+    let mut os_version = None;
+    let mut matches_file = |file: &ToolReleaseFile| {
+        if file.arch != arch || file.platform != platform {
+            return false;
+        }
+        match &file.platform_version {
+            Some(spec) => {
+                let os_version = os_version.get_or_insert_with(self::os_version);
+                os_version.as_ref().map_or(false, |os_version| {
+                    os_version == spec
+                        || version::coerce(os_version).map_or(false, |os_version| {
+                            version::satisfies(&os_version, &trim_leading_zeros(spec))
+                        })
+                })
+            }
+            None => true,
+        }
+    };
+
+    let mut best: Option<(semver::Version, &ToolRelease, &ToolReleaseFile)> = None;
+    for release in manifest {
+        if stable && !release.stable {
+            continue;
+        }
+        let version = match version::clean(&release.version) {
+            Some(version) if version::satisfies(&version, version_spec) => version,
+            _ => continue,
+        };
+        if best.as_ref().map_or(false, |(best, _, _)| *best >= version) {
+            continue;
+        }
+        if let Some(file) = release.files.iter().find(|file| matches_file(file)) {
+            best = Some((version, release, file));
+        }
+    }
+
+    best.map(|(_, release, file)| ToolRelease {
+        files: vec![file.clone()],
+        ..release.clone()
+    })
+}
+
+/// Gets the platform of the runner with the names used by Node.js, e.g.
+/// `linux`, `darwin` or `win32`.
+pub fn platform() -> &'static str {
+    match env::consts::OS {
+        "macos" => "darwin",
+        "windows" => "win32",
+        os => os,
+    }
+}
+
+/// Gets the version of the operating system the `platform_version` of
+/// manifest files is compared to, e.g. `22.04` on Ubuntu or `13.0` on macOS.
+fn os_version() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        let plist = fs::read_to_string("/System/Library/CoreServices/SystemVersion.plist").ok()?;
+        let (_, rest) = plist.split_once("<key>ProductVersion</key>")?;
+        let (_, rest) = rest.split_once("<string>")?;
+        let (version, _) = rest.split_once("</string>")?;
+        Some(version.trim().to_string())
+    } else if cfg!(target_os = "linux") {
+        let release = fs::read_to_string("/etc/lsb-release")
+            .or_else(|_| fs::read_to_string("/etc/os-release"))
+            .ok()?;
+        release.lines().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            matches!(key, "DISTRIB_RELEASE" | "VERSION_ID")
+                .then(|| value.trim().trim_matches('"').to_string())
+        })
+    } else {
+        None
+    }
+}
+
+/// Removes leading zeros from numbers, which aren't valid in semantic
+/// versions, e.g. `>=20.04` to `>=20.4`.
+fn trim_leading_zeros(spec: &str) -> String {
+    let mut trimmed = String::with_capacity(spec.len());
+    let mut in_number = false;
+    let mut chars = spec.chars().peekable();
+    while let Some(c) = chars.next() {
+        let leading_zero =
+            c == '0' && !in_number && chars.peek().map_or(false, char::is_ascii_digit);
+        if !leading_zero {
+            trimmed.push(c);
+            in_number = c.is_ascii_digit();
+        }
+    }
+    trimmed
+}
@@ -0,0 +1,95 @@
+//! Version specs with the range syntax of [node-semver], as used by
+//! `@actions/tool-cache` and the setup actions.
+//!
+//! Ranges are translated to [`VersionReq`]s: comparators separated by
+//! whitespace must all match, `||` separates alternatives, `1.2 - 1.4` is a
+//! hyphen range and a partial version like `1.2` matches `1.2.x`, while a
+//! full version like `1.2.3` only matches that version.
+//!
+//! [node-semver]: https://github.com/npm/node-semver#ranges
+
+use semver::{Version, VersionReq};
+
+const OPERATOR_CHARS: &[char] = &['<', '>', '=', '~', '^'];
+
+/// Parses a version, ignoring surrounding whitespace and a leading `v` or `=`.
+pub(crate) fn clean(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches(['=', 'v', 'V']);
+    Version::parse(version.trim()).ok()
+}
+
+/// Parses a version leniently, filling in missing components, e.g. `22.04`
+/// as `22.4.0`.
+pub(crate) fn coerce(version: &str) -> Option<Version> {
+    let mut numbers = [0; 3];
+    for (i, part) in strip_v(version.trim()).split('.').take(3).enumerate() {
+        numbers[i] = part.parse().ok()?;
+    }
+    Some(Version::new(numbers[0], numbers[1], numbers[2]))
+}
+
+/// Returns `true` if `version` matches the range `spec`. Invalid ranges never
+/// match.
+pub(crate) fn satisfies(version: &Version, spec: &str) -> bool {
+    spec.split("||")
+        .map(parse_comparator_set)
+        .any(|req| req.map_or(false, |req| req.matches(version)))
+}
+
+/// Gets the latest of `versions` matching the range `spec`.
+pub(crate) fn evaluate<'a, I>(versions: I, spec: &str) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    versions
+        .into_iter()
+        .filter_map(|version| clean(version).map(|parsed| (parsed, version)))
+        .filter(|(parsed, _)| satisfies(parsed, spec))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, version)| version)
+}
+
+fn parse_comparator_set(set: &str) -> Option<VersionReq> {
+    let tokens: Vec<&str> = set.split_whitespace().collect();
+
+    let mut comparators = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens.get(i + 1) == Some(&"-") && i + 2 < tokens.len() {
+            comparators.push(format!(">={}", strip_v(tokens[i])));
+            comparators.push(format!("<={}", strip_v(tokens[i + 2])));
+            i += 3;
+            continue;
+        }
+
+        // The operator may be separated from the version, e.g. `>= 1.2`
+        let mut token = tokens[i].to_string();
+        if token.chars().all(|c| OPERATOR_CHARS.contains(&c)) && i + 1 < tokens.len() {
+            i += 1;
+            token.push_str(tokens[i]);
+        }
+        i += 1;
+
+        let split = token
+            .find(|c| !OPERATOR_CHARS.contains(&c))
+            .unwrap_or(token.len());
+        let (operator, version) = token.split_at(split);
+        let version = strip_v(version);
+        if matches!(version, "" | "*" | "x" | "X") {
+            comparators.push("*".to_string());
+        } else if operator.is_empty() {
+            comparators.push(format!("={version}"));
+        } else {
+            comparators.push(format!("{operator}{version}"));
+        }
+    }
+
+    if comparators.is_empty() {
+        comparators.push("*".to_string());
+    }
+    VersionReq::parse(&comparators.join(", ")).ok()
+}
+
+fn strip_v(version: &str) -> &str {
+    version.trim_start_matches(['v', 'V'])
+}
@@ -0,0 +1,133 @@
+//! HTTP client shared by the toolkit's service clients.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::prelude::*;
+use reqwest::Certificate;
+use reqwest_middleware::{ClientWithMiddleware, Middleware};
+use reqwest_retry::policies::ExponentialBackoff;
+use reqwest_retry::RetryTransientMiddleware;
+use reqwest_retry_after::RetryAfterMiddleware;
+use reqwest_tracing::TracingMiddleware;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::Result;
+
+pub(crate) const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_CRATE_NAME"), "/", env!("CARGO_PKG_VERSION"));
+pub(crate) const DEFAULT_MAX_RETRIES: u32 = 2;
+pub(crate) const DEFAULT_MIN_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+pub(crate) const DEFAULT_MAX_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+pub(crate) const DEFAULT_BACKOFF_FACTOR_BASE: u32 = 3;
+
+/// Options for building a [`ClientWithMiddleware`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ClientOptions<'a> {
+    pub user_agent: &'a str,
+    pub max_retries: u32,
+    pub min_retry_interval: Duration,
+    pub max_retry_interval: Duration,
+    pub backoff_factor_base: u32,
+    pub ca_bundle: Option<&'a Path>,
+}
+
+impl Default for ClientOptions<'_> {
+    fn default() -> Self {
+        Self {
+            user_agent: DEFAULT_USER_AGENT,
+            max_retries: DEFAULT_MAX_RETRIES,
+            min_retry_interval: DEFAULT_MIN_RETRY_INTERVAL,
+            max_retry_interval: DEFAULT_MAX_RETRY_INTERVAL,
+            backoff_factor_base: DEFAULT_BACKOFF_FACTOR_BASE,
+            ca_bundle: None,
+        }
+    }
+}
+
+impl ClientOptions<'_> {
+    /// Builds a client with tracing, `Retry-After` and transient error retry
+    /// middleware.
+    pub fn build(&self) -> Result<ClientWithMiddleware> {
+        Ok(self.with_middleware(self.build_client()?, &[]))
+    }
+
+    /// Builds a client without middleware, for requests with streaming
+    /// bodies that can't be retried.
+    ///
+    /// Proxies are read from the `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and
+    /// `NO_PROXY` environmental variables.
+    pub fn build_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::ClientBuilder::new().user_agent(self.user_agent);
+        if let Some(ca_bundle) = self.ca_bundle {
+            let pem_bundle = std::fs::read_to_string(ca_bundle)?;
+            for pem in pem_certificates(&pem_bundle) {
+                let certificate = Certificate::from_pem(pem.as_bytes())?;
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Adds tracing, `Retry-After` and transient error retry middleware to a
+    /// client, followed by `middleware`.
+    pub fn with_middleware(
+        &self,
+        client: reqwest::Client,
+        middleware: &[Arc<dyn Middleware>],
+    ) -> ClientWithMiddleware {
+        let retry_policy = ExponentialBackoff::builder()
+            .retry_bounds(self.min_retry_interval, self.max_retry_interval)
+            .backoff_exponent(self.backoff_factor_base)
+            .build_with_max_retries(self.max_retries);
+
+        let builder = reqwest_middleware::ClientBuilder::new(client)
+            .with(TracingMiddleware::default())
+            .with(RetryAfterMiddleware::new())
+            .with(RetryTransientMiddleware::new_with_policy(retry_policy));
+        middleware
+            .iter()
+            .cloned()
+            .fold(builder, |builder, middleware| builder.with_arc(middleware))
+            .build()
+    }
+}
+
+/// Splits a PEM bundle into its certificates, ignoring other blocks and text
+/// between them.
+fn pem_certificates(pem_bundle: &str) -> impl Iterator<Item = &str> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let mut rest = pem_bundle;
+    std::iter::from_fn(move || {
+        let start = rest.find(BEGIN)?;
+        let end = start + rest[start..].find(END)? + END.len();
+        let pem = &rest[start..end];
+        rest = &rest[end..];
+        Some(pem)
+    })
+}
+
+/// Reads `reader` as a stream of chunks of at most `chunk_size` bytes, e.g.
+/// for a streaming request body.
+pub(crate) fn read_chunks<R>(reader: R, chunk_size: u64) -> impl Stream<Item = io::Result<Bytes>>
+where
+    R: AsyncRead + Unpin,
+{
+    stream::try_unfold(reader, move |mut reader| async move {
+        let mut chunk = Vec::new();
+        (&mut reader)
+            .take(chunk_size)
+            .read_to_end(&mut chunk)
+            .await?;
+        if chunk.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some((Bytes::from(chunk), reader)))
+        }
+    })
+}
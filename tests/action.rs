@@ -0,0 +1,18 @@
+use gha_toolkit::action::Phase;
+
+use std::env;
+
+#[test]
+fn detect_phase() {
+    env::remove_var("STATE_isPre");
+    env::remove_var("STATE_isPost");
+    assert_eq!(Phase::detect(false), Phase::Main);
+    assert_eq!(Phase::detect(true), Phase::Pre);
+
+    env::set_var("STATE_isPre", "true");
+    assert_eq!(Phase::detect(true), Phase::Main);
+
+    env::set_var("STATE_isPost", "true");
+    assert_eq!(Phase::detect(false), Phase::Post);
+    assert_eq!(Phase::detect(true), Phase::Post);
+}
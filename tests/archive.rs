@@ -0,0 +1,162 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use gha_toolkit::archive::{self, ExtractOptions};
+use gha_toolkit::Error;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("gha-toolkit-archive-{name}-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn header(name: &str, kind: tar::EntryType, size: u64, mode: u32) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    // Written directly, since `set_path` rejects unsafe paths
+    header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+    header.set_entry_type(kind);
+    header.set_size(size);
+    header.set_mode(mode);
+    header.set_mtime(1_600_000_000);
+    header.set_cksum();
+    header
+}
+
+fn tar_gz(path: &Path, entries: &[(&str, tar::EntryType, &str)]) {
+    let encoder = GzEncoder::new(fs::File::create(path).unwrap(), Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    for (name, kind, data) in entries {
+        if *kind == tar::EntryType::Symlink {
+            let mut header = header(name, *kind, 0, 0o777);
+            header.set_link_name(data).unwrap();
+            header.set_cksum();
+            builder.append(&header, &[][..]).unwrap();
+        } else {
+            let mode = if kind.is_dir() { 0o755 } else { 0o750 };
+            let header = header(name, *kind, data.len() as u64, mode);
+            builder.append(&header, data.as_bytes()).unwrap();
+        }
+    }
+    builder.into_inner().unwrap().finish().unwrap();
+}
+
+#[test]
+fn extract_tar() {
+    let root = temp_dir("tar");
+    let file = root.join("tool.tar.gz");
+    tar_gz(
+        &file,
+        &[
+            ("tool-1.0/", tar::EntryType::Directory, ""),
+            ("tool-1.0/bin/tool", tar::EntryType::Regular, "#!/bin/sh\n"),
+            ("tool-1.0/tool", tar::EntryType::Symlink, "bin/tool"),
+            ("README", tar::EntryType::Regular, "skipped"),
+        ],
+    );
+
+    let options = ExtractOptions {
+        strip_components: 1,
+        ..Default::default()
+    };
+    let dest = archive::extract_tar(&file, Some(&root.join("dest")), &options).unwrap();
+    assert_eq!(
+        fs::read_to_string(dest.join("bin/tool")).unwrap(),
+        "#!/bin/sh\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dest.join("tool")).unwrap(),
+        "#!/bin/sh\n"
+    );
+    assert!(!dest.join("README").exists());
+
+    let metadata = fs::metadata(dest.join("bin/tool")).unwrap();
+    let mtime = metadata.modified().unwrap();
+    assert_eq!(
+        mtime
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        1_600_000_000
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o750);
+    }
+
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn extract_tar_escape() {
+    let root = temp_dir("tar-escape");
+
+    for (name, entries) in [
+        ("parent", vec![("../evil", tar::EntryType::Regular, "evil")]),
+        ("symlink", vec![("link", tar::EntryType::Symlink, "../")]),
+        (
+            "absolute-symlink",
+            vec![("link", tar::EntryType::Symlink, "/tmp")],
+        ),
+    ] {
+        let file = root.join(format!("{name}.tar.gz"));
+        tar_gz(&file, &entries);
+
+        let dest = root.join(name).join("dest");
+        let result = archive::extract_tar(&file, Some(&dest), &ExtractOptions::default());
+        assert!(
+            matches!(result, Err(Error::InvalidArchivePath(_))),
+            "{name}: {result:?}"
+        );
+        assert!(!root.join(name).join("evil").exists());
+    }
+
+    // Writing through a link pointing outside the destination
+    #[cfg(unix)]
+    {
+        let dest = root.join("existing").join("dest");
+        fs::create_dir_all(&dest).unwrap();
+        std::os::unix::fs::symlink("..", dest.join("link")).unwrap();
+
+        let file = root.join("existing.tar.gz");
+        tar_gz(&file, &[("link/evil", tar::EntryType::Regular, "evil")]);
+        let result = archive::extract_tar(&file, Some(&dest), &ExtractOptions::default());
+        assert!(matches!(result, Err(Error::InvalidArchivePath(_))));
+        assert!(!root.join("existing/evil").exists());
+    }
+
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn extract_zip() {
+    let root = temp_dir("zip");
+    let file = root.join("tool.zip");
+    let mut writer = zip::ZipWriter::new(fs::File::create(&file).unwrap());
+    let options = zip::write::FileOptions::default().unix_permissions(0o750);
+    writer.add_directory("tool-1.0/bin/", options).unwrap();
+    writer.start_file("tool-1.0/bin/tool", options).unwrap();
+    writer.write_all(b"#!/bin/sh\n").unwrap();
+    writer.start_file("../evil", options).unwrap();
+    writer.finish().unwrap();
+
+    let options = ExtractOptions {
+        strip_components: 1,
+        ..Default::default()
+    };
+    let result = archive::extract_zip(&file, Some(&root.join("dest")), &options);
+    assert!(matches!(result, Err(Error::InvalidArchivePath(_))));
+    assert_eq!(
+        fs::read_to_string(root.join("dest/bin/tool")).unwrap(),
+        "#!/bin/sh\n"
+    );
+    assert!(!root.join("evil").exists());
+
+    let _ = fs::remove_dir_all(&root);
+}
@@ -0,0 +1,607 @@
+mod common;
+
+use gha_toolkit::artifact::*;
+use gha_toolkit::Error;
+
+use std::env;
+use std::fs;
+use std::io::{self, prelude::*, Cursor, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use sha2::{Digest, Sha256};
+use tokio::test;
+use zip::{CompressionMethod, ZipArchive};
+
+use self::common::serve;
+
+#[test]
+async fn artifact_name() {
+    assert!(check_artifact_name("my-artifact_1.0").is_ok());
+    assert!(matches!(
+        check_artifact_name("bad/name"),
+        Err(Error::InvalidArtifactName(_))
+    ));
+    assert!(matches!(
+        check_artifact_name(""),
+        Err(Error::InvalidArtifactName(_))
+    ));
+}
+
+#[test]
+async fn runtime_token() {
+    let payload = base64::encode_config(
+        r#"{"scp": "Actions.ExampleScope Actions.Results:run-id:job-id"}"#,
+        base64::URL_SAFE_NO_PAD,
+    );
+    let token = format!("e30.{payload}.c2ln");
+
+    let builder = ArtifactClient::builder("https://results.example.com/", &token);
+    assert!(!format!("{builder:?}").contains(&token));
+    assert!(builder.build().is_ok());
+
+    let result = ArtifactClient::builder("https://results.example.com/", "e30.e30.c2ln").build();
+    assert!(matches!(result, Err(Error::InvalidRuntimeToken(_))));
+}
+
+#[test]
+async fn find_by() {
+    let find_by = FindBy::new("ghp_find_by_token", "octo-org", "octo-repo", 1234);
+    assert!(!format!("{find_by:?}").contains("ghp_find_by_token"));
+    assert!(gha_toolkit::core::secrets::is_registered(
+        "ghp_find_by_token"
+    ));
+}
+
+#[test]
+async fn legacy_service() {
+    let builder = ArtifactClient::builder("https://pipelines.example.com/", "token")
+        .version(ServiceVersion::V3);
+    assert!(matches!(
+        builder.clone().build(),
+        Err(Error::MissingWorkflowRunId)
+    ));
+    assert!(builder.workflow_run_id(1234).build().is_ok());
+}
+
+/// Writes an archive with the given files using [`ZipWriter`].
+fn write_zip(level: u32, files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(level);
+    for (name, data) in files {
+        zip.start_file(name, SystemTime::now(), 0o100644).unwrap();
+        // Written in several chunks to cover the streaming compression
+        for chunk in data.chunks(1000) {
+            zip.write_file(chunk).unwrap();
+        }
+    }
+    zip.finish().unwrap();
+    zip.take_output()
+}
+
+fn check_zip(archive: Vec<u8>, files: &[(&str, &[u8])], method: CompressionMethod) {
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    assert_eq!(archive.len(), files.len());
+    for (name, data) in files {
+        let mut file = archive.by_name(name).unwrap();
+        assert_eq!(file.compression(), method);
+        assert_eq!(file.unix_mode(), Some(0o100644));
+        let mut contents = vec![];
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(&contents, data);
+    }
+}
+
+#[test]
+async fn zip_writer_stored() {
+    let data = b"Hello World!\n".repeat(1000);
+    let files: &[(&str, &[u8])] = &[("hello.txt", &data), ("dir/empty.txt", b"")];
+    check_zip(write_zip(0, files), files, CompressionMethod::Stored);
+}
+
+#[test]
+async fn zip_writer_deflated() {
+    let data = b"Hello World!\n".repeat(1000);
+    let files: &[(&str, &[u8])] = &[("hello.txt", &data), ("dir/empty.txt", b"")];
+    let archive = write_zip(6, files);
+    assert!(archive.len() < data.len());
+    check_zip(archive, files, CompressionMethod::Deflated);
+}
+
+/// Archive which doesn't store the data of large all-zero entries.
+struct SparseArchive {
+    segments: Vec<(u64, Vec<u8>)>,
+    len: u64,
+    pos: u64,
+}
+
+impl Read for SparseArchive {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let pos = self.pos;
+        let next = self
+            .segments
+            .iter()
+            .find(|(offset, data)| offset + data.len() as u64 > pos);
+        let n = match next {
+            Some((offset, data)) if *offset <= pos => {
+                let data = &data[(pos - offset) as usize..];
+                let n = data.len().min(buf.len());
+                buf[..n].copy_from_slice(&data[..n]);
+                n
+            }
+            next => {
+                let end = next.map_or(self.len, |(offset, _)| *offset);
+                let n = ((end - pos) as usize).min(buf.len());
+                buf[..n].fill(0);
+                n
+            }
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for SparseArchive {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = match pos {
+            SeekFrom::Start(pos) => pos,
+            SeekFrom::End(delta) => (self.len as i64 + delta) as u64,
+            SeekFrom::Current(delta) => (self.pos as i64 + delta) as u64,
+        };
+        Ok(self.pos)
+    }
+}
+
+#[test]
+async fn zip_writer_zip64() {
+    const CHUNK_SIZE: u64 = 1 << 20;
+    const LARGE_SIZE: u64 = (4 << 30) + CHUNK_SIZE;
+
+    let mut archive = SparseArchive {
+        segments: vec![],
+        len: 0,
+        pos: 0,
+    };
+    let mut zip = ZipWriter::new(0);
+    let mut take_output = |zip: &mut ZipWriter, store: bool| {
+        let output = zip.take_output();
+        if store {
+            archive.segments.push((archive.len, output.clone()));
+        }
+        archive.len += output.len() as u64;
+    };
+
+    zip.start_file("large.bin", SystemTime::now(), 0o100644)
+        .unwrap();
+    take_output(&mut zip, true);
+    let zeros = vec![0; CHUNK_SIZE as usize];
+    for _ in 0..LARGE_SIZE / CHUNK_SIZE {
+        zip.write_file(&zeros).unwrap();
+        // Stored data is output as is
+        take_output(&mut zip, false);
+    }
+    zip.start_file("small.txt", SystemTime::now(), 0o100644)
+        .unwrap();
+    zip.write_file(b"Hello World!").unwrap();
+    zip.finish().unwrap();
+    take_output(&mut zip, true);
+
+    let mut archive = ZipArchive::new(archive).unwrap();
+    assert_eq!(archive.len(), 2);
+    assert_eq!(archive.by_name("large.bin").unwrap().size(), LARGE_SIZE);
+
+    let mut small = archive.by_name("small.txt").unwrap();
+    assert!(small.header_start() > u32::MAX as u64);
+    let mut contents = String::new();
+    small.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "Hello World!");
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("gha-toolkit-artifact-{name}-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn sha256_digest(data: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(data)))
+}
+
+/// Creates a client for the results service at `base_url`.
+fn results_client(base_url: &str) -> ArtifactClientBuilder {
+    let payload = base64::encode_config(
+        r#"{"scp": "Actions.Results:run-id:job-id"}"#,
+        base64::URL_SAFE_NO_PAD,
+    );
+    ArtifactClient::builder(base_url, format!("e30.{payload}.c2ln"))
+}
+
+#[test]
+async fn upload_artifact() {
+    let root = temp_dir("upload");
+    let hello = b"Hello World!\n".repeat(20);
+    fs::write(root.join("hello.txt"), &hello).unwrap();
+    fs::create_dir(root.join("dir")).unwrap();
+    fs::write(root.join("dir").join("empty.txt"), b"").unwrap();
+
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let received = bodies.clone();
+    let (base_url, requests) = serve(move |request| {
+        received
+            .lock()
+            .unwrap()
+            .push((request.path.clone(), request.body.clone()));
+        let service = "/twirp/github.actions.results.api.v1.ArtifactService";
+        match request.path.as_str() {
+            path if path == format!("{service}/CreateArtifact") => {
+                let response = format!(
+                    r#"{{"ok":true,"signed_upload_url":"{}/upload?sig=signature"}}"#,
+                    request.base_url
+                );
+                ("200 OK", String::new(), response.into_bytes())
+            }
+            path if path == format!("{service}/FinalizeArtifact") => (
+                "200 OK",
+                String::new(),
+                br#"{"ok":true,"artifact_id":"42"}"#.to_vec(),
+            ),
+            "/upload?sig=signature&comp=blocklist" => {
+                assert!(request
+                    .headers
+                    .contains("x-ms-blob-content-type: application/zip"));
+                ("201 Created", String::new(), vec![])
+            }
+            path if path.starts_with("/upload?sig=signature&comp=block&blockid=") => {
+                ("201 Created", String::new(), vec![])
+            }
+            _ => ("404 Not Found", String::new(), vec![]),
+        }
+    });
+
+    let client = results_client(&base_url)
+        .compression_level(0)
+        .upload_chunk_size(100)
+        .upload_concurrency(1)
+        .build()
+        .unwrap();
+    let files = [root.join("hello.txt"), root.join("dir").join("empty.txt")];
+    let response = client
+        .upload_artifact("artifact", &files, &root, &Default::default())
+        .await
+        .unwrap();
+    let _ = fs::remove_dir_all(&root);
+
+    let bodies = bodies.lock().unwrap();
+    let (create, rest) = bodies.split_first().unwrap();
+    let (finalize, rest) = rest.split_last().unwrap();
+    let (commit, blocks) = rest.split_last().unwrap();
+
+    let create: serde_json::Value = serde_json::from_slice(&create.1).unwrap();
+    assert_eq!(create["workflow_run_backend_id"], "run-id");
+    assert_eq!(create["workflow_job_run_backend_id"], "job-id");
+    assert_eq!(create["name"], "artifact");
+    assert_eq!(create["version"], 4);
+
+    // Blocks are uploaded in order with base64 encoded IDs of equal length
+    let block_ids: Vec<_> = (0..blocks.len())
+        .map(|index| base64::encode(format!("{index:016x}")))
+        .collect();
+    let mut archive = vec![];
+    for ((path, body), block_id) in blocks.iter().zip(&block_ids) {
+        assert_eq!(
+            path,
+            &format!(
+                "/upload?sig=signature&comp=block&blockid={}",
+                block_id.replace('=', "%3D")
+            )
+        );
+        assert!(body.len() <= 100);
+        archive.extend_from_slice(body);
+    }
+    assert!(blocks.len() > 1);
+
+    let block_list: String = block_ids
+        .iter()
+        .map(|block_id| format!("<Latest>{block_id}</Latest>"))
+        .collect();
+    assert_eq!(
+        std::str::from_utf8(&commit.1).unwrap(),
+        format!(r#"<?xml version="1.0" encoding="utf-8"?><BlockList>{block_list}</BlockList>"#)
+    );
+
+    let digest = sha256_digest(&archive);
+    let finalize: serde_json::Value = serde_json::from_slice(&finalize.1).unwrap();
+    assert_eq!(finalize["workflow_run_backend_id"], "run-id");
+    assert_eq!(finalize["name"], "artifact");
+    assert_eq!(finalize["size"], archive.len().to_string());
+    assert_eq!(finalize["hash"], digest);
+    assert_eq!(
+        response,
+        UploadArtifactResponse {
+            id: 42,
+            size: archive.len() as u64,
+            digest: Some(digest),
+        }
+    );
+
+    let mut expected = vec![
+        "POST /twirp/github.actions.results.api.v1.ArtifactService/CreateArtifact HTTP/1.1"
+            .to_string(),
+    ];
+    expected.extend(
+        blocks
+            .iter()
+            .map(|(path, _)| format!("PUT {path} HTTP/1.1")),
+    );
+    expected.push("PUT /upload?sig=signature&comp=blocklist HTTP/1.1".to_string());
+    expected.push(
+        "POST /twirp/github.actions.results.api.v1.ArtifactService/FinalizeArtifact HTTP/1.1"
+            .to_string(),
+    );
+    assert_eq!(*requests.lock().unwrap(), expected);
+
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    assert_eq!(archive.len(), 2);
+    let mut contents = vec![];
+    archive
+        .by_name("hello.txt")
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    assert_eq!(contents, hello);
+    assert_eq!(archive.by_name("dir/empty.txt").unwrap().size(), 0);
+}
+
+/// Chunk uploaded to the pipelines API with its item path, content range
+/// and whether it's gzipped.
+type PipelinesChunk = (String, String, bool, Vec<u8>);
+
+#[test]
+async fn pipelines_artifact() {
+    let root = temp_dir("pipelines");
+    let hello = b"Hello World!\n".repeat(100);
+    let mut state = 0x1234_5678u32;
+    let random: Vec<u8> = (0..250)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    fs::create_dir(root.join("dir")).unwrap();
+    fs::write(root.join("dir").join("hello.txt"), &hello).unwrap();
+    fs::write(root.join("random.bin"), &random).unwrap();
+    fs::write(root.join("empty.txt"), b"").unwrap();
+
+    let chunks = Arc::new(Mutex::new(Vec::<PipelinesChunk>::new()));
+    let uploaded = chunks.clone();
+    let (base_url, requests) = serve(move |request| {
+        let artifacts = "/_apis/pipelines/workflows/1234/artifacts?api-version=6.0-preview";
+        let container = format!(
+            r#"{{"containerId":7,"size":0,"name":"artifact","fileContainerResourceUrl":"{}/resources/7"}}"#,
+            request.base_url
+        );
+        let mut chunks = uploaded.lock().unwrap();
+        let mut items: Vec<(String, bool)> = vec![];
+        for (item, _, gzip, _) in chunks.iter() {
+            if !items.iter().any(|(path, _)| path == item) {
+                items.push((item.clone(), *gzip));
+            }
+        }
+
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", path) if path == artifacts => {
+                let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                assert_eq!(
+                    body,
+                    serde_json::json!({"Type": "actions_storage", "Name": "artifact", "RetentionDays": 3})
+                );
+                ("201 Created", String::new(), container.into_bytes())
+            }
+            ("GET", path) if path == artifacts => {
+                let list = format!(r#"{{"count":1,"value":[{container}]}}"#);
+                ("200 OK", String::new(), list.into_bytes())
+            }
+            ("PATCH", path) if path == format!("{artifacts}&artifactName=artifact") => {
+                assert_eq!(request.text(), r#"{"Size":1550}"#);
+                ("200 OK", String::new(), container.into_bytes())
+            }
+            ("GET", "/resources/7?itemPath=artifact") => {
+                let mut list = vec![r#"{"path":"artifact/dir","itemType":"folder"}"#.to_string()];
+                list.extend(items.iter().enumerate().map(|(index, (path, _))| {
+                    format!(
+                        r#"{{"path":"{path}","itemType":"file","contentLocation":"{}/content/{index}"}}"#,
+                        request.base_url
+                    )
+                }));
+                let list = format!(r#"{{"count":{},"value":[{}]}}"#, list.len(), list.join(","));
+                ("200 OK", String::new(), list.into_bytes())
+            }
+            ("PUT", path) => {
+                let item = path.strip_prefix("/resources/7?itemPath=").unwrap();
+                let content_range = request
+                    .headers
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-range: "))
+                    .unwrap();
+                chunks.push((
+                    item.replace("%2F", "/"),
+                    content_range.to_string(),
+                    request.headers.contains("content-encoding: gzip"),
+                    request.body.clone(),
+                ));
+                ("200 OK", String::new(), b"{}".to_vec())
+            }
+            ("GET", path) if path.starts_with("/content/") => {
+                let (item, gzip) = &items[path["/content/".len()..].parse::<usize>().unwrap()];
+                let body: Vec<u8> = chunks
+                    .iter()
+                    .filter(|(path, ..)| path == item)
+                    .flat_map(|(.., body)| body.clone())
+                    .collect();
+                let headers = if *gzip {
+                    "content-encoding: gzip\r\n".to_string()
+                } else {
+                    String::new()
+                };
+                ("200 OK", headers, body)
+            }
+            _ => ("404 Not Found", String::new(), vec![]),
+        }
+    });
+
+    let client = ArtifactClient::builder(&base_url, "token")
+        .version(ServiceVersion::V3)
+        .workflow_run_id(1234)
+        .upload_chunk_size(100)
+        .upload_concurrency(1)
+        .build()
+        .unwrap();
+    let files = [
+        root.join("dir").join("hello.txt"),
+        root.join("random.bin"),
+        root.join("empty.txt"),
+    ];
+    let options = UploadArtifactOptions {
+        retention_days: Some(3),
+        ..Default::default()
+    };
+    let response = client
+        .upload_artifact("artifact", &files, &root, &options)
+        .await
+        .unwrap();
+    assert_eq!(
+        response,
+        UploadArtifactResponse {
+            id: 7,
+            size: 1550,
+            digest: None,
+        }
+    );
+
+    {
+        // Only the compressible file is gzipped, the others are uploaded as is
+        let chunks = chunks.lock().unwrap();
+        let ranges: Vec<_> = chunks
+            .iter()
+            .map(|(item, range, gzip, _)| (item.as_str(), range.as_str(), *gzip))
+            .collect();
+        let gzip_size = chunks[0].3.len();
+        let hello_range = format!("bytes 0-{}/{gzip_size}", gzip_size - 1);
+        assert_eq!(
+            ranges,
+            [
+                ("artifact/dir/hello.txt", hello_range.as_str(), true),
+                ("artifact/random.bin", "bytes 0-99/250", false),
+                ("artifact/random.bin", "bytes 100-199/250", false),
+                ("artifact/random.bin", "bytes 200-249/250", false),
+                ("artifact/empty.txt", "bytes 0--1/0", false),
+            ]
+        );
+    }
+
+    let dest = root.join("dest");
+    client.download_artifact(7, &dest, None).await.unwrap();
+    assert_eq!(fs::read(dest.join("dir").join("hello.txt")).unwrap(), hello);
+    assert_eq!(fs::read(dest.join("random.bin")).unwrap(), random);
+    assert_eq!(fs::read(dest.join("empty.txt")).unwrap(), b"");
+    let _ = fs::remove_dir_all(&root);
+
+    let artifacts = "/_apis/pipelines/workflows/1234/artifacts?api-version=6.0-preview";
+    assert_eq!(
+        *requests.lock().unwrap(),
+        [
+            format!("POST {artifacts} HTTP/1.1"),
+            "PUT /resources/7?itemPath=artifact%2Fdir%2Fhello.txt HTTP/1.1".to_string(),
+            "PUT /resources/7?itemPath=artifact%2Frandom.bin HTTP/1.1".to_string(),
+            "PUT /resources/7?itemPath=artifact%2Frandom.bin HTTP/1.1".to_string(),
+            "PUT /resources/7?itemPath=artifact%2Frandom.bin HTTP/1.1".to_string(),
+            "PUT /resources/7?itemPath=artifact%2Fempty.txt HTTP/1.1".to_string(),
+            format!("PATCH {artifacts}&artifactName=artifact HTTP/1.1"),
+            format!("GET {artifacts} HTTP/1.1"),
+            "GET /resources/7?itemPath=artifact HTTP/1.1".to_string(),
+            "GET /content/0 HTTP/1.1".to_string(),
+            "GET /content/1 HTTP/1.1".to_string(),
+            "GET /content/2 HTTP/1.1".to_string(),
+        ]
+    );
+}
+
+/// Downloads artifact 1 of `octo-org/octo-repo` into `dest` from a server
+/// returning `archive` with the given `digest`.
+async fn download(archive: Vec<u8>, digest: &str, dest: &Path) -> gha_toolkit::Result<()> {
+    let artifact = format!(
+        r#"{{"id":1,"name":"artifact","size_in_bytes":{},"digest":"{digest}"}}"#,
+        archive.len()
+    );
+    let (api_url, _) = serve(move |request| match request.path.as_str() {
+        "/repos/octo-org/octo-repo/actions/artifacts/1" => {
+            ("200 OK", String::new(), artifact.as_bytes().to_vec())
+        }
+        "/repos/octo-org/octo-repo/actions/artifacts/1/zip" => {
+            ("200 OK", String::new(), archive.clone())
+        }
+        _ => ("404 Not Found", String::new(), vec![]),
+    });
+
+    let client = results_client("https://results.example.com/")
+        .api_url(api_url)
+        .build()
+        .unwrap();
+    let find_by = FindBy::new("token", "octo-org", "octo-repo", 1234);
+    client.download_artifact(1, dest, Some(&find_by)).await
+}
+
+#[test]
+async fn download_artifact_escape() {
+    let root = temp_dir("escape");
+    let absolute = root.join("absolute-evil");
+
+    for (name, entry) in [
+        ("parent", "../evil"),
+        ("absolute", absolute.to_str().unwrap()),
+    ] {
+        let archive = write_zip(6, &[(entry, b"evil")]);
+        let digest = sha256_digest(&archive);
+        let dest = root.join(name).join("dest");
+        let result = download(archive, &digest, &dest).await;
+        assert!(
+            matches!(result, Err(Error::InvalidArtifactPath(_))),
+            "{name}: {result:?}"
+        );
+        assert!(!root.join(name).join("evil").exists());
+    }
+    assert!(!absolute.exists());
+
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+async fn download_artifact_digest() {
+    let root = temp_dir("digest");
+    let archive = write_zip(6, &[("hello.txt", b"Hello World!")]);
+    let digest = sha256_digest(&archive);
+
+    let dest = root.join("valid");
+    download(archive, &digest, &dest).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(dest.join("hello.txt")).unwrap(),
+        "Hello World!"
+    );
+
+    // A valid archive with other contents than the digested one
+    let tampered = write_zip(6, &[("hello.txt", b"Hello Mallory!")]);
+    let result = download(tampered.clone(), &digest, &root.join("tampered")).await;
+    match result {
+        Err(Error::ArtifactDigestMismatch { expected, actual }) => {
+            assert_eq!(expected, digest);
+            assert_eq!(actual, sha256_digest(&tampered));
+        }
+        result => panic!("unexpected result {result:?}"),
+    }
+
+    let _ = fs::remove_dir_all(&root);
+}
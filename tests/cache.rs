@@ -1,10 +1,68 @@
-use gha_toolkit::cache::{CacheClient, CacheClientBuilder};
+mod common;
 
+use gha_toolkit::cache::{BackoffPolicy, CacheClient, CacheClientBuilder, RetryPolicy};
+use gha_toolkit::Error;
+
+use std::env;
+use std::fs;
 use std::io;
-use std::time::SystemTime;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use http::StatusCode;
 
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use task_local_extensions::Extensions;
 use tokio::test;
 
+use self::common::serve;
+
+const CA_BUNDLE: &str = "\
+# root 1
+-----BEGIN CERTIFICATE-----
+MIIBpTCCAUugAwIBAgIUVyQ7BiBz8Dmt9B6p95YNmGWB2YkwCgYIKoZIzj0EAwIw
+JzEUMBIGA1UECgwLZ2hhLXRvb2xraXQxDzANBgNVBAMMBnJvb3QgMTAgFw0yNjEw
+MTgxNDExMTBaGA8yMTI2MDkyNDE0MTExMFowJzEUMBIGA1UECgwLZ2hhLXRvb2xr
+aXQxDzANBgNVBAMMBnJvb3QgMTBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABBDu
+s9mPuSbWFasp2msFK2zkTiX3jwfvPFVfhRpx2e/FisAbC0jUMicykUI4BUanV8ON
+QYsIV+FB+ykj8bHrwcyjUzBRMB0GA1UdDgQWBBR1JPw+/QlDchDSMT9PtwZUeIoV
+YDAfBgNVHSMEGDAWgBR1JPw+/QlDchDSMT9PtwZUeIoVYDAPBgNVHRMBAf8EBTAD
+AQH/MAoGCCqGSM49BAMCA0gAMEUCIQCL4rHFBwVwPOSvAT1GMlB+h5gUjVmA1O0y
+LKg+4Rqk8gIgOpU/0c5q5D95lHYRuGq11UuF/LtjF9KkFLKoShnG+G0=
+-----END CERTIFICATE-----
+
+# root 2
+-----BEGIN CERTIFICATE-----
+MIIBpTCCAUugAwIBAgIUc3c9WmEEWociR3QU7ghjVb68ULEwCgYIKoZIzj0EAwIw
+JzEUMBIGA1UECgwLZ2hhLXRvb2xraXQxDzANBgNVBAMMBnJvb3QgMjAgFw0yNjEw
+MTgxNDExMTBaGA8yMTI2MDkyNDE0MTExMFowJzEUMBIGA1UECgwLZ2hhLXRvb2xr
+aXQxDzANBgNVBAMMBnJvb3QgMjBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABE40
+CP6HNPQ+W1QC7I0Ls541yQ6qZ5RIS6FD0FDk/XaKL+9Be19ttv0ZAMoWKf7/3vAO
+wi1sB6A9bsWD8ZSpcGSjUzBRMB0GA1UdDgQWBBSBUJ6W5KJticMN7P6Cn0zPbb4H
+cjAfBgNVHSMEGDAWgBSBUJ6W5KJticMN7P6Cn0zPbb4HcjAPBgNVHRMBAf8EBTAD
+AQH/MAoGCCqGSM49BAMCA0gAMEUCIGvP4poDHt9aCjuhWHVDfLNu/DB3udSOOE/W
+/lKFL6daAiEAoxp6P7sFPrdcdcX+yuWCMFvaxhufqB9Y2UPYNZ1vu3E=
+-----END CERTIFICATE-----
+";
+
+struct CountRequests(Arc<AtomicUsize>);
+
+#[async_trait::async_trait]
+impl Middleware for CountRequests {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        next.run(req, extensions).await
+    }
+}
+
 #[test]
 async fn builder() {
     assert!(CacheClient::builder("http://localhost", "token")
@@ -26,6 +84,219 @@ async fn builder() {
         .cache_from(["key"].into_iter())
         .build()
         .is_ok());
+
+    // Retry intervals are deterministic unless jitter is enabled
+    assert!(!CacheClient::builder("http://localhost", "token").retry_jitter);
+}
+
+struct RetryAll;
+
+impl RetryPolicy for RetryAll {
+    fn retry_interval(&self, _: &Error, retries: u32, _: Duration) -> Option<Duration> {
+        (retries < 3).then(|| Duration::ZERO)
+    }
+}
+
+#[test]
+async fn builder_eq() {
+    let builder = CacheClient::builder("http://localhost", "token").cache_to("key");
+    assert_eq!(builder, builder.clone());
+    assert_ne!(builder, builder.clone().cache_to("other"));
+
+    let builder = builder.middleware(CountRequests(Default::default()));
+    assert_eq!(builder, builder.clone());
+    assert_ne!(
+        builder,
+        builder
+            .clone()
+            .middleware(CountRequests(Default::default()))
+    );
+}
+
+#[test]
+async fn transport() {
+    let (base_url, _) = serve(|_| ("204 No Content", String::new(), ""));
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    let client = CacheClient::builder(&base_url, "token")
+        .cache_from(["key"].into_iter())
+        .client(reqwest::Client::builder().no_proxy().build().unwrap())
+        .middleware(CountRequests(requests.clone()))
+        .build()
+        .unwrap();
+    assert!(client.entry("transport").await.unwrap().is_none());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    let result = CacheClient::builder(&base_url, "token")
+        .cache_from(["key"].into_iter())
+        .ca_bundle("does-not-exist.pem")
+        .build();
+    assert!(matches!(result, Err(Error::IO(_))));
+
+    let ca_bundle = env::temp_dir().join(format!("gha-toolkit-ca-{}.pem", process::id()));
+    let build = |pem_bundle: &str| {
+        fs::write(&ca_bundle, pem_bundle).unwrap();
+        CacheClient::builder(&base_url, "token")
+            .cache_from(["key"].into_iter())
+            .ca_bundle(&ca_bundle)
+            .build()
+    };
+    assert!(build(CA_BUNDLE).is_ok());
+    // Every certificate of the bundle is parsed
+    let corrupted = CA_BUNDLE.replace("MIIBpTCCAUugAwIBAgIUc3c9", "MIIBpTCCAUugAwIBAgIU____");
+    assert!(matches!(build(&corrupted), Err(Error::Reqwest(_))));
+    fs::remove_file(&ca_bundle).unwrap();
+}
+
+#[test]
+async fn backoff_policy() {
+    let policy = BackoffPolicy {
+        max_retries: 2,
+        min_retry_interval: Duration::from_millis(100),
+        max_retry_interval: Duration::from_secs(1),
+        backoff_factor_base: 3,
+        jitter: false,
+        time_budget: Some(Duration::from_secs(1)),
+    };
+    let checksum = Error::CacheChunkChecksum;
+    assert_eq!(
+        policy.retry_interval(&checksum, 0, Duration::ZERO),
+        Some(Duration::from_millis(100))
+    );
+    assert_eq!(
+        policy.retry_interval(&checksum, 1, Duration::ZERO),
+        Some(Duration::from_millis(300))
+    );
+    assert_eq!(policy.retry_interval(&checksum, 2, Duration::ZERO), None);
+    assert_eq!(
+        policy.retry_interval(&checksum, 1, Duration::from_millis(800)),
+        None
+    );
+
+    let status = |status| Error::CacheServiceStatus {
+        status,
+        message: String::new(),
+    };
+    let interval = |err: &Error| policy.retry_interval(err, 0, Duration::ZERO);
+    assert!(interval(&status(StatusCode::TOO_MANY_REQUESTS)).is_some());
+    assert!(interval(&status(StatusCode::BAD_GATEWAY)).is_some());
+    assert!(interval(&status(StatusCode::FORBIDDEN)).is_none());
+    assert!(interval(&Error::MissingKey).is_none());
+
+    let policy = BackoffPolicy {
+        jitter: true,
+        ..policy
+    };
+    for _ in 0..100 {
+        let interval = policy.retry_interval(&checksum, 0, Duration::ZERO).unwrap();
+        assert!(interval >= Duration::from_millis(100));
+        assert!(interval < Duration::from_millis(200));
+    }
+}
+
+/// Serves cache entries whose first archive URL is expired.
+fn serve_expiring_archive() -> (String, Arc<Mutex<Vec<String>>>) {
+    let entries = AtomicUsize::new(0);
+    serve(move |request| {
+        let body = if request.path.starts_with("/_apis/artifactcache/cache?") {
+            let n = entries.fetch_add(1, Ordering::SeqCst) + 1;
+            format!(
+                r#"{{"archiveLocation":"{}/archive/{n}"}}"#,
+                request.base_url
+            )
+        } else if request.path == "/archive/1" {
+            return ("403 Forbidden", String::new(), "expired".to_string());
+        } else {
+            "Hello World!".to_string()
+        };
+        ("200 OK", String::new(), body)
+    })
+}
+
+fn archive_requests(requests: &Mutex<Vec<String>>) -> Vec<String> {
+    requests
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|request| request.starts_with("GET /archive/"))
+        .cloned()
+        .collect()
+}
+
+#[test]
+async fn get_entry_refreshes_url() {
+    let (base_url, requests) = serve_expiring_archive();
+
+    let client = CacheClient::builder(&base_url, "token")
+        .cache_from(["key"].into_iter())
+        .retry_policy(RetryAll)
+        .build()
+        .unwrap();
+    let data = client.get_entry("get_entry").await.unwrap().unwrap();
+    assert_eq!(data, b"Hello World!");
+
+    assert_eq!(
+        archive_requests(&requests),
+        ["GET /archive/1 HTTP/1.1", "GET /archive/2 HTTP/1.1"]
+    );
+}
+
+#[test]
+async fn get_entry_default_policy() {
+    let (base_url, requests) = serve_expiring_archive();
+
+    let client = CacheClient::builder(&base_url, "token")
+        .cache_from(["key"].into_iter())
+        .build()
+        .unwrap();
+    let result = client.get_entry("get_entry").await;
+    assert!(
+        matches!(
+            result,
+            Err(Error::CacheServiceStatus {
+                status: StatusCode::FORBIDDEN,
+                ..
+            })
+        ),
+        "unexpected result {result:?}"
+    );
+
+    assert_eq!(archive_requests(&requests), ["GET /archive/1 HTTP/1.1"]);
+}
+
+#[test]
+async fn reserve_not_retried() {
+    // The reservation may have been made before the server error
+    let (base_url, requests) = serve(|_| {
+        (
+            "500 Internal Server Error",
+            String::new(),
+            "reserved".to_string(),
+        )
+    });
+
+    let client = CacheClient::builder(&base_url, "token")
+        .cache_to("key")
+        .max_retries(0)
+        .retry_policy(RetryAll)
+        .build()
+        .unwrap();
+    let result = client.put("reserve", io::Cursor::new("Hello World!")).await;
+    assert!(
+        matches!(
+            result,
+            Err(Error::CacheServiceStatus {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                ..
+            })
+        ),
+        "unexpected result {result:?}"
+    );
+
+    assert_eq!(
+        *requests.lock().unwrap(),
+        ["POST /_apis/artifactcache/caches HTTP/1.1"]
+    );
 }
 
 #[test]
@@ -0,0 +1,88 @@
+//! HTTP server fixture shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Request received by [`serve`].
+pub struct Request {
+    pub base_url: String,
+    pub method: String,
+    pub path: String,
+    pub headers: String,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Gets the body as UTF-8 text.
+    pub fn text(&self) -> &str {
+        std::str::from_utf8(&self.body).unwrap()
+    }
+}
+
+/// Serves HTTP requests with `handler` returning the status, headers and
+/// body of the response. Returns the base URL and the received request
+/// lines.
+pub fn serve<F, B>(handler: F) -> (String, Arc<Mutex<Vec<String>>>)
+where
+    F: Fn(&Request) -> (&'static str, String, B) + Send + 'static,
+    B: AsRef<[u8]>,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let received = requests.clone();
+    let server_url = base_url.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut headers = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.to_lowercase();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some(length) = line.strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                headers.push_str(&line);
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let request_line = request_line.trim().to_string();
+            let mut parts = request_line.split(' ');
+            let request = Request {
+                base_url: server_url.clone(),
+                method: parts.next().unwrap().to_string(),
+                path: parts.next().unwrap().to_string(),
+                headers,
+                body,
+            };
+            received.lock().unwrap().push(request_line);
+
+            let (status, headers, body) = handler(&request);
+            let body = body.as_ref();
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nconnection: close\r\ncontent-length: {}\r\n{headers}\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(body).unwrap();
+        }
+    });
+
+    (base_url, requests)
+}
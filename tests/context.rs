@@ -0,0 +1,91 @@
+use gha_toolkit::context::Event;
+
+use serde_json::json;
+
+#[test]
+fn pull_request_event() {
+    let payload = json!({
+        "action": "opened",
+        "number": 42,
+        "pull_request": {
+            "number": 42,
+            "title": "Add context",
+            "head": { "ref": "feature", "sha": "abc123" },
+            "base": { "ref": "main", "sha": "def456" },
+            "labels": [{ "name": "enhancement" }]
+        },
+        "repository": {
+            "name": "gha-toolkit",
+            "full_name": "BitskiCo/gha-toolkit",
+            "owner": { "login": "BitskiCo" }
+        }
+    });
+
+    let event = Event::parse("pull_request", payload).unwrap();
+    let event = match event {
+        Event::PullRequest(event) => event,
+        event => panic!("unexpected event {event:?}"),
+    };
+
+    assert_eq!(event.number, 42);
+    assert_eq!(event.pull_request.head.ref_, "feature");
+    assert_eq!(event.pull_request.labels[0].name, "enhancement");
+    assert_eq!(event.repository.unwrap().full_name, "BitskiCo/gha-toolkit");
+}
+
+#[test]
+fn issue_comment_event() {
+    let payload = json!({
+        "action": "created",
+        "issue": {
+            "number": 7,
+            "title": "Add sticky comments",
+            "pull_request": { "url": "https://api.github.com/repos/BitskiCo/gha-toolkit/pulls/7" }
+        },
+        "comment": { "id": 1, "body": "/bench" }
+    });
+
+    let event = Event::parse("issue_comment", payload).unwrap();
+    assert_eq!(event.issue_number(), Some(7));
+    let event = match event {
+        Event::IssueComment(event) => event,
+        event => panic!("unexpected event {event:?}"),
+    };
+
+    assert_eq!(event.comment.body, "/bench");
+    assert!(event.issue.pull_request.is_some());
+}
+
+#[test]
+fn unknown_event() {
+    let payload = json!({ "action": "created", "comment": { "id": 1 } });
+
+    let event = Event::parse("discussion_comment", payload.clone()).unwrap();
+    assert_eq!(event, Event::Other(payload));
+    assert!(event.repository().is_none());
+}
+
+#[test]
+fn workflow_dispatch_event() {
+    let payload = json!({
+        "inputs": null,
+        "ref": "refs/heads/main",
+        "workflow": ".github/workflows/release.yml",
+    });
+
+    let event = Event::parse("workflow_dispatch", payload).unwrap();
+    let event = match event {
+        Event::WorkflowDispatch(event) => event,
+        event => panic!("unexpected event {event:?}"),
+    };
+    assert!(event.inputs.is_empty());
+    assert_eq!(event.ref_, "refs/heads/main");
+
+    let payload = json!({ "inputs": { "dry_run": true } });
+    let event = Event::parse("workflow_dispatch", payload).unwrap();
+    let event = match event {
+        Event::WorkflowDispatch(event) => event,
+        event => panic!("unexpected event {event:?}"),
+    };
+    assert_eq!(event.inputs["dry_run"], true);
+}
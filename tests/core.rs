@@ -0,0 +1,129 @@
+use gha_toolkit::cache::CacheClient;
+use gha_toolkit::core::{secrets, ActionsLayer, Annotation, AnnotationLevel, Command, IdToken};
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use tracing_subscriber::prelude::*;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn command() {
+    assert_eq!(Command::new("debug", "").to_string(), "::debug::");
+
+    assert_eq!(
+        Command::new("warning", "100%\r\ndone")
+            .property("title", "a:b,c")
+            .to_string(),
+        "::warning title=a%3Ab%2Cc::100%25%0D%0Adone"
+    );
+
+    let annotation = Annotation::new(AnnotationLevel::Error, "failed")
+        .file("src/lib.rs")
+        .lines(1, 2);
+    assert_eq!(
+        annotation.command().to_string(),
+        "::error file=src/lib.rs,line=1,endLine=2::failed"
+    );
+}
+
+#[test]
+fn layer() {
+    let buffer = Buffer::default();
+    let layer = ActionsLayer::with_writer(buffer.clone()).debug(false);
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        let span = tracing::info_span!("restore");
+        let _guard = span.enter();
+
+        tracing::info!(key = "abc", "Restoring");
+        tracing::debug!("hidden");
+        tracing::warn!("Cache miss");
+
+        let nested = tracing::info_span!("nested");
+        let _nested = nested.enter();
+    });
+
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines[0], "::group::restore");
+    assert_eq!(lines[1], "Restoring key=abc");
+    assert!(lines[2].starts_with("::warning file=tests"));
+    assert!(lines[2].ends_with("::Cache miss"));
+    assert_eq!(lines[3], "::endgroup::");
+    assert_eq!(lines.len(), 4);
+}
+
+#[test]
+fn secrets() {
+    secrets::register("first-secret\nsecond-secret\n");
+
+    assert!(secrets::is_registered("first-secret"));
+    assert!(secrets::is_registered("second-secret"));
+    assert_eq!(
+        secrets::redact("first-secret and second-secret"),
+        "*** and ***"
+    );
+
+    let builder = CacheClient::builder("http://localhost/first-secret", "token-secret");
+    let debug = format!("{builder:?}");
+    assert!(!debug.contains("first-secret"));
+    assert!(!debug.contains("token-secret"));
+}
+
+#[test]
+fn id_token() {
+    let payload = base64::encode_config(
+        r#"{
+            "iss": "https://token.actions.githubusercontent.com",
+            "sub": "repo:octo-org/octo-repo:ref:refs/heads/main",
+            "aud": "sts.amazonaws.com",
+            "exp": 1632493867,
+            "iat": 1632493567,
+            "ref": "refs/heads/main",
+            "repository": "octo-org/octo-repo",
+            "job_workflow_ref": "octo-org/octo-repo/.github/workflows/ci.yml@refs/heads/main",
+            "custom": "claim"
+        }"#,
+        base64::URL_SAFE_NO_PAD,
+    );
+    let token = IdToken::parse(format!("e30.{payload}.c2ln")).unwrap();
+
+    assert_eq!(token.claims.aud, ["sts.amazonaws.com"]);
+    assert_eq!(token.claims.ref_.as_deref(), Some("refs/heads/main"));
+    assert_eq!(
+        token.claims.repository.as_deref(),
+        Some("octo-org/octo-repo")
+    );
+    assert_eq!(token.claims.other["custom"], "claim");
+    assert!(!format!("{token:?}").contains(&payload));
+
+    assert!(IdToken::parse("not-a-jwt").is_err());
+}
+
+#[test]
+fn save_state() {
+    let path = std::env::temp_dir().join(format!("gha-toolkit-state-{}", std::process::id()));
+    std::env::set_var("GITHUB_STATE", &path);
+
+    gha_toolkit::core::save_state("key", "first\nsecond").unwrap();
+
+    let state = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let lines: Vec<_> = state.lines().collect();
+    let delimiter = lines[0].strip_prefix("key<<").unwrap();
+    assert_eq!(lines[1..], ["first", "second", delimiter]);
+}
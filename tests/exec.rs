@@ -0,0 +1,104 @@
+use std::sync::{Arc, Mutex};
+
+use gha_toolkit::exec::{self, args_string_to_array, ExecListeners, ExecOptions};
+use gha_toolkit::Error;
+use tokio::test;
+
+#[test]
+async fn args_string() {
+    let cases: &[(&str, &[&str])] = &[
+        ("", &[]),
+        ("  ", &[]),
+        ("foo bar", &["foo", "bar"]),
+        ("  foo   bar  ", &["foo", "bar"]),
+        (r#""foo bar" baz"#, &["foo bar", "baz"]),
+        (r#"foo"bar baz"qux"#, &["foobar bazqux"]),
+        (r#""foo \"bar\"""#, &[r#"foo "bar""#]),
+        (r#""foo\\bar""#, &[r"foo\\bar"]),
+        (r#""foo\bar""#, &[r"foo\bar"]),
+        (r"foo\bar", &[r"foo\bar"]),
+        (r#"-m "multi word message""#, &["-m", "multi word message"]),
+    ];
+    for (args, expected) in cases {
+        assert_eq!(args_string_to_array(args), *expected, "{args}");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+async fn exec_output() {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let errlines = Arc::new(Mutex::new(Vec::new()));
+    let options = ExecOptions {
+        silent: true,
+        input: Some(b"input\n".to_vec()),
+        listeners: ExecListeners {
+            stdline: Some(Box::new({
+                let lines = lines.clone();
+                move |line| lines.lock().unwrap().push(line.to_string())
+            })),
+            errline: Some(Box::new({
+                let errlines = errlines.clone();
+                move |line| errlines.lock().unwrap().push(line.to_string())
+            })),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let output = exec::get_exec_output(
+        "sh -c",
+        &["cat; printf 'a\\r\\nb'; echo error >&2"],
+        &options,
+    )
+    .await
+    .unwrap();
+    assert_eq!(output.exit_code, 0);
+    assert_eq!(output.stdout, "input\na\r\nb");
+    assert_eq!(output.stderr, "error\n");
+    assert_eq!(*lines.lock().unwrap(), ["input", "a", "b"]);
+    assert_eq!(*errlines.lock().unwrap(), ["error"]);
+}
+
+#[cfg(unix)]
+#[test]
+async fn exec_unread_input() {
+    // More input than fits into the pipe of a process exiting without reading it
+    let options = ExecOptions {
+        silent: true,
+        ignore_return_code: true,
+        input: Some(vec![b'a'; 1 << 20]),
+        ..Default::default()
+    };
+    let output = exec::get_exec_output("sh -c", &["echo done; exit 2"], &options)
+        .await
+        .unwrap();
+    assert_eq!(output.exit_code, 2);
+    assert_eq!(output.stdout, "done\n");
+}
+
+#[cfg(unix)]
+#[test]
+async fn exec_failure() {
+    let options = ExecOptions {
+        silent: true,
+        ..Default::default()
+    };
+    let result = exec::exec("sh -c", &["exit 3"], &options).await;
+    assert!(matches!(result, Err(Error::ExecExitStatus { .. })));
+
+    let options = ExecOptions {
+        silent: true,
+        ignore_return_code: true,
+        ..Default::default()
+    };
+    assert_eq!(exec::exec("sh -c", &["exit 3"], &options).await.unwrap(), 3);
+
+    let options = ExecOptions {
+        silent: true,
+        fail_on_stderr: true,
+        ..Default::default()
+    };
+    let result = exec::exec("sh -c", &["echo error >&2"], &options).await;
+    assert!(matches!(result, Err(Error::ExecStderr(_))));
+}
@@ -0,0 +1,179 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use gha_toolkit::glob::{self, GlobOptions, Globber};
+use gha_toolkit::Error;
+use tokio::test;
+
+fn create_tree(name: &str) -> PathBuf {
+    let root = env::temp_dir().join(format!("gha-toolkit-glob-{name}-{}", process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["a/b/c", "a/d", ".hidden"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["a/b/c/file.txt", "a/b/file.rs", "a/d/file.txt", "a/x.txt"] {
+        fs::write(root.join(file), file).unwrap();
+    }
+    fs::write(root.join(".hidden/file.txt"), "hidden").unwrap();
+    root
+}
+
+async fn glob_paths(patterns: &str, options: &GlobOptions) -> Vec<PathBuf> {
+    Globber::create(patterns, options)
+        .unwrap()
+        .glob()
+        .await
+        .unwrap()
+}
+
+#[test]
+async fn glob_patterns() {
+    let root = create_tree("patterns");
+    let root_str = root.to_str().unwrap();
+    let options = GlobOptions::default();
+
+    let files = glob_paths(&format!("{root_str}/**/*.txt"), &options).await;
+    assert_eq!(
+        files,
+        [
+            root.join(".hidden/file.txt"),
+            root.join("a/b/c/file.txt"),
+            root.join("a/d/file.txt"),
+            root.join("a/x.txt"),
+        ]
+    );
+
+    let patterns = format!(
+        "
+        # Comment
+        {root_str}/a/**/*.txt
+        !{root_str}/a/b
+        "
+    );
+    let files = glob_paths(&patterns, &options).await;
+    assert_eq!(files, [root.join("a/d/file.txt"), root.join("a/x.txt")]);
+
+    // Implicit descendants
+    let files = glob_paths(&format!("{root_str}/a/b"), &options).await;
+    assert_eq!(
+        files,
+        [
+            root.join("a/b"),
+            root.join("a/b/c"),
+            root.join("a/b/c/file.txt"),
+            root.join("a/b/file.rs"),
+        ]
+    );
+
+    let options = GlobOptions {
+        implicit_descendants: false,
+        ..Default::default()
+    };
+    let files = glob_paths(&format!("{root_str}/a/b"), &options).await;
+    assert_eq!(files, [root.join("a/b")]);
+
+    // Trailing separators only match directories
+    let files = glob_paths(&format!("{root_str}/a/*/"), &options).await;
+    assert_eq!(files, [root.join("a/b"), root.join("a/d")]);
+
+    let options = GlobOptions {
+        match_directories: false,
+        exclude_hidden_files: true,
+        ..Default::default()
+    };
+    let files = glob_paths(&format!("{root_str}/*/?/file.*"), &options).await;
+    assert_eq!(files, [root.join("a/b/file.rs"), root.join("a/d/file.txt")]);
+
+    let files = glob_paths(&format!("{root_str}/a/[a-c]/**"), &options).await;
+    assert_eq!(
+        files,
+        [root.join("a/b/c/file.txt"), root.join("a/b/file.rs")]
+    );
+
+    let files = glob_paths(&format!("{root_str}/missing/**"), &options).await;
+    assert!(files.is_empty());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+async fn search_paths() {
+    let root = create_tree("search-paths");
+    let root_str = root.to_str().unwrap();
+
+    let patterns = format!("{root_str}/a/b/**\n{root_str}/a/*.txt\n!{root_str}/c");
+    let globber = Globber::create(&patterns, &GlobOptions::default()).unwrap();
+    assert_eq!(globber.search_paths(), [root.join("a")]);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+async fn symbolic_links() {
+    let root = create_tree("symlinks");
+    let root_str = root.to_str().unwrap();
+    std::os::unix::fs::symlink(root.join("a"), root.join("a/d/cycle")).unwrap();
+    std::os::unix::fs::symlink(root.join("missing"), root.join("a/broken")).unwrap();
+
+    let options = GlobOptions {
+        match_directories: false,
+        ..Default::default()
+    };
+    let files = glob_paths(&format!("{root_str}/a/**/file.txt"), &options).await;
+    assert_eq!(
+        files,
+        [root.join("a/b/c/file.txt"), root.join("a/d/file.txt")]
+    );
+
+    let options = GlobOptions {
+        omit_broken_symbolic_links: false,
+        ..Default::default()
+    };
+    let globber = Globber::create(&format!("{root_str}/a/*"), &options).unwrap();
+    assert!(globber.glob().await.is_err());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+async fn invalid_pattern() {
+    let result = Globber::create("a/../b", &GlobOptions::default());
+    assert!(matches!(result, Err(Error::InvalidGlobPattern { .. })));
+}
+
+#[test]
+async fn hash_files() {
+    let root = create_tree("hash-files");
+    let root_str = root.to_str().unwrap();
+    env::set_var("GITHUB_WORKSPACE", root.join("a"));
+
+    // sha256(sha256("a/b/c/file.txt") + sha256("a/d/file.txt"))
+    let hash = glob::hash_files(&format!("{root_str}/**/*.txt\n!{root_str}/a/x.txt"))
+        .await
+        .unwrap();
+    assert_eq!(
+        hash,
+        "af2a272cd186a8b2249b08f2d137eb0242c860b95c95fc99f3f6c3c5ebad1880"
+    );
+
+    let hash = glob::hash_files(&format!("{root_str}/.hidden/*"))
+        .await
+        .unwrap();
+    assert_eq!(hash, "");
+
+    // Relative to the workspace, without following the linked directory
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(root.join("a/b"), root.join("a/link")).unwrap();
+        let hash = glob::hash_files("**/*.txt\n!x.txt").await.unwrap();
+        assert_eq!(
+            hash,
+            "af2a272cd186a8b2249b08f2d137eb0242c860b95c95fc99f3f6c3c5ebad1880"
+        );
+    }
+
+    fs::remove_dir_all(root).unwrap();
+}
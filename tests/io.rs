@@ -0,0 +1,93 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use gha_toolkit::io::{self, CopyOptions, MoveOptions};
+use tokio::test;
+
+#[test]
+async fn cp_mv_rm_rf() {
+    let root = env::temp_dir().join(format!("gha-toolkit-io-{}", process::id()));
+    let source = root.join("source");
+    io::mkdir_p(source.join("nested")).await.unwrap();
+    fs::write(source.join("nested/file"), "file").unwrap();
+
+    let options = CopyOptions::default();
+    assert!(io::cp(&source, root.join("copy"), &options).await.is_err());
+
+    let options = CopyOptions {
+        recursive: true,
+        ..Default::default()
+    };
+    io::cp(&source, root.join("copy"), &options).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(root.join("copy/nested/file")).unwrap(),
+        "file"
+    );
+
+    // Copied into the existing directory
+    io::cp(&source, root.join("copy"), &options).await.unwrap();
+    assert!(root.join("copy/source/nested/file").is_file());
+
+    let options = CopyOptions {
+        recursive: true,
+        copy_source_directory: false,
+        ..Default::default()
+    };
+    fs::write(source.join("nested/file"), "changed").unwrap();
+    io::cp(&source, root.join("copy"), &options).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(root.join("copy/nested/file")).unwrap(),
+        "changed"
+    );
+
+    let options = CopyOptions {
+        force: false,
+        ..Default::default()
+    };
+    fs::write(source.join("nested/file"), "not copied").unwrap();
+    io::cp(
+        source.join("nested/file"),
+        root.join("copy/nested"),
+        &options,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(root.join("copy/nested/file")).unwrap(),
+        "changed"
+    );
+
+    let options = MoveOptions { force: false };
+    assert!(
+        io::mv(root.join("copy/nested"), root.join("copy/source"), &options)
+            .await
+            .is_err()
+    );
+    io::mv(root.join("copy/nested"), root.join("moved"), &options)
+        .await
+        .unwrap();
+    assert!(root.join("moved/file").is_file());
+    assert!(!root.join("copy/nested").exists());
+
+    let mut permissions = fs::metadata(root.join("moved/file")).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(root.join("moved/file"), permissions).unwrap();
+    io::rm_rf(&root).await.unwrap();
+    assert!(!root.exists());
+    io::rm_rf(&root).await.unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+async fn which() {
+    let sh = io::which("sh", true).await.unwrap().unwrap();
+    assert!(sh.is_absolute());
+    assert_eq!(
+        io::which(sh.to_str().unwrap(), false).await.unwrap(),
+        Some(sh)
+    );
+
+    assert_eq!(io::which("gha-toolkit-missing", false).await.unwrap(), None);
+    assert!(io::which("gha-toolkit-missing", true).await.is_err());
+}
@@ -0,0 +1,135 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use gha_toolkit::tool_cache;
+
+#[test]
+fn cache_and_find() {
+    let root = env::temp_dir().join(format!("gha-toolkit-tool-cache-{}", process::id()));
+    let source = root.join("source");
+    fs::create_dir_all(source.join("bin")).unwrap();
+    fs::write(source.join("bin/tool"), "#!/bin/sh\n").unwrap();
+    env::set_var("RUNNER_TOOL_CACHE", root.join("cache"));
+
+    for version in ["1.2.3", "v1.3.0", "2.0.0-beta.1"] {
+        let dir = tool_cache::cache_dir(&source, "tool", version, Some("x64")).unwrap();
+        assert!(dir.join("bin/tool").is_file());
+    }
+    tool_cache::cache_file(
+        source.join("bin/tool"),
+        "tool",
+        "other",
+        "0.1.0",
+        Some("x64"),
+    )
+    .unwrap();
+
+    assert_eq!(
+        tool_cache::find_all_versions("tool", Some("x64")),
+        ["1.2.3", "1.3.0", "2.0.0-beta.1"]
+    );
+    assert!(tool_cache::find_all_versions("tool", Some("arm64")).is_empty());
+
+    let find = |spec| {
+        tool_cache::find("tool", spec, Some("x64"))
+            .map(|dir| dir.parent().unwrap().file_name().unwrap().to_owned())
+    };
+    assert_eq!(find("1.2.3").unwrap(), "1.2.3");
+    assert_eq!(find("1.x").unwrap(), "1.3.0");
+    assert_eq!(find(">= 1.2 <1.3").unwrap(), "1.2.3");
+    assert_eq!(find("1.2 - 1.2.9 || ^3").unwrap(), "1.2.3");
+    assert_eq!(find("2.0.0-beta.1").unwrap(), "2.0.0-beta.1");
+    assert_eq!(find("^2"), None);
+    assert_eq!(find("1.4.0"), None);
+    assert!(tool_cache::find("other", "0.1", Some("x64"))
+        .unwrap()
+        .join("tool")
+        .is_file());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn find_from_manifest() {
+    let path = env::temp_dir().join(format!("gha-toolkit-manifest-{}.json", process::id()));
+    fs::write(
+        &path,
+        r#"[
+            {
+                "version": "3.12.0-alpha.1",
+                "stable": false,
+                "release_url": "https://github.com/actions/python-versions/releases/tag/3.12.0-alpha.1",
+                "files": [
+                    {
+                        "filename": "python-3.12.0-alpha.1-linux-x64.tar.gz",
+                        "arch": "x64",
+                        "platform": "linux",
+                        "download_url": "https://example.com/python-3.12.0-alpha.1-linux-x64.tar.gz"
+                    }
+                ]
+            },
+            {
+                "version": "3.11.1",
+                "stable": true,
+                "release_url": "https://github.com/actions/python-versions/releases/tag/3.11.1",
+                "files": [
+                    {
+                        "filename": "python-3.11.1-linux-never-x64.tar.gz",
+                        "arch": "x64",
+                        "platform": "linux",
+                        "platform_version": "<0",
+                        "download_url": "https://example.com/python-3.11.1-linux-never-x64.tar.gz"
+                    }
+                ]
+            },
+            {
+                "version": "3.11.0",
+                "stable": true,
+                "release_url": "https://github.com/actions/python-versions/releases/tag/3.11.0",
+                "files": [
+                    {
+                        "filename": "python-3.11.0-darwin-x64.tar.gz",
+                        "arch": "x64",
+                        "platform": "darwin",
+                        "download_url": "https://example.com/python-3.11.0-darwin-x64.tar.gz"
+                    },
+                    {
+                        "filename": "python-3.11.0-linux-x64.tar.gz",
+                        "arch": "x64",
+                        "platform": "linux",
+                        "download_url": "https://example.com/python-3.11.0-linux-x64.tar.gz"
+                    }
+                ]
+            }
+        ]"#,
+    )
+    .unwrap();
+    let manifest = tool_cache::get_manifest_from_file(&path).unwrap();
+    fs::remove_file(path).unwrap();
+    assert_eq!(manifest.len(), 3);
+
+    let find = |spec, stable, platform| {
+        tool_cache::find_from_manifest(spec, stable, &manifest, Some("x64"), Some(platform)).map(
+            |release| {
+                assert_eq!(release.files.len(), 1);
+                release.files[0].filename.clone()
+            },
+        )
+    };
+    assert_eq!(
+        find("3.x", true, "linux").unwrap(),
+        "python-3.11.0-linux-x64.tar.gz"
+    );
+    assert_eq!(
+        find(">=3.12.0-alpha", false, "linux").unwrap(),
+        "python-3.12.0-alpha.1-linux-x64.tar.gz"
+    );
+    assert_eq!(
+        find("3.11", true, "darwin").unwrap(),
+        "python-3.11.0-darwin-x64.tar.gz"
+    );
+    assert_eq!(find(">=3.12.0-alpha", true, "linux"), None);
+    assert_eq!(find("3.11.1", true, "linux"), None);
+    assert_eq!(find("3.x", true, "win32"), None);
+}